@@ -0,0 +1,49 @@
+//! Cells of the e = 65537 exponentiation of cert_3's signature under cert_2's key, with halo2-rsa's
+//! `mul_mod` and with `CircuitParams::montgomery_exp`, alone and in the whole certificate circuit.
+//!
+//! `cargo bench --bench montgomery_exp`
+use halo2_base::halo2_proofs::{dev::MockProver, halo2curves::bn256::Fr};
+use halo2_zkcert_experimental::{
+    cert::parse_pem_certificate, modulus_instance, CertificateVerificationCircuit, CircuitParams, CircuitStats, RsaExpCircuit,
+};
+use num_bigint::BigUint;
+use x509_parser::{pem::parse_x509_pem, public_key::PublicKey};
+
+fn main() {
+    let parts = parse_pem_certificate(&std::fs::read("./certs/cert_3.pem").unwrap()).unwrap();
+    let issuer_pem = std::fs::read("./certs/cert_2.pem").unwrap();
+    let (_, issuer_pem) = parse_x509_pem(&issuer_pem).unwrap();
+    let issuer = issuer_pem.parse_x509().unwrap();
+    let n_big = match issuer.public_key().parsed().unwrap() {
+        PublicKey::RSA(key) => BigUint::from_bytes_be(key.modulus),
+        _ => panic!("cert_2 has an RSA key"),
+    };
+    let e = BigUint::from(65537u32);
+
+    let mut measured = vec![];
+    for montgomery_exp in [false, true] {
+        let params = CircuitParams { montgomery_exp, ..Default::default() };
+        params.set_current();
+
+        let circuit = RsaExpCircuit::<Fr>::new(n_big.clone(), parts.signature.clone(), e.clone(), params.clone());
+        let instances = vec![modulus_instance(&n_big, &params), modulus_instance(&circuit.result(), &params)];
+        MockProver::run(params.k, &circuit, instances).unwrap().assert_satisfied();
+        let exp = CircuitStats::last().unwrap();
+
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big.clone(), parts.signature.clone(), parts.tbs.clone(), params.clone());
+        MockProver::run(params.k, &circuit, circuit.public_inputs()).unwrap().assert_satisfied();
+        let cert = CircuitStats::last().unwrap();
+        measured.push((montgomery_exp, exp, cert));
+    }
+
+    for (montgomery_exp, exp, cert) in &measured {
+        let path = if *montgomery_exp { "montgomery" } else { "mul_mod" };
+        println!(
+            "{path:>10}: exponentiation {} advice / {} lookup cells, certificate {} advice / {} lookup cells",
+            exp.total_advice, exp.lookup_cells, cert.total_advice, cert.lookup_cells
+        );
+    }
+    let (mul_mod, montgomery) = (measured[0].1, measured[1].1);
+    let change = montgomery.total_advice as f64 / mul_mod.total_advice as f64 - 1.0;
+    println!("montgomery exponentiation advice cells: {:+.1}% against mul_mod", change * 100.0);
+}
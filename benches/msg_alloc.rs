@@ -0,0 +1,48 @@
+//! Heap bytes allocated when handing a 4KB message to `CertificateVerificationCircuit::new`.
+//!
+//! `cargo bench --bench msg_alloc`
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use halo2_zkcert_experimental::{CertificateVerificationCircuit, CircuitParams, Field};
+use num_bigint::BigUint;
+
+struct CountingAlloc;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+fn allocated_during<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    let out = f();
+    (out, ALLOCATED.load(Ordering::Relaxed) - before)
+}
+
+fn main() {
+    // A 4KB message needs 65 SHA256 blocks
+    let params = CircuitParams { msg_len: 4160, ..CircuitParams::rsa_2048_sha256() };
+    let msg = vec![0xa5u8; 4096];
+    let (n_big, sign_big) = (BigUint::from(1u8) << 2047usize, BigUint::from(2u8));
+
+    // Arguments are cloned up front so only the constructor's own allocations are counted
+    let (n, sign, p) = (n_big.clone(), sign_big.clone(), params.clone());
+    let (_, copied) = allocated_during(|| CertificateVerificationCircuit::<Field>::new(n, sign, msg.as_slice(), p));
+
+    let (_, moved) = allocated_during(|| CertificateVerificationCircuit::<Field>::new(n_big, sign_big, msg, params));
+
+    println!("4KB message: {moved} bytes allocated when moved, {copied} when copied from a slice");
+    assert!(moved < 4096 && copied >= 4096);
+}
@@ -0,0 +1,57 @@
+//! Per-proof time over 10 proofs of cert_3: keygen and prove for each proof, `prove` with a shared
+//! proving key, and a warm `ProvingContext` that also skips reloading the lookup tables.
+//!
+//! `cargo bench --bench proving_context`
+use std::time::{Duration, Instant};
+
+use halo2_base::halo2_proofs::{
+    halo2curves::bn256::{Bn256, Fr},
+    poly::kzg::commitment::ParamsKZG,
+};
+use halo2_zkcert_experimental::{cert::parse_pem_certificate, prover, CertificateVerificationCircuit, CircuitParams};
+use num_bigint::BigUint;
+use rand::rngs::OsRng;
+use x509_parser::{pem::parse_x509_pem, public_key::PublicKey};
+
+const NUM_PROOFS: u32 = 10;
+
+fn per_proof(mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    for _ in 0..NUM_PROOFS {
+        f();
+    }
+    start.elapsed() / NUM_PROOFS
+}
+
+fn main() {
+    let parts = parse_pem_certificate(&std::fs::read("./certs/cert_3.pem").unwrap()).unwrap();
+    let issuer_pem = std::fs::read("./certs/cert_2.pem").unwrap();
+    let (_, issuer_pem) = parse_x509_pem(&issuer_pem).unwrap();
+    let issuer = issuer_pem.parse_x509().unwrap();
+    let n_big = match issuer.public_key().parsed().unwrap() {
+        PublicKey::RSA(key) => BigUint::from_bytes_be(key.modulus),
+        _ => panic!("cert_2 has an RSA key"),
+    };
+    let new_circuit = || {
+        CertificateVerificationCircuit::<Fr>::new(n_big.clone(), parts.signature.clone(), parts.tbs.clone(), CircuitParams::default())
+    };
+    let params = ParamsKZG::<Bn256>::setup(CircuitParams::default().k, OsRng);
+
+    let cold = per_proof(|| {
+        let circuit = new_circuit();
+        let pk = prover::keygen(&params, &circuit).unwrap();
+        prover::prove(&params, &pk, &circuit).unwrap();
+    });
+
+    let pk = prover::keygen(&params, &new_circuit()).unwrap();
+    let shared_pk = per_proof(|| {
+        prover::prove(&params, &pk, &new_circuit()).unwrap();
+    });
+
+    let context = prover::ProvingContext::new(params.clone(), &new_circuit()).unwrap();
+    let warm = per_proof(|| {
+        context.prove(new_circuit()).unwrap();
+    });
+
+    println!("per proof over {NUM_PROOFS} proofs: keygen + prove {cold:?}, shared key {shared_pk:?}, ProvingContext {warm:?}");
+}
@@ -0,0 +1,232 @@
+//! One signature checked as a SHA-256 and as a SHA-512 PKCS#1 v1.5 encoding, to show the DigestInfo
+//! binds it to one algorithm.
+use halo2_base::{
+    gates::RangeInstructions,
+    utils::PrimeField,
+    halo2_proofs::{
+        plonk::{Circuit, ConstraintSystem, Error, Column, Instance},
+        circuit::{SimpleFloorPlanner, Layouter, Value, Cell},
+    },
+    SKIP_FIRST_PASS
+};
+use halo2_dynamic_sha256::Sha256DynamicConfig;
+use halo2_rsa::{RSAConfig, RSAInstructions, RSAPubE, RSAPublicKey, RSASignature};
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
+
+use crate::gadgets::unpack_le_bytes;
+use crate::{
+    algorithm_digest_info_prefix, assign_modpow, check_pkcs1v15_em, configure_gadgets, modulus_instance, CertificateVerificationCircuit,
+    CircuitParams, CircuitStats, DigestAlgorithm
+};
+
+/// Recovers the EM of a signature under a full `params.bits_len`-bit key with e = 65537 once, and
+/// checks it as the SHA-256 encoding of `msg` and as a SHA-512 encoding. Public inputs are `[n
+/// limbs, SHA-256 digest bytes, [sha256 result, sha512 result]]`, the results 0/1.
+///
+/// There is no SHA-512 gadget, so the SHA-512 check covers the padding, separator and DigestInfo
+/// prefix and skips the trailing 64 bytes: its result says whether the EM is a SHA-512 encoding of
+/// any digest. A digest the prover chose could clear it for a real SHA-512 signature, so no digest
+/// is taken, and a 0 soundly means the signature is no SHA-512 signature at all.
+pub struct DigestAgilityCircuit<F: PrimeField> {
+    n_big: BigUint,
+    sign_big: BigUint,
+    msg: Vec<u8>,
+    params: CircuitParams,
+    _f: std::marker::PhantomData<F>,
+}
+
+impl<F: PrimeField> DigestAgilityCircuit<F> {
+    pub fn new(n_big: BigUint, sign_big: BigUint, msg: Vec<u8>, params: CircuitParams) -> Self {
+        Self { n_big, sign_big, msg, params, _f: std::marker::PhantomData }
+    }
+
+    /// The recovered EM, `bits_len / 8` big-endian bytes.
+    fn em(&self) -> Vec<u8> {
+        let e = BigUint::from(CertificateVerificationCircuit::<F>::DEFAULT_E);
+        let em = self.sign_big.modpow(&e, &self.n_big).to_bytes_be();
+        let mut padded = vec![0; (self.params.bits_len / 8).saturating_sub(em.len())];
+        padded.extend(em);
+        padded
+    }
+
+    /// Whether the signature is the SHA-256 encoding of `msg` and a SHA-512 encoding of any digest,
+    /// computed natively.
+    pub fn results(&self) -> [bool; 2] {
+        let em = self.em();
+        let encodes = |algorithm: DigestAlgorithm, digest: &[u8]| {
+            let prefix = algorithm_digest_info_prefix(algorithm);
+            let tail = [prefix.as_slice(), digest].concat();
+            let separator = em.len() - tail.len() - 1;
+            em[..2] == [0x00, 0x01]
+                && em[2..separator].iter().all(|byte| *byte == 0xff)
+                && em[separator] == 0x00
+                && em[separator + 1..] == tail
+        };
+        [
+            encodes(DigestAlgorithm::Sha256, &Sha256::digest(&self.msg)),
+            encodes(DigestAlgorithm::Sha512, &em[em.len() - 64..]),
+        ]
+    }
+
+    pub fn public_inputs(&self) -> Vec<Vec<F>> {
+        let digest = Sha256::digest(&self.msg).iter().map(|byte| F::from(*byte as u64)).collect();
+        let results = self.results().iter().map(|valid| F::from(*valid as u64)).collect();
+        vec![modulus_instance(&self.n_big, &self.params), digest, results]
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DigestAgilityConfig<F: PrimeField> {
+    rsa_config: RSAConfig<F>,
+    sha256_config: Sha256DynamicConfig<F>,
+    n_instance: Column<Instance>,
+    hash_instance: Column<Instance>,
+    result_instance: Column<Instance>,
+}
+
+impl<F: PrimeField> Circuit<F> for DigestAgilityCircuit<F> {
+    type Config = DigestAgilityConfig<F>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        unimplemented!();
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let params = CircuitParams::current();
+        let (rsa_config, sha256_config) = configure_gadgets(meta, &params, 1);
+        let n_instance = meta.instance_column();
+        meta.enable_equality(n_instance);
+        let hash_instance = meta.instance_column();
+        meta.enable_equality(hash_instance);
+        let result_instance = meta.instance_column();
+        meta.enable_equality(result_instance);
+
+        Self::Config {
+            rsa_config,
+            sha256_config,
+            n_instance,
+            hash_instance,
+            result_instance
+        }
+    }
+
+    fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+        let biguint_config = config.rsa_config.biguint_config();
+        config.sha256_config.load(&mut layouter)?;
+        biguint_config.range().load_lookup_table(&mut layouter)?;
+        let mut first_pass = SKIP_FIRST_PASS;
+        let (n_cells, hash_cells, result_cells) = layouter.assign_region(
+            || "digest agility verifier",
+            |region| {
+                if first_pass {
+                    first_pass = false;
+                    return Ok((vec![], vec![], vec![]));
+                }
+
+                let mut aux = biguint_config.new_context(region);
+                let ctx = &mut aux;
+                let range = biguint_config.range();
+                let gate = range.gate();
+                let mut sha256_config = config.sha256_config.clone();
+
+                let e_fix = RSAPubE::Fix(BigUint::from(CertificateVerificationCircuit::<F>::DEFAULT_E));
+                let public_key = RSAPublicKey::new(Value::known(self.n_big.clone()), e_fix);
+                let public_key = config.rsa_config.assign_public_key(ctx, public_key)?;
+                let signature = RSASignature::new(Value::known(self.sign_big.clone()));
+                let signature = config.rsa_config.assign_signature(ctx, signature)?;
+                let em = assign_modpow(&config.rsa_config, ctx, &public_key, &signature, self.params.montgomery_exp)?;
+                let mut em_bytes = unpack_le_bytes(range, ctx, &em, biguint_config.limb_bits);
+                em_bytes.reverse();
+
+                let sha256_digest = sha256_config.digest(ctx, &self.msg, None)?.output_bytes;
+                // The EM's own trailing bytes as the digest, so only the structure is checked
+                let em_digest = &em_bytes[em_bytes.len() - 64..];
+                let results = [
+                    (DigestAlgorithm::Sha256, &sha256_digest[..]),
+                    (DigestAlgorithm::Sha512, em_digest),
+                ].map(|(algorithm, digest)| {
+                    check_pkcs1v15_em(gate, ctx, &em_bytes, &[], &algorithm_digest_info_prefix(algorithm), digest)
+                });
+
+                range.finalize(ctx);
+                CircuitStats {
+                    total_advice: ctx.total_advice,
+                    total_fixed: ctx.total_fixed,
+                    lookup_cells: ctx.cells_to_lookup.len(),
+                }.record();
+                let n_cells = public_key.n.limbs().iter().map(|v| v.cell()).collect::<Vec<Cell>>();
+                let hash_cells = sha256_digest.iter().map(|v| v.cell()).collect::<Vec<Cell>>();
+                let result_cells = results.iter().map(|v| v.cell()).collect::<Vec<Cell>>();
+                Ok((n_cells, hash_cells, result_cells))
+            },
+        )?;
+        for (i, cell) in n_cells.into_iter().enumerate() {
+            layouter.constrain_instance(cell, config.n_instance, i)?;
+        }
+        for (i, cell) in hash_cells.into_iter().enumerate() {
+            layouter.constrain_instance(cell, config.hash_instance, i)?;
+        }
+        for (i, cell) in result_cells.into_iter().enumerate() {
+            layouter.constrain_instance(cell, config.result_instance, i)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::load_fixture;
+    use halo2_base::halo2_proofs::{dev::MockProver, halo2curves::bn256::Fr};
+
+    #[test]
+    fn test_sha256_valid_sha512_invalid() {
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let circuit = DigestAgilityCircuit::<Fr>::new(n_big, sign_big, tbs, CircuitParams::default());
+        assert_eq!(circuit.results(), [true, false]);
+        assert_eq!(
+            algorithm_digest_info_prefix(DigestAlgorithm::Sha512)[..],
+            [0x30, 0x51, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x03, 0x05, 0x00, 0x04, 0x40]
+        );
+
+        circuit.params.set_current();
+        let public_inputs = circuit.public_inputs();
+        assert_eq!(public_inputs[2], vec![Fr::one(), Fr::zero()]);
+        let prover = MockProver::run(circuit.params.k, &circuit, public_inputs.clone()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // Claiming the signature also verifies under SHA-512
+        let mut both_valid = public_inputs;
+        both_valid[2][1] = Fr::one();
+        let prover = MockProver::run(circuit.params.k, &circuit, both_valid).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_sha512_result_ignores_digest() {
+        use rsa::{Hash, PaddingScheme, PublicKeyParts, RsaPrivateKey};
+        use sha2::Sha512;
+
+        // A SHA-512 signature over a digest other than that of `msg`
+        let msg = b"a document signed with SHA-512".to_vec();
+        let key = RsaPrivateKey::new(&mut rand::thread_rng(), 2048).unwrap();
+        let wrong_digest = Sha512::digest(b"another document");
+        let signature = key.sign(PaddingScheme::new_pkcs1v15_sign(Some(Hash::SHA2_512)), &wrong_digest).unwrap();
+        let n_big = BigUint::from_bytes_be(&key.n().to_bytes_be());
+        let circuit = DigestAgilityCircuit::<Fr>::new(n_big, BigUint::from_bytes_be(&signature), msg, CircuitParams::default());
+        assert_eq!(circuit.results(), [false, true]);
+
+        circuit.params.set_current();
+        let public_inputs = circuit.public_inputs();
+        let prover = MockProver::run(circuit.params.k, &circuit, public_inputs.clone()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // No choice of digest can claim it isn't a SHA-512 signature
+        let mut neither_valid = public_inputs;
+        neither_valid[2][1] = Fr::zero();
+        let prover = MockProver::run(circuit.params.k, &circuit, neither_valid).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
@@ -0,0 +1,49 @@
+//! PDF-style `ByteRange`s: a signed message made of several segments of a larger document.
+use crate::CertError;
+
+/// `document[start..start + len]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    pub start: usize,
+    pub len: usize,
+}
+
+impl ByteRange {
+    pub fn new(start: usize, len: usize) -> Self {
+        Self { start, len }
+    }
+
+    pub fn end(&self) -> usize {
+        self.start + self.len
+    }
+}
+
+/// Concatenates the `ranges` of `document`, which must be in bounds, ascending and disjoint.
+pub fn concat(document: &[u8], ranges: &[ByteRange]) -> Result<Vec<u8>, CertError> {
+    let mut msg = Vec::with_capacity(ranges.iter().map(|range| range.len).sum());
+    let mut prev_end = 0;
+    for range in ranges {
+        if range.start < prev_end || range.start.checked_add(range.len).map_or(true, |end| end > document.len()) {
+            return Err(CertError::MalformedStructure("byte ranges out of bounds or overlapping"));
+        }
+        msg.extend_from_slice(&document[range.start..range.end()]);
+        prev_end = range.end();
+    }
+    Ok(msg)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_concat() {
+        let document = b"signed %%placeholder%% also signed";
+        let ranges = [ByteRange::new(0, 7), ByteRange::new(22, 12)];
+        assert_eq!(concat(document, &ranges).unwrap(), b"signed  also signed");
+
+        assert!(concat(document, &[ByteRange::new(30, 10)]).is_err());
+        assert!(concat(document, &[ByteRange::new(5, 5), ByteRange::new(8, 2)]).is_err());
+        assert!(concat(document, &[ByteRange::new(usize::MAX, 2)]).is_err());
+    }
+}
@@ -0,0 +1,439 @@
+//! Certificate-level parsing: from DER to the bytes and integers the circuit consumes.
+use std::ops::Range;
+
+use num_bigint::BigUint;
+use x509_parser::{
+    certificate::X509Certificate, pem::parse_x509_pem, prelude::FromDer, public_key::PublicKey, x509::SubjectPublicKeyInfo,
+};
+
+use crate::der::{self, children, oid_to_string, read_tlv, TAG_BIT_STRING, TAG_OID, TAG_SEQUENCE};
+use crate::CertError;
+
+/// The only signature algorithm the circuit verifies.
+pub const SHA256_WITH_RSA_ENCRYPTION: &str = "1.2.840.113549.1.1.11";
+
+/// Common certificate signature algorithms, `(oid, name)`.
+pub const SIGNATURE_ALGORITHMS: &[(&str, &str)] = &[
+    ("1.2.840.113549.1.1.5", "sha1WithRSAEncryption"),
+    (SHA256_WITH_RSA_ENCRYPTION, "sha256WithRSAEncryption"),
+    ("1.2.840.113549.1.1.12", "sha384WithRSAEncryption"),
+    ("1.2.840.113549.1.1.13", "sha512WithRSAEncryption"),
+    ("1.2.840.113549.1.1.10", "rsassa-pss"),
+    ("1.2.840.10045.4.3.2", "ecdsa-with-SHA256"),
+    ("1.2.840.10045.4.3.3", "ecdsa-with-SHA384"),
+    ("1.2.840.10045.4.3.4", "ecdsa-with-SHA512"),
+    ("1.3.101.112", "Ed25519"),
+];
+
+/// Named curves of EC subject keys, `(oid, name)`.
+pub const NAMED_CURVES: &[(&str, &str)] = &[
+    ("1.2.840.10045.3.1.7", "P-256"),
+    ("1.3.132.0.34", "P-384"),
+    ("1.3.132.0.35", "P-521"),
+    ("1.3.132.0.10", "secp256k1"),
+    ("1.3.36.3.3.2.8.1.1.7", "brainpoolP256r1"),
+    ("1.3.36.3.3.2.8.1.1.11", "brainpoolP384r1"),
+    ("1.3.36.3.3.2.8.1.1.13", "brainpoolP512r1"),
+];
+
+/// The only subject key algorithm the circuit uses.
+pub const RSA_ENCRYPTION: &str = "1.2.840.113549.1.1.1";
+
+/// Common subject key algorithms, `(oid, name)`.
+pub const KEY_ALGORITHMS: &[(&str, &str)] = &[
+    (RSA_ENCRYPTION, "rsaEncryption"),
+    ("1.2.840.113549.1.1.10", "rsassa-pss"),
+    ("1.2.840.10045.2.1", "id-ecPublicKey"),
+    ("1.3.101.112", "Ed25519"),
+];
+
+/// Digest algorithms of PKCS#1 DigestInfos, `(oid, name)`.
+pub const DIGEST_ALGORITHMS: &[(&str, &str)] = &[
+    ("1.3.14.3.2.26", "sha1"),
+    ("2.16.840.1.101.3.4.2.4", "sha224"),
+    ("2.16.840.1.101.3.4.2.1", "sha256"),
+    ("2.16.840.1.101.3.4.2.2", "sha384"),
+    ("2.16.840.1.101.3.4.2.3", "sha512"),
+];
+
+pub(crate) fn lookup(table: &[(&str, &'static str)], oid: &str) -> &'static str {
+    table.iter().find(|(known, _)| *known == oid).map_or("unknown", |(_, name)| name)
+}
+
+/// OID of the digest algorithm named by the DER DigestInfo at the start of `digest_info`.
+pub fn digest_info_algorithm(digest_info: &[u8]) -> Option<String> {
+    let outer = read_tlv(digest_info, 0).filter(|tlv| tlv.tag == TAG_SEQUENCE)?;
+    let algorithm = children(digest_info, &outer)?.first().copied().filter(|tlv| tlv.tag == TAG_SEQUENCE)?;
+    let oid = children(digest_info, &algorithm)?.first().copied().filter(|tlv| tlv.tag == TAG_OID)?;
+    oid_to_string(&digest_info[oid.content()])
+}
+
+/// The parts of a DER `Certificate` the circuit consumes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertParts {
+    /// The signed `tbsCertificate` TLV.
+    pub tbs: Vec<u8>,
+    pub signature: BigUint,
+}
+
+/// `NotDer` if the `Certificate` or `tbsCertificate` header of `der` has an indefinite length, the
+/// BER encoding some non-compliant issuers emit. Their raw bytes can still go to the circuit.
+fn check_definite_lengths(der: &[u8]) -> Result<(), CertError> {
+    if der::is_indefinite_length(der, 0) {
+        return Err(CertError::NotDer { offset: 0 });
+    }
+    match read_tlv(der, 0) {
+        Some(outer) if der::is_indefinite_length(der, outer.content().start) => {
+            Err(CertError::NotDer { offset: outer.content().start })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Splits a sha256WithRSAEncryption `Certificate` into its TBS and signature. Other signature
+/// algorithms are reported as `UnsupportedSignatureAlgorithm` so callers can route them elsewhere.
+pub fn parse_der_certificate(der: &[u8]) -> Result<CertParts, CertError> {
+    check_definite_lengths(der)?;
+    let outer = read_tlv(der, 0)
+        .filter(|tlv| tlv.tag == TAG_SEQUENCE && tlv.end() == der.len())
+        .ok_or(CertError::MalformedStructure("certificate is not a DER SEQUENCE"))?;
+    let fields = children(der, &outer).ok_or(CertError::MalformedStructure("certificate fields don't parse"))?;
+    // Exactly three fields, so nothing the signature doesn't cover rides along
+    let (tbs, algorithm, value) = match fields.as_slice() {
+        [tbs, algorithm, value] => (tbs, algorithm, value),
+        _ => return Err(CertError::MalformedStructure("certificate is not SEQUENCE { tbsCertificate, signatureAlgorithm, signatureValue }")),
+    };
+    if tbs.tag != TAG_SEQUENCE || algorithm.tag != TAG_SEQUENCE || value.tag != TAG_BIT_STRING {
+        return Err(CertError::MalformedStructure("unexpected certificate field tags"));
+    }
+
+    let oid = read_tlv(der, algorithm.content().start)
+        .filter(|tlv| tlv.tag == TAG_OID && tlv.end() <= algorithm.end())
+        .and_then(|tlv| oid_to_string(&der[tlv.content()]))
+        .ok_or(CertError::MalformedStructure("malformed signatureAlgorithm"))?;
+    if oid != SHA256_WITH_RSA_ENCRYPTION {
+        let name = lookup(SIGNATURE_ALGORITHMS, &oid);
+        return Err(CertError::UnsupportedSignatureAlgorithm { oid, name });
+    }
+
+    // RSA signatures are whole bytes, so no unused bits
+    let value = &der[value.content()];
+    if value.first() != Some(&0) {
+        return Err(CertError::MalformedStructure("signatureValue has unused bits"));
+    }
+    Ok(CertParts { tbs: der[tbs.start..tbs.end()].to_vec(), signature: BigUint::from_bytes_be(&value[1..]) })
+}
+
+/// Human-readable fields of a certificate, owned so they outlive the DER.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertInfo {
+    /// RFC 4514-style, e.g. `C=US, O=Example, CN=example.com`.
+    pub subject: String,
+    pub issuer: String,
+    /// Unix timestamps of the validity period.
+    pub not_before: i64,
+    pub not_after: i64,
+    /// `(dotted OID, critical)` of each extension, in certificate order.
+    pub extensions: Vec<(String, bool)>,
+}
+
+/// `parse_der_certificate`, plus the fields of `der` worth showing next to the circuit inputs.
+pub fn parse_der_certificate_with_info(der: &[u8]) -> Result<(CertParts, CertInfo), CertError> {
+    let parts = parse_der_certificate(der)?;
+    let (_, cert) = X509Certificate::from_der(der).map_err(|_| CertError::MalformedStructure("x509-parser rejected the certificate"))?;
+    let info = CertInfo {
+        subject: cert.subject().to_string(),
+        issuer: cert.issuer().to_string(),
+        not_before: cert.validity().not_before.timestamp(),
+        not_after: cert.validity().not_after.timestamp(),
+        extensions: cert.extensions().iter().map(|ext| (ext.oid.to_id_string(), ext.critical)).collect(),
+    };
+    Ok((parts, info))
+}
+
+/// `parse_der_certificate` of a PEM `CERTIFICATE` block.
+pub fn parse_pem_certificate(pem: &[u8]) -> Result<CertParts, CertError> {
+    let (_, pem) = parse_x509_pem(pem).map_err(|_| CertError::MalformedStructure("not PEM"))?;
+    if pem.label != "CERTIFICATE" {
+        return Err(CertError::MalformedStructure("PEM block is not a CERTIFICATE"));
+    }
+    parse_der_certificate(&pem.contents)
+}
+
+/// Modulus of the key in a DER SubjectPublicKeyInfo, after checking its algorithm is
+/// rsaEncryption: the bytes of any other key would otherwise be misread as a modulus. In-circuit,
+/// `gadgets::assign_spki_modulus` constrains the same AlgorithmIdentifier and the same zero
+/// unused-bits count of the subjectPublicKey BIT STRING.
+pub fn spki_rsa_modulus(spki: &[u8]) -> Result<BigUint, CertError> {
+    spki_rsa_public_key(spki).map(|(n, _)| n)
+}
+
+/// `(modulus, public exponent)` of the key in a DER SubjectPublicKeyInfo, checked as in
+/// `spki_rsa_modulus`.
+pub fn spki_rsa_public_key(spki: &[u8]) -> Result<(BigUint, BigUint), CertError> {
+    // Checked on the raw DER, as x509-parser rejects some nonzero counts as malformed DER. Keys are
+    // whole bytes, so a nonzero count means the key was corrupted
+    let key = read_tlv(spki, 0)
+        .and_then(|outer| children(spki, &outer))
+        .and_then(|fields| fields.get(1).copied())
+        .filter(|key| key.tag == TAG_BIT_STRING && key.len > 0);
+    if key.map_or(false, |key| spki[key.content().start] != 0) {
+        return Err(CertError::MalformedPublicKey("subjectPublicKey BIT STRING has unused bits"));
+    }
+    let (_, parsed) = SubjectPublicKeyInfo::from_der(spki)
+        .map_err(|_| CertError::MalformedStructure("not a SubjectPublicKeyInfo"))?;
+    let oid = parsed.algorithm.algorithm.to_id_string();
+    if oid != RSA_ENCRYPTION {
+        let name = lookup(KEY_ALGORITHMS, &oid);
+        return Err(CertError::UnsupportedKeyAlgorithm { oid, name });
+    }
+    match parsed.parsed() {
+        Ok(PublicKey::RSA(key)) => Ok((BigUint::from_bytes_be(key.modulus), BigUint::from_bytes_be(key.exponent))),
+        _ => Err(CertError::MalformedPublicKey("not an RSAPublicKey")),
+    }
+}
+
+/// `spki_rsa_modulus` of the subject key of a DER certificate, i.e. the modulus it verifies the
+/// certificates it issued with.
+pub fn issuer_modulus(der: &[u8]) -> Result<BigUint, CertError> {
+    let (tbs, _) = signed_region(der)?;
+    let spki = der::subject_public_key_info(&tbs)
+        .ok_or(CertError::MalformedStructure("missing subjectPublicKeyInfo"))?;
+    spki_rsa_modulus(&tbs[spki.start..spki.end()])
+}
+
+/// Named curve of an EC subject key in `tbs`, `None` if the key isn't an EC key on a listed curve.
+pub fn subject_named_curve(tbs: &[u8]) -> Option<&'static str> {
+    let spki = der::subject_public_key_info(tbs)?;
+    let algorithm = read_tlv(tbs, spki.content().start).filter(|tlv| tlv.tag == TAG_SEQUENCE)?;
+    let params = children(tbs, &algorithm)?;
+    match params.as_slice() {
+        [key_type, curve] if key_type.tag == TAG_OID && curve.tag == TAG_OID => {
+            if oid_to_string(&tbs[key_type.content()])? != "1.2.840.10045.2.1" {
+                return None;
+            }
+            let name = lookup(NAMED_CURVES, &oid_to_string(&tbs[curve.content()])?);
+            (name != "unknown").then_some(name)
+        }
+        _ => None,
+    }
+}
+
+/// Returns the bytes covered by the signature of a DER `Certificate`, and their range in `der`.
+///
+/// For X.509 the signature is over the whole `tbsCertificate` TLV, header included, exactly as
+/// encoded: the first element of the outer `Certificate` SEQUENCE. Hashing anything else (the
+/// whole certificate, or the TBS contents without its header) won't match the signature.
+pub fn signed_region(der: &[u8]) -> Result<(Vec<u8>, Range<usize>), CertError> {
+    check_definite_lengths(der)?;
+    let outer = read_tlv(der, 0)
+        .filter(|tlv| tlv.tag == TAG_SEQUENCE)
+        .ok_or(CertError::MalformedStructure("certificate is not a DER SEQUENCE"))?;
+    let tbs = read_tlv(der, outer.content().start)
+        .filter(|tlv| tlv.tag == TAG_SEQUENCE && tlv.end() <= outer.end())
+        .ok_or(CertError::MalformedStructure("missing tbsCertificate"))?;
+    let range = tbs.start..tbs.end();
+    Ok((der[range.clone()].to_vec(), range))
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use crate::test::load_fixture;
+    use crate::test_utils::fixture_path;
+    use crate::{CertificateVerificationCircuit, CircuitParams};
+    use halo2_base::halo2_proofs::{dev::MockProver, halo2curves::bn256::Fr};
+    use num_bigint::BigUint;
+    use proptest::{collection::vec, prelude::*};
+    use sha2::{Digest, Sha256};
+
+    pub(crate) fn der_from_pem(path: &str) -> Vec<u8> {
+        let pem = std::fs::read(fixture_path(path)).expect("Failed to read PEM file");
+        parse_x509_pem(&pem).expect("Failed to parse PEM").1.contents
+    }
+
+    #[test]
+    fn test_signed_region_matches_signature() {
+        let der = der_from_pem("./certs/cert_3.pem");
+        let (region, range) = signed_region(&der).unwrap();
+        assert_eq!(range, 4..1203);
+        assert_eq!(region, der[range].to_vec());
+
+        // The digest recovered from the signature is the trailing 32 bytes of the PKCS#1 v1.5 EM
+        let (n_big, sign_big, _) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let em = sign_big.modpow(&BigUint::from(65537u32), &n_big).to_bytes_be();
+        assert_eq!(&em[em.len() - 32..], Sha256::digest(&region).as_slice());
+    }
+
+    #[test]
+    fn test_ber_indefinite_length() {
+        // test_ca's TBS re-encoded as `30 80 .. 00 00` in a `30 80 .. 00 00` certificate, signed
+        // with test_ca.key
+        let ber = std::fs::read(fixture_path("./certs/test_ca_ber.der")).unwrap();
+        assert_eq!(parse_der_certificate(&ber), Err(CertError::NotDer { offset: 0 }));
+        assert_eq!(signed_region(&ber).map(|(_, range)| range), Err(CertError::NotDer { offset: 0 }));
+
+        // The raw TBS still verifies: it ends before the signatureAlgorithm (15 bytes), the
+        // signatureValue (261) and the end-of-contents octets
+        let tbs = &ber[2..ber.len() - 15 - 261 - 2];
+        assert_eq!(&tbs[..2], &[0x30, 0x80]);
+        let (n_big, _, _) = load_fixture("./certs/test_ca.pem", "./certs/test_ca.pem");
+        let sign_big = BigUint::from_bytes_be(&ber[ber.len() - 2 - 256..ber.len() - 2]);
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big, sign_big, tbs, CircuitParams::default());
+        assert!(circuit.signature_is_valid());
+        circuit.params.set_current();
+        let prover = MockProver::run(circuit.params.k, &circuit, circuit.public_inputs()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_issuer_modulus() {
+        let (n_big, _, _) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        assert_eq!(issuer_modulus(&der_from_pem("./certs/cert_2.pem")), Ok(n_big));
+
+        let err = issuer_modulus(&der_from_pem("./certs/p256_self_signed.pem")).unwrap_err();
+        assert_eq!(
+            err,
+            CertError::UnsupportedKeyAlgorithm { oid: "1.2.840.10045.2.1".to_string(), name: "id-ecPublicKey" }
+        );
+        assert_eq!(err.to_string(), "key algorithm is id-ecPublicKey (1.2.840.10045.2.1), not rsaEncryption");
+    }
+
+    #[test]
+    fn test_spki_unused_bits_rejected() {
+        let (tbs, _) = signed_region(&der_from_pem("./certs/cert_2.pem")).unwrap();
+        let spki = der::subject_public_key_info(&tbs).unwrap();
+        let mut spki = tbs[spki.start..spki.end()].to_vec();
+        assert!(spki_rsa_modulus(&spki).is_ok());
+
+        let key = children(&spki, &read_tlv(&spki, 0).unwrap()).unwrap()[1];
+        assert_eq!(spki[key.content().start], 0);
+        spki[key.content().start] = 0x01;
+        let err = spki_rsa_modulus(&spki).unwrap_err();
+        assert_eq!(err, CertError::MalformedPublicKey("subjectPublicKey BIT STRING has unused bits"));
+        assert_eq!(err.to_string(), "malformed public key: subjectPublicKey BIT STRING has unused bits");
+    }
+
+    #[test]
+    fn test_parse_der_certificate() {
+        let der = der_from_pem("./certs/cert_3.pem");
+        let parts = parse_der_certificate(&der).unwrap();
+        let (_, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        assert_eq!(parts, CertParts { tbs, signature: sign_big });
+
+        // cert_1 is self-signed with SHA-1
+        assert_eq!(
+            parse_der_certificate(&der_from_pem("./certs/cert_1.pem")),
+            Err(CertError::UnsupportedSignatureAlgorithm { oid: "1.2.840.113549.1.1.5".to_string(), name: "sha1WithRSAEncryption" })
+        );
+    }
+
+    #[test]
+    fn test_certificate_field_count() {
+        let der = der_from_pem("./certs/cert_3.pem");
+        let fields = children(&der, &read_tlv(&der, 0).unwrap()).unwrap();
+        let [tbs, algorithm, value] = [0, 1, 2].map(|i| &der[fields[i].start..fields[i].end()]);
+        // Every combination below is between 256 and 65535 bytes long
+        let sequence = |fields: &[&[u8]]| {
+            let contents = fields.concat();
+            [&[TAG_SEQUENCE, 0x82][..], &(contents.len() as u16).to_be_bytes()[..], &contents[..]].concat()
+        };
+        assert!(parse_der_certificate(&sequence(&[tbs, algorithm, value])).is_ok());
+
+        let wrong_count = CertError::MalformedStructure("certificate is not SEQUENCE { tbsCertificate, signatureAlgorithm, signatureValue }");
+        assert_eq!(parse_der_certificate(&sequence(&[tbs, algorithm])), Err(wrong_count.clone()));
+        assert_eq!(parse_der_certificate(&sequence(&[tbs, algorithm, value, value])), Err(wrong_count));
+        assert!(parse_der_certificate(&sequence(&[algorithm, tbs, value])).is_err());
+    }
+
+    #[test]
+    fn test_parse_der_certificate_with_info() {
+        let der = der_from_pem("./certs/cert_3.pem");
+        let (parts, info) = parse_der_certificate_with_info(&der).unwrap();
+        assert_eq!(parts, parse_der_certificate(&der).unwrap());
+        assert!(info.subject.contains("DocuSign, Inc."));
+        assert!(info.issuer.contains("Entrust Class 3 Client CA - SHA256"));
+        assert!(info.not_before < info.not_after);
+        assert!(info.extensions.iter().any(|(oid, _)| oid == "2.5.29.15"));
+    }
+
+    #[test]
+    fn test_ecdsa_certificate_is_unsupported() {
+        let der = der_from_pem("./certs/p256_self_signed.pem");
+        assert_eq!(
+            parse_der_certificate(&der),
+            Err(CertError::UnsupportedSignatureAlgorithm { oid: "1.2.840.10045.4.3.2".to_string(), name: "ecdsa-with-SHA256" })
+        );
+        let (tbs, _) = signed_region(&der).unwrap();
+        assert_eq!(subject_named_curve(&tbs), Some("P-256"));
+        assert_eq!(subject_named_curve(&signed_region(&der_from_pem("./certs/cert_3.pem")).unwrap().0), None);
+    }
+
+    #[test]
+    fn test_parse_pem_certificate() {
+        let pem = std::fs::read(fixture_path("./certs/cert_3.pem")).unwrap();
+        assert_eq!(parse_pem_certificate(&pem), parse_der_certificate(&der_from_pem("./certs/cert_3.pem")));
+        let key = std::fs::read(fixture_path("./certs/cert_2_pubkey.pem")).unwrap();
+        assert_eq!(parse_pem_certificate(&key), Err(CertError::MalformedStructure("PEM block is not a CERTIFICATE")));
+    }
+
+    /// Seed corpus for the fuzz tests below.
+    const FIXTURES: [&str; 6] = [
+        "./certs/cert_1.pem",
+        "./certs/cert_2.pem",
+        "./certs/cert_3.pem",
+        "./certs/san_leaf.pem",
+        "./certs/test_ca_4096.pem",
+        "./certs/p256_self_signed.pem",
+    ];
+
+    /// Everything that walks untrusted DER, none of which may panic.
+    fn parse_everything(der: &[u8]) {
+        let _ = parse_der_certificate(der);
+        let _ = crate::pkcs7::parse_signed_data(der);
+        if let Ok((tbs, _)) = signed_region(der) {
+            let _ = der::extensions(&tbs);
+            let _ = der::subject_public_key_info(&tbs);
+            let _ = subject_named_curve(&tbs);
+            if let Some(san) = der::find_extension(&tbs, der::OID_SUBJECT_ALT_NAME) {
+                let _ = der::san_dns_names(&tbs[san]);
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn fuzz_mutated_fixtures(
+            fixture in 0..FIXTURES.len(),
+            edits in vec((any::<usize>(), any::<u8>()), 0..8),
+            truncate in any::<usize>(),
+        ) {
+            let mut der = der_from_pem(FIXTURES[fixture]);
+            for (pos, byte) in edits {
+                let len = der.len();
+                der[pos % len] = byte;
+            }
+            der.truncate(truncate % (der.len() + 1));
+            parse_everything(&der);
+        }
+
+        #[test]
+        fn fuzz_random_der(bytes in vec(any::<u8>(), 0..512)) {
+            parse_everything(&bytes);
+            prop_assert!(parse_der_certificate(&bytes).is_err());
+        }
+
+        #[test]
+        fn fuzz_random_pem(body in "[A-Za-z0-9+/=\n]{0,512}", label in "(CERTIFICATE|PUBLIC KEY|X)") {
+            let pem = format!("-----BEGIN {label}-----\n{body}\n-----END {label}-----\n");
+            prop_assert!(parse_pem_certificate(pem.as_bytes()).is_err());
+            prop_assert!(parse_pem_certificate(body.as_bytes()).is_err());
+        }
+    }
+
+    #[test]
+    fn test_signed_region_rejects_garbage() {
+        assert!(signed_region(&[0x02, 0x01, 0x00]).is_err());
+        assert!(signed_region(&[0x30, 0x03, 0x02, 0x01, 0x00]).is_err());
+    }
+}
@@ -0,0 +1,858 @@
+//! Verification of a whole certificate chain in one circuit.
+//!
+//! Certificates are ordered leaf first, each signed by the key of the next one. The key that
+//! verifies a certificate is constrained to the SubjectPublicKeyInfo inside its issuer's hashed
+//! TBS, so only the last certificate's issuer key (the anchor) is public.
+use halo2_base::{
+    gates::{GateInstructions, RangeInstructions},
+    utils::PrimeField,
+    AssignedValue,
+    Context,
+    QuantumCell::{Constant, Existing},
+    halo2_proofs::{
+        plonk::{Circuit, ConstraintSystem, Error, Column, Instance},
+        circuit::{SimpleFloorPlanner, Layouter, Value, Cell},
+    },
+    SKIP_FIRST_PASS
+};
+use halo2_dynamic_sha256::Sha256DynamicConfig;
+use halo2_rsa::{AssignedRSAPublicKey, RSAConfig, RSAInstructions, RSAPubE, RSAPublicKey, RSASignature};
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
+use x509_parser::{
+    certificate::{TbsCertificate, X509Certificate},
+    extensions::ParsedExtension,
+    prelude::FromDer,
+};
+
+use crate::cert::{self, parse_der_certificate, spki_rsa_modulus};
+use crate::gadgets::{assign_spki_modulus, select_window};
+use crate::{configure_gadgets, der, modulus_instance, verify_pkcs1v15_signature};
+use crate::{CertError, CertificateVerificationCircuit, ChainLinkReport, CircuitParams, CircuitStats};
+
+/// Orders an unordered bundle of DER certificates (leaf, intermediates and optionally the root)
+/// into a chain, off-circuit.
+///
+/// A certificate issued another when its subject is the other's issuer and, when both are
+/// present, its subjectKeyIdentifier is the other's authorityKeyIdentifier.
+#[derive(Debug, Clone, Default)]
+pub struct CertChainBuilder {
+    certs: Vec<Vec<u8>>,
+}
+
+/// Certificates ordered leaf first, each issued by the next.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertChain {
+    pub certs: Vec<Vec<u8>>,
+}
+
+struct ChainEntry<'a> {
+    cert: X509Certificate<'a>,
+}
+
+impl<'a> ChainEntry<'a> {
+    fn parse(der: &'a [u8]) -> Result<Self, CertError> {
+        let (_, cert) = X509Certificate::from_der(der).map_err(|_| CertError::MalformedStructure("certificate doesn't parse"))?;
+        Ok(Self { cert })
+    }
+
+    fn is_self_signed(&self) -> bool {
+        self.cert.subject().as_raw() == self.cert.issuer().as_raw()
+    }
+
+    fn issued(&self, child: &ChainEntry<'_>) -> bool {
+        issued(&self.cert.tbs_certificate, &child.cert.tbs_certificate)
+    }
+}
+
+/// Whether `issuer`'s subject is `child`'s issuer and, when both are present, its
+/// subjectKeyIdentifier is `child`'s authorityKeyIdentifier.
+fn issued(issuer: &TbsCertificate<'_>, child: &TbsCertificate<'_>) -> bool {
+    let key_ids_match = match (key_ids(issuer).0, key_ids(child).1) {
+        (Some(ski), Some(aki)) => ski == aki,
+        _ => true,
+    };
+    issuer.subject().as_raw() == child.issuer().as_raw() && key_ids_match
+}
+
+/// The subjectKeyIdentifier and authorityKeyIdentifier keyIdentifier of `tbs`, if present.
+fn key_ids<'a>(tbs: &TbsCertificate<'a>) -> (Option<&'a [u8]>, Option<&'a [u8]>) {
+    let (mut subject_key_id, mut authority_key_id) = (None, None);
+    for ext in tbs.extensions() {
+        match ext.parsed_extension() {
+            ParsedExtension::SubjectKeyIdentifier(ski) => subject_key_id = Some(ski.0),
+            ParsedExtension::AuthorityKeyIdentifier(aki) => authority_key_id = aki.key_identifier.as_ref().map(|id| id.0),
+            _ => {}
+        }
+    }
+    (subject_key_id, authority_key_id)
+}
+
+impl CertChainBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_der(mut self, der: Vec<u8>) -> Self {
+        self.certs.push(der);
+        self
+    }
+
+    /// Orders the bundle, which must form exactly one chain.
+    pub fn build(self) -> Result<CertChain, CertError> {
+        let entries = self.certs.iter().map(|der| ChainEntry::parse(der)).collect::<Result<Vec<_>, _>>()?;
+        // The leaf issued nothing else in the bundle. A root that issued nothing either means the
+        // bundle has a gap, which the walk below reports against the real leaf.
+        let leaf = (0..entries.len())
+            .filter(|&i| !entries.iter().enumerate().any(|(j, other)| i != j && entries[i].issued(other)))
+            .min_by_key(|&i| entries[i].is_self_signed())
+            .ok_or(CertError::MalformedStructure("bundle has no leaf certificate"))?;
+
+        let mut order = vec![leaf];
+        let mut current = leaf;
+        while !entries[current].is_self_signed() {
+            let issuer = (0..entries.len()).find(|&i| !order.contains(&i) && entries[i].issued(&entries[current]));
+            match issuer {
+                Some(issuer) => {
+                    order.push(issuer);
+                    current = issuer;
+                }
+                None => break,
+            }
+        }
+        if order.len() != entries.len() {
+            return Err(CertError::IncompleteChain { issuer: entries[current].cert.issuer().to_string() });
+        }
+        // Release the borrows of `self.certs` before moving out of it
+        drop(entries);
+
+        let mut certs = self.certs.into_iter().map(Some).collect::<Vec<_>>();
+        Ok(CertChain { certs: order.into_iter().map(|i| certs[i].take().unwrap()).collect() })
+    }
+}
+
+impl CertChain {
+    /// Inputs for `ChainCircuit::new`: the `(tbs, signature)` links and the anchor modulus.
+    ///
+    /// The last certificate is the trust anchor, so by default only its key is used. With
+    /// `verify_root`, it must be self-signed and is kept as the final link, for
+    /// `with_self_signed_root`.
+    ///
+    /// There is no ECDSA circuit, so mixed chains, such as an RSA leaf under an ECDSA intermediate,
+    /// fail with the `UnsupportedSignatureAlgorithm` of their first ECDSA-signed link (see
+    /// `signature_algorithms`) rather than being verified link by link.
+    pub fn circuit_inputs(&self, verify_root: bool) -> Result<(Vec<(Vec<u8>, BigUint)>, BigUint), CertError> {
+        let root = ChainEntry::parse(self.certs.last().ok_or(CertError::MalformedStructure("empty chain"))?)?;
+        let links = match verify_root {
+            true if !root.is_self_signed() => {
+                return Err(CertError::IncompleteChain { issuer: root.cert.issuer().to_string() })
+            }
+            true => &self.certs[..],
+            false => &self.certs[..self.certs.len() - 1],
+        };
+        let links = links
+            .iter()
+            .map(|der| parse_der_certificate(der).map(|parts| (parts.tbs, parts.signature)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let anchor_n = spki_rsa_modulus(root.cert.public_key().raw)?;
+        Ok((links, anchor_n))
+    }
+
+    /// Name of the signatureAlgorithm of each certificate, leaf first, to tell chains the circuit
+    /// verifies (all `sha256WithRSAEncryption`) from mixed ones. `"unknown"` for OIDs missing from
+    /// `cert::SIGNATURE_ALGORITHMS`.
+    pub fn signature_algorithms(&self) -> Result<Vec<&'static str>, CertError> {
+        self.certs
+            .iter()
+            .map(|der| {
+                let entry = ChainEntry::parse(der)?;
+                let oid = entry.cert.signature_algorithm.algorithm.to_id_string();
+                Ok(cert::lookup(cert::SIGNATURE_ALGORITHMS, &oid))
+            })
+            .collect()
+    }
+}
+
+/// Verifies `N` certificates, leaf first. Public inputs are `[anchor modulus limbs, leaf TBS digest]`.
+///
+/// Every issuer key must be a `params.bits_len`-bit RSA key with e = 65537. Each link costs a
+/// SHA256 slot of `msg_len` bytes, and locating an issuer SPKI selects `bits_len / 8 + 38` bytes
+/// out of its TBS, so chains need a larger `k` than a single certificate.
+pub struct ChainCircuit<F: PrimeField, const N: usize> {
+    /// `(tbs, signature)` of each certificate.
+    certs: Vec<(Vec<u8>, BigUint)>,
+    anchor_n: BigUint,
+    self_signed_root: bool,
+    path_len_check: bool,
+    key_usage: Option<[u8; N]>,
+    params: CircuitParams,
+    _f: std::marker::PhantomData<F>,
+}
+
+impl<F: PrimeField, const N: usize> ChainCircuit<F, N> {
+    /// `anchor_n` is the modulus that verifies the last certificate.
+    pub fn new(certs: Vec<(Vec<u8>, BigUint)>, anchor_n: BigUint, params: CircuitParams) -> Self {
+        assert_eq!(certs.len(), N, "expected {N} certificates");
+        Self { certs, anchor_n, self_signed_root: false, path_len_check: false, key_usage: None, params, _f: std::marker::PhantomData }
+    }
+
+    /// Treats the last certificate as a self-signed root: its signature is verified as the final
+    /// link, and the anchor is constrained to be the root's own SPKI modulus.
+    pub fn with_self_signed_root(mut self) -> Self {
+        self.self_signed_root = true;
+        self
+    }
+
+    /// Constrains every certificate above the leaf to be a CA (basicConstraints cA TRUE) whose
+    /// pathLenConstraint, if it has one, allows the intermediates below it in this chain: at
+    /// least `i - 1` for `certs[i]`. Self-issued intermediates count like any other.
+    pub fn with_path_len_check(mut self) -> Self {
+        self.path_len_check = true;
+        self
+    }
+
+    /// Constrains `certs[i]` to have a keyUsage extension with every bit of `masks[i]` set, such
+    /// as `KEY_USAGE_KEY_CERT_SIGN` for CAs and `KEY_USAGE_DIGITAL_SIGNATURE` for a TLS leaf. A
+    /// zero mask leaves that certificate unchecked, keyUsage or not.
+    pub fn with_key_usage(mut self, masks: [u8; N]) -> Self {
+        self.key_usage = Some(masks);
+        self
+    }
+
+    /// A `ChainLinkReport` per certificate, leaf first, with validity periods checked at the unix
+    /// timestamp `at`, to tell which link of a chain breaks. The circuit proves only chains whose
+    /// signatures all verify; names, key identifiers and validity periods are checked natively
+    /// here alone. Errors mean a signed message isn't a TBS certificate.
+    pub fn verify_chain_report(&self, at: i64) -> Result<Vec<ChainLinkReport>, CertError> {
+        let parsed = self
+            .certs
+            .iter()
+            .map(|(tbs, _)| TbsCertificate::from_der(tbs).map(|(_, tbs)| tbs))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| CertError::MalformedStructure("signed message is not a tbsCertificate"))?;
+        Ok((0..N)
+            .map(|i| {
+                let (key, key_linked) = match parsed.get(i + 1) {
+                    Some(issuer) => {
+                        let key = rsa_spki(&self.certs[i + 1].0, self.params.bits_len).map(|(_, n_big)| n_big);
+                        let key_linked = key.is_some() && issued(issuer, &parsed[i]);
+                        (key, key_linked)
+                    }
+                    None if self.self_signed_root => {
+                        let own_key = rsa_spki(&self.certs[i].0, self.params.bits_len).map(|(_, n_big)| n_big);
+                        let key_linked = own_key.as_ref() == Some(&self.anchor_n) && issued(&parsed[i], &parsed[i]);
+                        (Some(self.anchor_n.clone()), key_linked)
+                    }
+                    None => (Some(self.anchor_n.clone()), true),
+                };
+                let (tbs, sign_big) = &self.certs[i];
+                let signature_valid = key.map_or(false, |n_big| {
+                    CertificateVerificationCircuit::<F>::new(n_big, sign_big.clone(), tbs.clone(), self.params.clone()).signature_is_valid()
+                });
+                let validity = parsed[i].validity();
+                ChainLinkReport {
+                    subject_cn: parsed[i].subject().iter_common_name().next().and_then(|cn| cn.as_str().ok()).map(String::from),
+                    signature_valid,
+                    key_linked,
+                    within_validity: validity.not_before.timestamp() <= at && at <= validity.not_after.timestamp(),
+                }
+            })
+            .collect())
+    }
+
+    pub fn public_inputs(&self) -> Vec<Vec<F>> {
+        let hash_fes = Sha256::digest(&self.certs[0].0).iter().map(|byte| F::from(*byte as u64)).collect::<Vec<F>>();
+        vec![modulus_instance(&self.anchor_n, &self.params), hash_fes]
+    }
+}
+
+/// Offset of the RSA SubjectPublicKeyInfo in `tbs` and its modulus, if it has exactly the layout
+/// `assign_spki_modulus` constrains.
+pub(crate) fn rsa_spki(tbs: &[u8], bits_len: usize) -> Option<(usize, BigUint)> {
+    let spki = der::subject_public_key_info(tbs)?;
+    let prefix = der::rsa_spki_prefix(bits_len);
+    let modulus_start = spki.start + prefix.len();
+    let modulus_end = modulus_start + bits_len / 8;
+    let matches = spki.end() == modulus_end + der::RSA_SPKI_SUFFIX.len()
+        && tbs[spki.start..modulus_start] == prefix[..]
+        && &tbs[modulus_end..spki.end()] == der::RSA_SPKI_SUFFIX;
+    matches.then(|| (spki.start, BigUint::from_bytes_be(&tbs[modulus_start..modulus_end])))
+}
+
+/// extnID of basicConstraints, the first bytes `constrain_path_len` anchors the extension with.
+const BASIC_CONSTRAINTS_EXTN_ID: [u8; 5] = [0x06, 0x03, 0x55, 0x1d, 0x13];
+/// The DER BOOLEAN TRUE of a critical extension.
+const CRITICAL: [u8; 3] = [0x01, 0x01, 0xff];
+/// Bytes of a basicConstraints `extnValue` with a pathLenConstraint, tag and length included.
+const BASIC_CONSTRAINTS_VALUE_LEN: usize = 10;
+/// extnID of keyUsage, the first bytes `constrain_key_usage` anchors the extension with.
+const KEY_USAGE_EXTN_ID: [u8; 5] = [0x06, 0x03, 0x55, 0x1d, 0x0f];
+/// Bytes of a keyUsage `extnValue` up to the first byte of bits, tag and length included.
+const KEY_USAGE_VALUE_LEN: usize = 6;
+
+/// keyUsage digitalSignature, in the first byte of the BIT STRING, for `ChainCircuit::with_key_usage`.
+pub const KEY_USAGE_DIGITAL_SIGNATURE: u8 = 0x80;
+/// keyUsage keyCertSign, in the first byte of the BIT STRING, for `ChainCircuit::with_key_usage`.
+pub const KEY_USAGE_KEY_CERT_SIGN: u8 = 0x04;
+
+/// Offset of the basicConstraints extnID in `tbs`, whether it is critical and its
+/// pathLenConstraint, if the extension has exactly a layout `constrain_path_len` covers: cA TRUE
+/// and a pathLenConstraint, if any, below 128.
+fn ca_basic_constraints(tbs: &[u8]) -> Option<(usize, bool, Option<u8>)> {
+    let ext = der::find_extension_entry(tbs, der::OID_BASIC_CONSTRAINTS)?;
+    let path_len = match &tbs[ext.value.clone()] {
+        [0x30, 0x03, 0x01, 0x01, 0xff] => None,
+        [0x30, 0x06, 0x01, 0x01, 0xff, 0x02, 0x01, path_len] if *path_len < 0x80 => Some(*path_len),
+        _ => return None,
+    };
+    Some((ext.oid.start - 2, ext.critical, path_len))
+}
+
+/// Offset of the keyUsage extnID in `tbs`, whether it is critical, the first byte of its bits and
+/// whether there is a second, if the extension has a layout `constrain_key_usage` covers.
+fn key_usage(tbs: &[u8]) -> Option<(usize, bool, u8, bool)> {
+    let ext = der::find_extension_entry(tbs, der::OID_KEY_USAGE)?;
+    let (bits, two_bytes) = match &tbs[ext.value.clone()] {
+        [0x03, 0x02, _, bits] => (*bits, false),
+        [0x03, 0x03, _, bits, _] => (*bits, true),
+        _ => return None,
+    };
+    Some((ext.oid.start - 2, ext.critical, bits, two_bytes))
+}
+
+#[derive(Debug, Clone)]
+pub struct ChainConfig<F: PrimeField> {
+    rsa_config: RSAConfig<F>,
+    sha256_config: Sha256DynamicConfig<F>,
+    n_instance: Column<Instance>,
+    hash_instance: Column<Instance>,
+    params: CircuitParams,
+}
+
+impl<F: PrimeField> ChainConfig<F> {
+    /// Constrains `public_key` to the RSA SPKI at `offset` in the hashed `msg_bytes`.
+    fn constrain_certified_key<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        msg_bytes: &[AssignedValue<'v, F>],
+        offset: usize,
+        public_key: &AssignedRSAPublicKey<'v, F>
+    ) {
+        let gate = self.rsa_config.biguint_config().gate();
+        let spki_limbs = assign_spki_modulus(gate, ctx, msg_bytes, offset, self.params.bits_len, self.params.limb_bits);
+        for (spki_limb, key_limb) in spki_limbs.iter().zip(public_key.n.limbs().iter()) {
+            gate.assert_equal(ctx, Existing(spki_limb), Existing(key_limb));
+        }
+    }
+
+    /// Selects the extension at `offset` in the hashed `msg_bytes`, constrains it to begin with
+    /// `extn_id` and, if it is `critical` (a witnessed bit), the critical flag, and returns the
+    /// `value_len` bytes from its extnValue OCTET STRING tag on.
+    fn select_extension_value<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        msg_bytes: &[AssignedValue<'v, F>],
+        offset: usize,
+        extn_id: &[u8],
+        critical: bool,
+        value_len: usize
+    ) -> Vec<AssignedValue<'v, F>> {
+        let range = self.rsa_config.biguint_config().range();
+        let gate = range.gate();
+        let start = gate.load_witness(ctx, Value::known(F::from(offset as u64)));
+        let window = select_window(gate, ctx, msg_bytes, &start, extn_id.len() + CRITICAL.len() + value_len);
+        let (window_id, rest) = window.split_at(extn_id.len());
+        for (byte, expected) in window_id.iter().zip(extn_id) {
+            gate.assert_is_const(ctx, byte, F::from(*expected as u64));
+        }
+        let critical = gate.load_witness(ctx, Value::known(F::from(critical as u64)));
+        range.range_check(ctx, &critical, 1);
+        for (byte, expected) in rest.iter().zip(CRITICAL) {
+            assert_if(gate, ctx, &critical, byte, expected);
+        }
+        // The OCTET STRING follows the critical flag, or directly the extnID
+        (0..value_len)
+            .map(|j| gate.select(ctx, Existing(&rest[j + CRITICAL.len()]), Existing(&rest[j]), Existing(&critical)))
+            .collect()
+    }
+
+    /// Constrains the basicConstraints extension at `offset` in the hashed `msg_bytes` (see
+    /// `ca_basic_constraints`) to have cA TRUE and, if `path_len` is set, a pathLenConstraint of
+    /// at least `depth`. The extension's DER is fixed up to the critical flag and the presence of
+    /// a pathLenConstraint, both witnessed bits, and the encoded lengths tie the latter to the
+    /// certificate's bytes.
+    fn constrain_path_len<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        msg_bytes: &[AssignedValue<'v, F>],
+        offset: usize,
+        critical: bool,
+        path_len: Option<u8>,
+        depth: usize
+    ) {
+        let range = self.rsa_config.biguint_config().range();
+        let gate = range.gate();
+        let value = self.select_extension_value(ctx, msg_bytes, offset, &BASIC_CONSTRAINTS_EXTN_ID, critical, BASIC_CONSTRAINTS_VALUE_LEN);
+
+        // 04 len 30 len 01 01 ff, then 02 01 pathLen if there is one
+        let has_path_len = gate.load_witness(ctx, Value::known(F::from(path_len.is_some() as u64)));
+        range.range_check(ctx, &has_path_len, 1);
+        let octet_len = gate.mul_add(ctx, Existing(&has_path_len), Constant(F::from(3)), Constant(F::from(5)));
+        let seq_len = gate.mul_add(ctx, Existing(&has_path_len), Constant(F::from(3)), Constant(F::from(3)));
+        gate.assert_is_const(ctx, &value[0], F::from(der::TAG_OCTET_STRING as u64));
+        gate.assert_equal(ctx, Existing(&value[1]), Existing(&octet_len));
+        gate.assert_is_const(ctx, &value[2], F::from(der::TAG_SEQUENCE as u64));
+        gate.assert_equal(ctx, Existing(&value[3]), Existing(&seq_len));
+        for (byte, expected) in value[4..7].iter().zip([der::TAG_BOOLEAN, 0x01, 0xff]) {
+            gate.assert_is_const(ctx, byte, F::from(expected as u64));
+        }
+        assert_if(gate, ctx, &has_path_len, &value[7], 0x02);
+        assert_if(gate, ctx, &has_path_len, &value[8], 0x01);
+
+        // Past the extension without a pathLenConstraint, so only read with one
+        let path_len = gate.mul(ctx, Existing(&has_path_len), Existing(&value[9]));
+        range.range_check(ctx, &path_len, 7);
+        let too_short = range.is_less_than(ctx, Existing(&path_len), Constant(F::from(depth as u64)), 8);
+        let violated = gate.mul(ctx, Existing(&has_path_len), Existing(&too_short));
+        gate.assert_is_const(ctx, &violated, F::zero());
+    }
+
+    /// Constrains the keyUsage extension at `offset` in the hashed `msg_bytes` (see `key_usage`)
+    /// to have every bit of `mask` set in the first byte of its BIT STRING. Whether the BIT STRING
+    /// has a second byte is a witnessed bit tied to the encoded lengths, like the
+    /// pathLenConstraint of `constrain_path_len`.
+    fn constrain_key_usage<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        msg_bytes: &[AssignedValue<'v, F>],
+        offset: usize,
+        critical: bool,
+        two_bytes: bool,
+        mask: u8
+    ) {
+        let range = self.rsa_config.biguint_config().range();
+        let gate = range.gate();
+        let value = self.select_extension_value(ctx, msg_bytes, offset, &KEY_USAGE_EXTN_ID, critical, KEY_USAGE_VALUE_LEN);
+
+        // 04 len 03 len unusedBits, then one or two bytes of bits
+        let two_bytes = gate.load_witness(ctx, Value::known(F::from(two_bytes as u64)));
+        range.range_check(ctx, &two_bytes, 1);
+        let octet_len = gate.add(ctx, Existing(&two_bytes), Constant(F::from(4)));
+        let bit_string_len = gate.add(ctx, Existing(&two_bytes), Constant(F::from(2)));
+        gate.assert_is_const(ctx, &value[0], F::from(der::TAG_OCTET_STRING as u64));
+        gate.assert_equal(ctx, Existing(&value[1]), Existing(&octet_len));
+        gate.assert_is_const(ctx, &value[2], F::from(der::TAG_BIT_STRING as u64));
+        gate.assert_equal(ctx, Existing(&value[3]), Existing(&bit_string_len));
+
+        // Bit 0 of the BIT STRING, digitalSignature, is the most significant of the byte
+        let bits = gate.num_to_bits(ctx, &value[5], 8);
+        for (j, bit) in bits.iter().enumerate() {
+            if mask >> j & 1 == 1 {
+                gate.assert_is_const(ctx, bit, F::one());
+            }
+        }
+    }
+}
+
+/// `flag * (byte - expected) == 0`: the byte only matters when the flag is set.
+fn assert_if<'v, F: PrimeField>(
+    gate: &impl GateInstructions<F>,
+    ctx: &mut Context<'v, F>,
+    flag: &AssignedValue<'v, F>,
+    byte: &AssignedValue<'v, F>,
+    expected: u8
+) {
+    let diff = gate.sub(ctx, Existing(byte), Constant(F::from(expected as u64)));
+    let masked = gate.mul(ctx, Existing(flag), Existing(&diff));
+    gate.assert_is_const(ctx, &masked, F::zero());
+}
+
+impl<F: PrimeField, const N: usize> Circuit<F> for ChainCircuit<F, N> {
+    type Config = ChainConfig<F>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        unimplemented!();
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let params = CircuitParams::current();
+        let (rsa_config, sha256_config) = configure_gadgets(meta, &params, N);
+        let n_instance = meta.instance_column();
+        meta.enable_equality(n_instance);
+        let hash_instance = meta.instance_column();
+        meta.enable_equality(hash_instance);
+
+        Self::Config {
+            rsa_config,
+            sha256_config,
+            n_instance,
+            hash_instance,
+            params
+        }
+    }
+
+    fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+        let biguint_config = config.rsa_config.biguint_config();
+        config.sha256_config.load(&mut layouter)?;
+        biguint_config.range().load_lookup_table(&mut layouter)?;
+        let mut first_pass = SKIP_FIRST_PASS;
+        let (anchor_cells, leaf_hash_cells) = layouter.assign_region(
+            || "certificate chain verifier",
+            |region| {
+                if first_pass {
+                    first_pass = false;
+                    return Ok((vec![], vec![]));
+                }
+
+                let mut aux = biguint_config.new_context(region);
+                let ctx = &mut aux;
+                let bits_len = config.params.bits_len;
+                let e_fix = || RSAPubE::Fix(BigUint::from(CertificateVerificationCircuit::<F>::DEFAULT_E));
+
+                // Each TBS takes the next SHA256 slot
+                let mut sha256_config = config.sha256_config.clone();
+                let hash_results = self
+                    .certs
+                    .iter()
+                    .map(|(tbs, _)| sha256_config.digest(ctx, tbs, None))
+                    .collect::<Result<Vec<_>, Error>>()?;
+
+                // keys[i] verifies certs[i]: the key certified by certs[i + 1], or the anchor
+                let mut keys = vec![];
+                for ((tbs, _), hash_result) in self.certs.iter().zip(&hash_results).skip(1) {
+                    let (offset, n_big) = rsa_spki(tbs, bits_len).ok_or(Error::Synthesis)?;
+                    let public_key = RSAPublicKey::new(Value::known(n_big), e_fix());
+                    let public_key = config.rsa_config.assign_public_key(ctx, public_key)?;
+                    config.constrain_certified_key(ctx, &hash_result.input_bytes, offset, &public_key);
+                    keys.push(public_key);
+                }
+                let anchor = RSAPublicKey::new(Value::known(self.anchor_n.clone()), e_fix());
+                let anchor = config.rsa_config.assign_public_key(ctx, anchor)?;
+                if self.self_signed_root {
+                    let (offset, _) = rsa_spki(&self.certs[N - 1].0, bits_len).ok_or(Error::Synthesis)?;
+                    config.constrain_certified_key(ctx, &hash_results[N - 1].input_bytes, offset, &anchor);
+                }
+                keys.push(anchor);
+                if self.path_len_check {
+                    for (depth, ((tbs, _), hash_result)) in self.certs.iter().zip(&hash_results).skip(1).enumerate() {
+                        let (offset, critical, path_len) = ca_basic_constraints(tbs).ok_or(Error::Synthesis)?;
+                        config.constrain_path_len(ctx, &hash_result.input_bytes, offset, critical, path_len, depth);
+                    }
+                }
+                if let Some(masks) = &self.key_usage {
+                    for (((tbs, _), hash_result), mask) in self.certs.iter().zip(&hash_results).zip(masks) {
+                        if *mask == 0 {
+                            continue;
+                        }
+                        let (offset, critical, _, two_bytes) = key_usage(tbs).ok_or(Error::Synthesis)?;
+                        config.constrain_key_usage(ctx, &hash_result.input_bytes, offset, critical, two_bytes, *mask);
+                    }
+                }
+
+                for (((_, sign_big), hash_result), public_key) in self.certs.iter().zip(&hash_results).zip(&keys) {
+                    let signature = RSASignature::new(Value::known(sign_big.clone()));
+                    let signature = config.rsa_config.assign_signature(ctx, signature)?;
+                    let is_valid = verify_pkcs1v15_signature(
+                        &config.rsa_config, ctx, public_key, &hash_result.output_bytes, &signature, self.params.montgomery_exp
+                    )?;
+                    biguint_config.gate().assert_is_const(ctx, &is_valid, F::one());
+                }
+
+                biguint_config.range().finalize(ctx);
+                CircuitStats {
+                    total_advice: ctx.total_advice,
+                    total_fixed: ctx.total_fixed,
+                    lookup_cells: ctx.cells_to_lookup.len(),
+                }.record();
+                let anchor_cells = keys[N - 1]
+                    .n
+                    .limbs()
+                    .iter()
+                    .map(|v| v.cell())
+                    .collect::<Vec<Cell>>();
+                let leaf_hash_cells = hash_results[0]
+                    .output_bytes
+                    .iter()
+                    .map(|v| v.cell())
+                    .collect::<Vec<Cell>>();
+                Ok((anchor_cells, leaf_hash_cells))
+            },
+        )?;
+        for (i, cell) in anchor_cells.into_iter().enumerate() {
+            layouter.constrain_instance(cell, config.n_instance, i)?;
+        }
+        for (i, cell) in leaf_hash_cells.into_iter().enumerate() {
+            layouter.constrain_instance(cell, config.hash_instance, i)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::load_fixture;
+    use halo2_base::halo2_proofs::{dev::MockProver, halo2curves::bn256::Fr};
+
+    use crate::cert::test::der_from_pem;
+    use crate::test_utils::fixture_path;
+    use openssl::{
+        asn1::Asn1Time,
+        bn::BigNum,
+        hash::MessageDigest,
+        pkey::{PKey, Private},
+        rsa::Rsa,
+        x509::{extension::{BasicConstraints, KeyUsage}, X509Builder, X509NameBuilder, X509},
+    };
+
+    #[test]
+    fn test_chain_builder_orders_shuffled_bundle() {
+        let [cert_1, cert_2, cert_3] = ["./certs/cert_1.pem", "./certs/cert_2.pem", "./certs/cert_3.pem"].map(der_from_pem);
+        let chain = CertChainBuilder::new()
+            .add_der(cert_2.clone())
+            .add_der(cert_1.clone())
+            .add_der(cert_3.clone())
+            .build()
+            .unwrap();
+        assert_eq!(chain.certs, vec![cert_3.clone(), cert_2.clone(), cert_1.clone()]);
+
+        let (links, anchor_n) = chain.circuit_inputs(false).unwrap();
+        let (cert_1_n, cert_2_sig, cert_2_tbs) = load_fixture("./certs/cert_2.pem", "./certs/cert_1.pem");
+        assert_eq!(anchor_n, cert_1_n);
+        assert_eq!(links.len(), 2);
+        assert_eq!(links[1], (cert_2_tbs, cert_2_sig));
+
+        let missing_intermediate = CertChainBuilder::new().add_der(cert_1).add_der(cert_3.clone()).build();
+        let (_, leaf) = X509Certificate::from_der(&cert_3).unwrap();
+        assert_eq!(missing_intermediate, Err(CertError::IncompleteChain { issuer: leaf.issuer().to_string() }));
+    }
+
+    #[test]
+    fn test_chain_builder_self_signed_root() {
+        let [root, leaf] = ["./certs/test_ca.pem", "./certs/san_leaf.pem"].map(der_from_pem);
+        let chain = CertChainBuilder::new().add_der(root).add_der(leaf).build().unwrap();
+        let (links, anchor_n) = chain.circuit_inputs(true).unwrap();
+        assert_eq!(links.len(), 2);
+        let circuit = ChainCircuit::<Fr, 2>::new(links, anchor_n, chain_params()).with_self_signed_root();
+        assert_eq!(circuit.public_inputs().len(), 2);
+    }
+
+    #[test]
+    fn test_mixed_chain_is_rejected() {
+        // An RSA leaf signed by a P-256 CA
+        let [root, leaf] = ["./certs/ec_ca.pem", "./certs/rsa_leaf_ec_issuer.pem"].map(der_from_pem);
+        let chain = CertChainBuilder::new().add_der(root).add_der(leaf).build().unwrap();
+        assert_eq!(chain.signature_algorithms().unwrap(), vec!["ecdsa-with-SHA256", "ecdsa-with-SHA256"]);
+        let unsupported = CertError::UnsupportedSignatureAlgorithm { oid: "1.2.840.10045.4.3.2".to_string(), name: "ecdsa-with-SHA256" };
+        assert_eq!(chain.circuit_inputs(false), Err(unsupported.clone()));
+        assert_eq!(chain.circuit_inputs(true), Err(unsupported));
+
+        let chain = CertChainBuilder::new()
+            .add_der(der_from_pem("./certs/test_ca.pem"))
+            .add_der(der_from_pem("./certs/san_leaf.pem"))
+            .build()
+            .unwrap();
+        assert_eq!(chain.signature_algorithms().unwrap(), vec!["sha256WithRSAEncryption"; 2]);
+    }
+
+    /// A sha256WithRSAEncryption certificate for `key`, issued by `issuer`. `ca` is `None` for a
+    /// leaf, else the pathLenConstraint of a CA.
+    fn issue(common_name: &str, key: &PKey<Private>, issuer: (&X509, &PKey<Private>), ca: Option<Option<u32>>) -> X509 {
+        issue_with_key_usage(common_name, key, issuer, ca, None)
+    }
+
+    /// `issue`, with a keyUsage extension if `key_usage` is set.
+    fn issue_with_key_usage(
+        common_name: &str,
+        key: &PKey<Private>,
+        issuer: (&X509, &PKey<Private>),
+        ca: Option<Option<u32>>,
+        key_usage: Option<KeyUsage>
+    ) -> X509 {
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_text("CN", common_name).unwrap();
+        let name = name.build();
+        let mut builder = X509Builder::new().unwrap();
+        builder.set_version(2).unwrap();
+        builder.set_serial_number(&BigNum::from_u32(1).unwrap().to_asn1_integer().unwrap()).unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder.set_issuer_name(issuer.0.subject_name()).unwrap();
+        builder.set_pubkey(key).unwrap();
+        builder.set_not_before(&Asn1Time::days_from_now(0).unwrap()).unwrap();
+        builder.set_not_after(&Asn1Time::days_from_now(365).unwrap()).unwrap();
+        if let Some(path_len) = ca {
+            let mut basic_constraints = BasicConstraints::new();
+            basic_constraints.critical().ca();
+            if let Some(path_len) = path_len {
+                basic_constraints.pathlen(path_len);
+            }
+            builder.append_extension(basic_constraints.build().unwrap()).unwrap();
+        }
+        if let Some(key_usage) = key_usage {
+            builder.append_extension(key_usage.build().unwrap()).unwrap();
+        }
+        builder.sign(issuer.1, MessageDigest::sha256()).unwrap();
+        builder.build()
+    }
+
+    /// The `(tbs, signature)` links of `certs`.
+    fn to_links(certs: &[X509]) -> Vec<(Vec<u8>, BigUint)> {
+        certs
+            .iter()
+            .map(|cert| {
+                let parts = parse_der_certificate(&cert.to_der().unwrap()).unwrap();
+                (parts.tbs, parts.signature)
+            })
+            .collect()
+    }
+
+    /// `[leaf, intermediate, intermediate with `path_len`]` links under test_ca, and its modulus.
+    fn path_len_chain(path_len: u32) -> (Vec<(Vec<u8>, BigUint)>, BigUint) {
+        let root = X509::from_pem(&std::fs::read(fixture_path("./certs/test_ca.pem")).unwrap()).unwrap();
+        let root_key = PKey::private_key_from_pem(&std::fs::read(fixture_path("./certs/test_ca.key")).unwrap()).unwrap();
+        let [upper_key, lower_key, leaf_key] = [(); 3].map(|_| PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap());
+        let upper = issue("zkcert Upper CA", &upper_key, (&root, &root_key), Some(Some(path_len)));
+        let lower = issue("zkcert Lower CA", &lower_key, (&upper, &upper_key), Some(None));
+        let leaf = issue("zkcert Leaf", &leaf_key, (&lower, &lower_key), None);
+        let links = to_links(&[leaf, lower, upper]);
+        let (root_n, _, _) = load_fixture("./certs/test_ca.pem", "./certs/test_ca.pem");
+        (links, root_n)
+    }
+
+    #[test]
+    fn test_ca_basic_constraints() {
+        // cert_2 is an intermediate with a critical `CA:TRUE, pathlen:0`, cert_1 has no pathlen
+        let (_, _, cert_2_tbs) = load_fixture("./certs/cert_2.pem", "./certs/cert_1.pem");
+        let (offset, critical, path_len) = ca_basic_constraints(&cert_2_tbs).unwrap();
+        assert_eq!(&cert_2_tbs[offset..offset + BASIC_CONSTRAINTS_EXTN_ID.len()], &BASIC_CONSTRAINTS_EXTN_ID);
+        assert_eq!((critical, path_len), (true, Some(0)));
+        let (_, _, cert_1_tbs) = load_fixture("./certs/cert_1.pem", "./certs/cert_1.pem");
+        assert_eq!(ca_basic_constraints(&cert_1_tbs).map(|(_, critical, path_len)| (critical, path_len)), Some((true, None)));
+        // cert_3 is a leaf, CA:FALSE
+        let (_, _, leaf_tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        assert_eq!(ca_basic_constraints(&leaf_tbs), None);
+    }
+
+    #[test]
+    fn test_path_len_constraint() {
+        // The upper CA has one intermediate below it, which pathlen:0 forbids
+        for (path_len, allowed) in [(1, true), (0, false)] {
+            let (links, root_n) = path_len_chain(path_len);
+            let circuit = ChainCircuit::<Fr, 3>::new(links.clone(), root_n.clone(), chain_params());
+            circuit.params.set_current();
+            let prover = MockProver::run(circuit.params.k, &circuit, circuit.public_inputs()).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+
+            let circuit = ChainCircuit::<Fr, 3>::new(links, root_n, chain_params()).with_path_len_check();
+            let prover = MockProver::run(circuit.params.k, &circuit, circuit.public_inputs()).unwrap();
+            assert_eq!(prover.verify().is_ok(), allowed);
+        }
+    }
+
+    /// `[leaf, CA]` links under test_ca, the leaf with digitalSignature and the CA with
+    /// keyCertSign unless `ca_signs_certs` is false, and test_ca's modulus.
+    fn key_usage_chain(ca_signs_certs: bool) -> (Vec<(Vec<u8>, BigUint)>, BigUint) {
+        let root = X509::from_pem(&std::fs::read(fixture_path("./certs/test_ca.pem")).unwrap()).unwrap();
+        let root_key = PKey::private_key_from_pem(&std::fs::read(fixture_path("./certs/test_ca.key")).unwrap()).unwrap();
+        let [ca_key, leaf_key] = [(); 2].map(|_| PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap());
+        let mut ca_usage = KeyUsage::new();
+        ca_usage.critical().crl_sign();
+        if ca_signs_certs {
+            ca_usage.key_cert_sign();
+        }
+        let ca = issue_with_key_usage("zkcert CA", &ca_key, (&root, &root_key), Some(None), Some(ca_usage));
+        let mut leaf_usage = KeyUsage::new();
+        leaf_usage.digital_signature().key_encipherment();
+        let leaf = issue_with_key_usage("zkcert Leaf", &leaf_key, (&ca, &ca_key), None, Some(leaf_usage));
+        let links = to_links(&[leaf, ca]);
+        let (root_n, _, _) = load_fixture("./certs/test_ca.pem", "./certs/test_ca.pem");
+        (links, root_n)
+    }
+
+    #[test]
+    fn test_key_usage_constraint() {
+        let (links, _) = key_usage_chain(true);
+        let (_, critical, bits, two_bytes) = key_usage(&links[1].0).unwrap();
+        assert_eq!((critical, bits & KEY_USAGE_KEY_CERT_SIGN, two_bytes), (true, KEY_USAGE_KEY_CERT_SIGN, false));
+        let (_, critical, bits, _) = key_usage(&links[0].0).unwrap();
+        assert_eq!((critical, bits & KEY_USAGE_DIGITAL_SIGNATURE), (false, KEY_USAGE_DIGITAL_SIGNATURE));
+
+        let masks = [KEY_USAGE_DIGITAL_SIGNATURE, KEY_USAGE_KEY_CERT_SIGN];
+        for ca_signs_certs in [true, false] {
+            let (links, root_n) = key_usage_chain(ca_signs_certs);
+            let circuit = ChainCircuit::<Fr, 2>::new(links.clone(), root_n.clone(), chain_params());
+            circuit.params.set_current();
+            let prover = MockProver::run(circuit.params.k, &circuit, circuit.public_inputs()).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+
+            let circuit = ChainCircuit::<Fr, 2>::new(links, root_n, chain_params()).with_key_usage(masks);
+            let prover = MockProver::run(circuit.params.k, &circuit, circuit.public_inputs()).unwrap();
+            assert_eq!(prover.verify().is_ok(), ca_signs_certs);
+        }
+    }
+
+    #[test]
+    fn test_chain_report() {
+        let root = X509::from_pem(&std::fs::read(fixture_path("./certs/test_ca.pem")).unwrap()).unwrap();
+        let root_key = PKey::private_key_from_pem(&std::fs::read(fixture_path("./certs/test_ca.key")).unwrap()).unwrap();
+        let [upper_key, lower_key, leaf_key, rogue_key] = [(); 4].map(|_| PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap());
+        let upper = issue("zkcert Upper CA", &upper_key, (&root, &root_key), Some(None));
+        // Names the upper CA as its issuer, but signed by another key
+        let lower = issue("zkcert Lower CA", &lower_key, (&upper, &rogue_key), Some(None));
+        let leaf = issue("zkcert Leaf", &leaf_key, (&lower, &lower_key), None);
+        let (root_n, _, _) = load_fixture("./certs/test_ca.pem", "./certs/test_ca.pem");
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() as i64;
+
+        let circuit = ChainCircuit::<Fr, 3>::new(to_links(&[leaf.clone(), lower, upper.clone()]), root_n.clone(), chain_params());
+        let report = circuit.verify_chain_report(now).unwrap();
+        assert_eq!(report.iter().map(ChainLinkReport::is_ok).collect::<Vec<_>>(), vec![true, false, true]);
+        assert_eq!(
+            report[1],
+            ChainLinkReport { subject_cn: Some("zkcert Lower CA".to_string()), signature_valid: false, key_linked: true, within_validity: true }
+        );
+        let expired = now + 2 * 365 * 24 * 60 * 60;
+        assert!(circuit.verify_chain_report(expired).unwrap().iter().all(|link| !link.within_validity));
+
+        // Without the lower CA, the upper one isn't the leaf's issuer
+        let circuit = ChainCircuit::<Fr, 2>::new(to_links(&[leaf, upper]), root_n, chain_params());
+        let report = circuit.verify_chain_report(now).unwrap();
+        assert_eq!((report[0].signature_valid, report[0].key_linked), (false, false));
+        assert!(report[1].is_ok());
+    }
+
+    fn chain_params() -> CircuitParams {
+        CircuitParams { k: 18, ..Default::default() }
+    }
+
+    #[test]
+    fn test_self_signed_root() {
+        let (root_n, leaf_sig, leaf_tbs) = load_fixture("./certs/san_leaf.pem", "./certs/test_ca.pem");
+        let (_, root_sig, root_tbs) = load_fixture("./certs/test_ca.pem", "./certs/test_ca.pem");
+        assert_eq!(rsa_spki(&root_tbs, 2048).unwrap().1, root_n);
+
+        let certs = vec![(leaf_tbs, leaf_sig), (root_tbs, root_sig)];
+        let circuit = ChainCircuit::<Fr, 2>::new(certs, root_n, chain_params()).with_self_signed_root();
+        circuit.params.set_current();
+        let prover = MockProver::run(circuit.params.k, &circuit, circuit.public_inputs()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_self_signed_root_rejects_cross_signed() {
+        // cert_2 is signed by cert_1, so it only verifies under an external anchor
+        let (issuer_n, leaf_sig, leaf_tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let (anchor_n, issuer_sig, issuer_tbs) = load_fixture("./certs/cert_2.pem", "./certs/cert_1.pem");
+        assert_eq!(rsa_spki(&issuer_tbs, 2048).unwrap().1, issuer_n);
+        let certs = vec![(leaf_tbs, leaf_sig), (issuer_tbs, issuer_sig)];
+
+        let circuit = ChainCircuit::<Fr, 2>::new(certs.clone(), anchor_n.clone(), chain_params());
+        circuit.params.set_current();
+        let prover = MockProver::run(circuit.params.k, &circuit, circuit.public_inputs()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let circuit = ChainCircuit::<Fr, 2>::new(certs, anchor_n, chain_params()).with_self_signed_root();
+        let prover = MockProver::run(circuit.params.k, &circuit, circuit.public_inputs()).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
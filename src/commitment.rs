@@ -0,0 +1,162 @@
+//! The hash behind every commitment the circuits expose: `issuer_key_hash`, `dns_names_commitment`
+//! and the nodes of the `merkle` trusted key tree. The off-circuit helpers hash with
+//! `commitment_digest` and the circuits with `assign_commitment_digest`, so the two sides agree on
+//! the hash, and replacing it, such as with Poseidon once the pinned halo2-base has a chip for it,
+//! is a change to this module alone.
+//!
+//! The hash is SHA-256, on the SHA256 chip the circuits already hash the message with.
+use halo2_base::{
+    gates::GateInstructions,
+    utils::PrimeField,
+    AssignedValue,
+    Context,
+    QuantumCell::{Constant, Existing},
+    halo2_proofs::plonk::Error,
+};
+use halo2_dynamic_sha256::Sha256DynamicConfig;
+use sha2::{Digest, Sha256};
+
+/// Length in bytes of a commitment digest.
+pub const COMMITMENT_LEN: usize = 32;
+
+/// Leading digest bytes `pack_commitment` keeps, which fit any field the circuit supports.
+const PACKED_LEN: usize = 31;
+
+/// Commitment to `preimage`, off-circuit.
+pub fn commitment_digest(preimage: &[u8]) -> [u8; COMMITMENT_LEN] {
+    Sha256::digest(preimage).into()
+}
+
+/// `digest` as one field element: its leading 31 bytes read as a big-endian integer.
+pub fn pack_commitment<F: PrimeField>(digest: &[u8; COMMITMENT_LEN]) -> F {
+    digest[..PACKED_LEN].iter().fold(F::zero(), |acc, byte| acc * F::from(256) + F::from(*byte as u64))
+}
+
+/// Commitment to `preimage` in the next slot of `sha256_config`. Returns the preimage cells, for
+/// the caller to constrain to what is committed to, and the digest bytes. The slot must fit
+/// `preimage`, whose length is fixed.
+pub(crate) fn assign_commitment_digest<'v, F: PrimeField>(
+    gate: &impl GateInstructions<F>,
+    ctx: &mut Context<'v, F>,
+    sha256_config: &mut Sha256DynamicConfig<F>,
+    preimage: &[u8],
+) -> Result<(Vec<AssignedValue<'v, F>>, Vec<AssignedValue<'v, F>>), Error> {
+    let hash_result = sha256_config.digest(ctx, preimage, None)?;
+    gate.assert_is_const(ctx, &hash_result.input_len, F::from(preimage.len() as u64));
+    let mut preimage_cells = hash_result.input_bytes;
+    preimage_cells.truncate(preimage.len());
+    Ok((preimage_cells, hash_result.output_bytes))
+}
+
+/// `pack_commitment` of the assigned digest bytes.
+pub(crate) fn assign_packed_commitment<'v, F: PrimeField>(
+    gate: &impl GateInstructions<F>,
+    ctx: &mut Context<'v, F>,
+    digest: &[AssignedValue<'v, F>],
+) -> AssignedValue<'v, F> {
+    let mut powers = vec![];
+    let mut power = F::one();
+    for _ in 0..PACKED_LEN {
+        powers.push(Constant(power));
+        power *= F::from(256);
+    }
+    gate.inner_product(ctx, digest[..PACKED_LEN].iter().rev().map(Existing), powers)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::merkle::{hash_pair, trusted_key_leaf};
+    use crate::test::load_fixture;
+    use crate::{configure_gadgets, dns_names_commitment, dns_names_preimage, issuer_key_hash, modulus_bytes, CircuitParams};
+    use halo2_base::{
+        gates::RangeInstructions,
+        halo2_proofs::{
+            circuit::{Layouter, SimpleFloorPlanner},
+            dev::MockProver,
+            halo2curves::bn256::Fr,
+            plonk::{Circuit, Column, ConstraintSystem, Instance},
+        },
+        SKIP_FIRST_PASS,
+    };
+    use halo2_rsa::{RSAConfig, RSAInstructions};
+
+    /// Commits to a preimage in-circuit. Instances are `[digest bytes.., packed digest]`.
+    struct CommitmentCircuit(Vec<u8>);
+
+    impl Circuit<Fr> for CommitmentCircuit {
+        type Config = (RSAConfig<Fr>, Sha256DynamicConfig<Fr>, Column<Instance>);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!();
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let (rsa_config, sha256_config) = configure_gadgets(meta, &CircuitParams::current(), 1);
+            let instance = meta.instance_column();
+            meta.enable_equality(instance);
+            (rsa_config, sha256_config, instance)
+        }
+
+        fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<Fr>) -> Result<(), Error> {
+            let (rsa_config, sha256_config, instance) = config;
+            let biguint_config = rsa_config.biguint_config();
+            sha256_config.load(&mut layouter)?;
+            biguint_config.range().load_lookup_table(&mut layouter)?;
+            let mut first_pass = SKIP_FIRST_PASS;
+            let cells = layouter.assign_region(
+                || "commitment",
+                |region| {
+                    if first_pass {
+                        first_pass = false;
+                        return Ok(vec![]);
+                    }
+                    let mut aux = biguint_config.new_context(region);
+                    let ctx = &mut aux;
+                    let gate = biguint_config.gate();
+                    let (_, digest) = assign_commitment_digest(gate, ctx, &mut sha256_config.clone(), &self.0)?;
+                    let packed = assign_packed_commitment(gate, ctx, &digest);
+                    biguint_config.range().finalize(ctx);
+                    Ok(digest.iter().chain([&packed]).map(|v| v.cell()).collect::<Vec<_>>())
+                },
+            )?;
+            for (i, cell) in cells.into_iter().enumerate() {
+                layouter.constrain_instance(cell, instance, i)?;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_helpers_agree_with_circuit() {
+        let params = CircuitParams::default();
+        let (n_big, _, _) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let (_, _, san_tbs) = load_fixture("./certs/san_leaf.pem", "./certs/test_ca.pem");
+        let modulus = modulus_bytes(&n_big, params.bits_len);
+        let leaf = trusted_key_leaf(&n_big, &params);
+        let sibling = commitment_digest(b"sibling");
+
+        // Each helper's off-circuit commitment, with the preimage the circuits hash for it
+        let helpers = [
+            ("issuer_key_hash", modulus.clone(), commitment_digest(&modulus)),
+            ("dns_names_commitment", dns_names_preimage(&san_tbs, 4, 16).unwrap(), dns_names_commitment(&san_tbs, 4, 16).unwrap()),
+            ("trusted_key_leaf", modulus, leaf),
+            ("hash_pair", [leaf, sibling].concat(), hash_pair(&leaf, &sibling)),
+        ];
+        assert_eq!(pack_commitment::<Fr>(&leaf), issuer_key_hash::<Fr>(&n_big, &params));
+
+        params.set_current();
+        for (helper, preimage, expected) in helpers {
+            let mut instance = expected.iter().map(|byte| Fr::from(*byte as u64)).collect::<Vec<_>>();
+            instance.push(pack_commitment(&expected));
+            let circuit = CommitmentCircuit(preimage);
+            let prover = MockProver::run(params.k, &circuit, vec![instance.clone()]).unwrap();
+            assert_eq!(prover.verify(), Ok(()), "{helper}");
+
+            instance[COMMITMENT_LEN] += Fr::one();
+            let prover = MockProver::run(params.k, &circuit, vec![instance]).unwrap();
+            assert!(prover.verify().is_err(), "{helper}");
+        }
+    }
+}
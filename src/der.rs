@@ -0,0 +1,399 @@
+//! Minimal DER walking over a raw `tbsCertificate`.
+//!
+//! The circuit hashes the TBS bytes as-is, so every field we pull out of it is
+//! located by offset into those same bytes rather than re-encoded.
+use std::ops::Range;
+
+pub const TAG_BOOLEAN: u8 = 0x01;
+pub const TAG_BIT_STRING: u8 = 0x03;
+pub const TAG_OCTET_STRING: u8 = 0x04;
+pub const TAG_OID: u8 = 0x06;
+pub const TAG_UTC_TIME: u8 = 0x17;
+pub const TAG_DNS_NAME: u8 = 0x82;
+pub const TAG_SEQUENCE: u8 = 0x30;
+pub const TAG_SET: u8 = 0x31;
+pub const TAG_EXTENSIONS: u8 = 0xa3;
+pub const TAG_VERSION: u8 = 0xa0;
+
+/// Index of the `signature` AlgorithmIdentifier among the TBS fields, counted without the optional version.
+pub const TBS_SIGNATURE: usize = 1;
+/// Index of validity among the TBS fields, counted without the optional version.
+pub const TBS_VALIDITY: usize = 3;
+/// Index of subjectPublicKeyInfo among the TBS fields, counted without the optional version.
+pub const TBS_SUBJECT_PUBLIC_KEY_INFO: usize = 5;
+
+/// AlgorithmIdentifier of rsaEncryption (1.2.840.113549.1.1.1) with NULL parameters.
+pub const RSA_ALGORITHM_IDENTIFIER: &[u8] =
+    &[0x30, 0x0d, 0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01, 0x05, 0x00];
+/// `publicExponent` INTEGER 65537, the last bytes of an RSA SubjectPublicKeyInfo.
+pub const RSA_SPKI_SUFFIX: &[u8] = &[0x02, 0x03, 0x01, 0x00, 0x01];
+
+/// DER contents of the subjectAltName OID (2.5.29.17).
+pub const OID_SUBJECT_ALT_NAME: &[u8] = &[0x55, 0x1d, 0x11];
+/// DER contents of the basicConstraints OID (2.5.29.19).
+pub const OID_BASIC_CONSTRAINTS: &[u8] = &[0x55, 0x1d, 0x13];
+/// DER contents of the keyUsage OID (2.5.29.15).
+pub const OID_KEY_USAGE: &[u8] = &[0x55, 0x1d, 0x0f];
+
+/// A tag-length header located at `start`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tlv {
+    pub tag: u8,
+    pub start: usize,
+    pub header_len: usize,
+    pub len: usize,
+}
+
+impl Tlv {
+    pub fn content(&self) -> Range<usize> {
+        self.start + self.header_len..self.end()
+    }
+
+    pub fn end(&self) -> usize {
+        self.start + self.header_len + self.len
+    }
+}
+
+/// Reads the TLV at `offset`, returning `None` unless it lies entirely inside `bytes`.
+pub fn read_tlv(bytes: &[u8], offset: usize) -> Option<Tlv> {
+    let tag = *bytes.get(offset)?;
+    // Multi-byte tags never appear in X.509.
+    if tag & 0x1f == 0x1f {
+        return None;
+    }
+    let first = *bytes.get(offset + 1)? as usize;
+    let (header_len, len) = if first < 0x80 {
+        (2, first)
+    } else {
+        let num_bytes = first & 0x7f;
+        // 0x80 is a BER indefinite length; more than 4 bytes can't be a real certificate.
+        if num_bytes == 0 || num_bytes > 4 {
+            return None;
+        }
+        let mut len = 0usize;
+        for i in 0..num_bytes {
+            len = (len << 8) | *bytes.get(offset + 2 + i)? as usize;
+        }
+        (2 + num_bytes, len)
+    };
+    let end = offset.checked_add(header_len)?.checked_add(len)?;
+    if end > bytes.len() {
+        return None;
+    }
+    Some(Tlv { tag, start: offset, header_len, len })
+}
+
+/// Whether the TLV at `offset` has a BER indefinite length (`0x80`), which `read_tlv` rejects.
+pub fn is_indefinite_length(bytes: &[u8], offset: usize) -> bool {
+    bytes.get(offset + 1) == Some(&0x80)
+}
+
+fn encode_header(tag: u8, len: usize) -> Vec<u8> {
+    if len < 0x80 {
+        return vec![tag, len as u8];
+    }
+    let len_bytes = len.to_be_bytes();
+    let len_bytes = &len_bytes[len_bytes.iter().position(|byte| *byte != 0).unwrap()..];
+    let mut header = vec![tag, 0x80 | len_bytes.len() as u8];
+    header.extend_from_slice(len_bytes);
+    header
+}
+
+/// DER TLV of `contents` under `tag`.
+pub fn encode_tlv(tag: u8, contents: &[u8]) -> Vec<u8> {
+    let mut tlv = encode_header(tag, contents.len());
+    tlv.extend_from_slice(contents);
+    tlv
+}
+
+/// DER of an RSA SubjectPublicKeyInfo up to the first modulus byte, for a modulus of exactly
+/// `bits_len` bits (so its INTEGER has a leading 0x00) and e = 65537. The modulus bytes and then
+/// `RSA_SPKI_SUFFIX` follow.
+pub fn rsa_spki_prefix(bits_len: usize) -> Vec<u8> {
+    let modulus_len = bits_len / 8 + 1;
+    let modulus_header = encode_header(0x02, modulus_len);
+    let rsa_key_len = modulus_header.len() + modulus_len + RSA_SPKI_SUFFIX.len();
+    let rsa_key_header = encode_header(TAG_SEQUENCE, rsa_key_len);
+    let bit_string_len = 1 + rsa_key_header.len() + rsa_key_len;
+    let bit_string_header = encode_header(0x03, bit_string_len);
+    let spki_len = RSA_ALGORITHM_IDENTIFIER.len() + bit_string_header.len() + bit_string_len;
+
+    let mut prefix = encode_header(TAG_SEQUENCE, spki_len);
+    prefix.extend_from_slice(RSA_ALGORITHM_IDENTIFIER);
+    prefix.extend(bit_string_header);
+    // No unused bits in the BIT STRING
+    prefix.push(0x00);
+    prefix.extend(rsa_key_header);
+    prefix.extend(modulus_header);
+    prefix.push(0x00);
+    prefix
+}
+
+/// Dotted form of DER OID contents, e.g. `1.2.840.113549.1.1.11`.
+pub fn oid_to_string(contents: &[u8]) -> Option<String> {
+    let mut arcs = vec![];
+    let mut arc = 0u64;
+    for (i, byte) in contents.iter().enumerate() {
+        // A leading 0x80 would be a non-minimal encoding
+        if (arc == 0 && *byte == 0x80) || arc >> 57 != 0 {
+            return None;
+        }
+        arc = (arc << 7) | (byte & 0x7f) as u64;
+        if byte & 0x80 == 0 {
+            if arcs.is_empty() {
+                let first = (arc / 40).min(2);
+                arcs.push(first);
+                arcs.push(arc - 40 * first);
+            } else {
+                arcs.push(arc);
+            }
+            arc = 0;
+        } else if i == contents.len() - 1 {
+            return None;
+        }
+    }
+    if arcs.is_empty() {
+        return None;
+    }
+    Some(arcs.iter().map(|arc| arc.to_string()).collect::<Vec<_>>().join("."))
+}
+
+/// Splits the contents of `parent` into its direct children, which must tile it exactly.
+pub fn children(bytes: &[u8], parent: &Tlv) -> Option<Vec<Tlv>> {
+    let bytes = &bytes[..parent.end()];
+    let mut out = vec![];
+    let mut offset = parent.content().start;
+    while offset < parent.end() {
+        let child = read_tlv(bytes, offset)?;
+        offset = child.end();
+        out.push(child);
+    }
+    Some(out)
+}
+
+/// The fields of `tbs` with the optional `[0]` version skipped, so index 0 is serialNumber.
+pub fn tbs_fields(tbs: &[u8]) -> Option<Vec<Tlv>> {
+    let outer = read_tlv(tbs, 0)?;
+    if outer.tag != TAG_SEQUENCE || outer.end() != tbs.len() {
+        return None;
+    }
+    let mut fields = children(tbs, &outer)?;
+    if fields.first()?.tag == TAG_VERSION {
+        fields.remove(0);
+    }
+    Some(fields)
+}
+
+/// Locates the subjectPublicKeyInfo TLV of `tbs`.
+pub fn subject_public_key_info(tbs: &[u8]) -> Option<Tlv> {
+    tbs_fields(tbs)?
+        .get(TBS_SUBJECT_PUBLIC_KEY_INFO)
+        .copied()
+        .filter(|tlv| tlv.tag == TAG_SEQUENCE)
+}
+
+/// Locates the OID TLV of the TBS `signature` AlgorithmIdentifier, the copy covered by the
+/// signature (the outer `signatureAlgorithm` isn't).
+pub fn tbs_signature_algorithm_oid(tbs: &[u8]) -> Option<Tlv> {
+    let algorithm = tbs_fields(tbs)?.get(TBS_SIGNATURE).copied().filter(|tlv| tlv.tag == TAG_SEQUENCE)?;
+    children(tbs, &algorithm)?.first().copied().filter(|tlv| tlv.tag == TAG_OID)
+}
+
+/// Locates the notBefore and notAfter TLVs of the TBS validity, each a UTCTime or GeneralizedTime.
+pub fn validity(tbs: &[u8]) -> Option<(Tlv, Tlv)> {
+    let validity = tbs_fields(tbs)?.get(TBS_VALIDITY).copied().filter(|tlv| tlv.tag == TAG_SEQUENCE)?;
+    match children(tbs, &validity)?.as_slice() {
+        [not_before, not_after] => Some((*not_before, *not_after)),
+        _ => None,
+    }
+}
+
+/// One entry of the Extensions SEQUENCE, as ranges into the TBS.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Extension {
+    pub oid: Range<usize>,
+    pub critical: bool,
+    /// Contents of the `extnValue` OCTET STRING, i.e. the extension's own DER.
+    pub value: Range<usize>,
+}
+
+/// Parses every extension of `tbs`. `Some(vec![])` means the certificate has none,
+/// `None` means the TBS or the Extensions SEQUENCE is malformed.
+pub fn extensions(tbs: &[u8]) -> Option<Vec<Extension>> {
+    let outer = read_tlv(tbs, 0)?;
+    if outer.tag != TAG_SEQUENCE || outer.end() != tbs.len() {
+        return None;
+    }
+    let wrapper = match children(tbs, &outer)?.into_iter().find(|tlv| tlv.tag == TAG_EXTENSIONS) {
+        Some(wrapper) => wrapper,
+        None => return Some(vec![]),
+    };
+    let seq = read_tlv(tbs, wrapper.content().start)?;
+    if seq.tag != TAG_SEQUENCE || seq.end() != wrapper.end() {
+        return None;
+    }
+
+    let mut out = vec![];
+    for ext in children(tbs, &seq)? {
+        if ext.tag != TAG_SEQUENCE {
+            return None;
+        }
+        let fields = children(tbs, &ext)?;
+        let (oid, critical, value) = match fields.as_slice() {
+            [oid, value] => (oid, false, value),
+            [oid, critical, value] if critical.tag == TAG_BOOLEAN && critical.len == 1 => {
+                (oid, tbs[critical.content().start] != 0, value)
+            }
+            _ => return None,
+        };
+        if oid.tag != TAG_OID || value.tag != TAG_OCTET_STRING {
+            return None;
+        }
+        out.push(Extension { oid: oid.content(), critical, value: value.content() });
+    }
+    Some(out)
+}
+
+/// Number of extensions in `tbs`, or `None` if the Extensions SEQUENCE is malformed.
+pub fn extension_count(tbs: &[u8]) -> Option<usize> {
+    extensions(tbs).map(|exts| exts.len())
+}
+
+/// Returns the extension identified by `oid` (DER contents, no tag/length), wherever it sits in
+/// the Extensions SEQUENCE. `None` if it is missing or, against RFC 5280, repeated, since picking
+/// either copy would make the result depend on the order of the extensions.
+pub fn find_extension_entry(tbs: &[u8], oid: &[u8]) -> Option<Extension> {
+    let mut matches = extensions(tbs)?.into_iter().filter(|ext| &tbs[ext.oid.clone()] == oid);
+    match (matches.next(), matches.next()) {
+        (Some(ext), None) => Some(ext),
+        _ => None,
+    }
+}
+
+/// Locates the `extnValue` contents of the extension identified by `oid` (DER contents, no tag/length).
+pub fn find_extension(tbs: &[u8], oid: &[u8]) -> Option<Range<usize>> {
+    find_extension_entry(tbs, oid).map(|ext| ext.value)
+}
+
+/// Extracts the dNSName entries from a subjectAltName `extnValue` (a GeneralNames SEQUENCE).
+/// Other GeneralName kinds are skipped.
+pub fn san_dns_names(san: &[u8]) -> Option<Vec<String>> {
+    let seq = read_tlv(san, 0)?;
+    if seq.tag != TAG_SEQUENCE || seq.end() != san.len() {
+        return None;
+    }
+    children(san, &seq)?
+        .into_iter()
+        .filter(|name| name.tag == TAG_DNS_NAME)
+        .map(|name| String::from_utf8(san[name.content()].to_vec()).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::{fixture_path, permute_extensions};
+    use x509_parser::pem::parse_x509_pem;
+
+    fn tbs_from_pem(path: &str) -> Vec<u8> {
+        let pem = std::fs::read(fixture_path(path)).expect("Failed to read PEM file");
+        let pem = parse_x509_pem(&pem).expect("Failed to parse PEM").1;
+        let cert = pem.parse_x509().expect("Failed to parse PEM certificate");
+        cert.tbs_certificate.as_ref().to_vec()
+    }
+
+    #[test]
+    fn test_extension_count() {
+        assert_eq!(extension_count(&tbs_from_pem("./certs/cert_3.pem")), Some(10));
+        assert_eq!(extension_count(&tbs_from_pem("./certs/san_leaf.pem")), Some(5));
+    }
+
+    #[test]
+    fn test_find_basic_constraints() {
+        let tbs = tbs_from_pem("./certs/cert_3.pem");
+        let range = find_extension(&tbs, OID_BASIC_CONSTRAINTS).unwrap();
+        // CA:FALSE encodes as an empty SEQUENCE
+        assert_eq!(&tbs[range], &[0x30, 0x00]);
+        assert_eq!(find_extension(&tbs, OID_SUBJECT_ALT_NAME), None);
+    }
+
+    #[test]
+    fn test_find_subject_alt_name() {
+        let tbs = tbs_from_pem("./certs/san_leaf.pem");
+        let range = find_extension(&tbs, OID_SUBJECT_ALT_NAME).unwrap();
+        assert_eq!(range.len(), 49);
+        assert_eq!(&tbs[range.start..range.start + 4], &[0x30, 0x2f, 0x82, 0x0b]);
+        assert_eq!(&tbs[range.start + 4..range.start + 15], b"example.com");
+    }
+
+    #[test]
+    fn test_san_dns_names() {
+        let tbs = tbs_from_pem("./certs/san_leaf.pem");
+        let range = find_extension(&tbs, OID_SUBJECT_ALT_NAME).unwrap();
+        assert_eq!(
+            san_dns_names(&tbs[range]).unwrap(),
+            vec!["example.com", "www.example.com", "api.example.com"]
+        );
+    }
+
+    #[test]
+    fn test_rsa_spki_layout() {
+        let tbs = tbs_from_pem("./certs/cert_3.pem");
+        let spki = subject_public_key_info(&tbs).unwrap();
+        let prefix = rsa_spki_prefix(2048);
+        assert_eq!(prefix.len(), 33);
+        assert_eq!(&tbs[spki.start..spki.start + prefix.len()], prefix.as_slice());
+        assert_eq!(spki.end(), spki.start + prefix.len() + 256 + RSA_SPKI_SUFFIX.len());
+        assert_eq!(&tbs[spki.end() - RSA_SPKI_SUFFIX.len()..spki.end()], RSA_SPKI_SUFFIX);
+
+        let tbs = tbs_from_pem("./certs/test_ca_4096.pem");
+        let spki = subject_public_key_info(&tbs).unwrap();
+        assert_eq!(&tbs[spki.start..spki.start + 33], rsa_spki_prefix(4096).as_slice());
+    }
+
+    #[test]
+    fn test_oid_to_string() {
+        assert_eq!(oid_to_string(OID_SUBJECT_ALT_NAME).unwrap(), "2.5.29.17");
+        assert_eq!(oid_to_string(&RSA_ALGORITHM_IDENTIFIER[4..13]).unwrap(), "1.2.840.113549.1.1.1");
+        assert_eq!(oid_to_string(&[0x2a, 0x86]), None);
+        assert_eq!(oid_to_string(&[]), None);
+    }
+
+    #[test]
+    fn test_extension_order_independence() {
+        let value = |tbs: &[u8], oid: &[u8]| find_extension_entry(tbs, oid).map(|ext| (ext.critical, tbs[ext.value].to_vec()));
+        for path in ["./certs/cert_3.pem", "./certs/san_leaf.pem"] {
+            let tbs = tbs_from_pem(path);
+            let oids = [OID_BASIC_CONSTRAINTS, OID_KEY_USAGE, OID_SUBJECT_ALT_NAME];
+            let expected = oids.map(|oid| value(&tbs, oid));
+            assert!(expected[0].is_some() && expected[1].is_some());
+            let count = extension_count(&tbs).unwrap();
+            for shift in 1..count {
+                let reordered = permute_extensions(&tbs, |exts| exts.rotate_left(shift));
+                assert_ne!(reordered, tbs);
+                assert_eq!(extension_count(&reordered), Some(count));
+                assert_eq!(oids.map(|oid| value(&reordered, oid)), expected);
+            }
+            let reversed = permute_extensions(&tbs, |exts| exts.reverse());
+            assert_eq!(oids.map(|oid| value(&reversed, oid)), expected);
+        }
+
+        let tbs = tbs_from_pem("./certs/san_leaf.pem");
+        let reversed = permute_extensions(&tbs, |exts| exts.reverse());
+        let names = |tbs: &[u8]| san_dns_names(&tbs[find_extension(tbs, OID_SUBJECT_ALT_NAME).unwrap()]);
+        assert_eq!(names(&reversed), names(&tbs));
+
+        // A repeated extension has no single answer in any order
+        let repeated = permute_extensions(&tbs, |exts| exts.push(exts[0].clone()));
+        assert_eq!(extension_count(&repeated), Some(6));
+        assert_eq!(find_extension(&repeated, OID_BASIC_CONSTRAINTS), None);
+        assert!(find_extension(&repeated, OID_KEY_USAGE).is_some());
+    }
+
+    #[test]
+    fn test_truncated_tbs_is_rejected() {
+        let tbs = tbs_from_pem("./certs/cert_3.pem");
+        assert_eq!(extension_count(&tbs[..tbs.len() - 1]), None);
+        assert_eq!(find_extension(&tbs[..tbs.len() - 1], OID_BASIC_CONSTRAINTS), None);
+    }
+}
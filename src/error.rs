@@ -0,0 +1,103 @@
+use std::fmt;
+
+use crate::DigestAlgorithm;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CertError {
+    /// The modulus is shorter than the configured minimum.
+    WeakKey { bits: u64, min_bits: u64 },
+    /// The DER doesn't have the expected certificate structure.
+    MalformedStructure(&'static str),
+    /// BER rather than DER: the TLV at `offset` has an indefinite length. The circuit still
+    /// verifies the signature over the raw TBS bytes, but fields can't be extracted.
+    NotDer { offset: usize },
+    /// The digest instance column doesn't have the configured digest's length.
+    DigestLengthMismatch { algorithm: DigestAlgorithm, expected: usize, actual: usize },
+    /// Wrong number of instance vectors in the public inputs.
+    InstanceCount { expected: usize, actual: usize },
+    /// An instance vector has the wrong length.
+    InstanceLength { column: &'static str, expected: usize, actual: usize },
+    /// The certificate is signed with something other than sha256WithRSAEncryption. `name` is
+    /// `"unknown"` for OIDs missing from `cert::SIGNATURE_ALGORITHMS`.
+    UnsupportedSignatureAlgorithm { oid: String, name: &'static str },
+    /// A key that should be RSA, such as an issuer key, is of another algorithm. `name` is
+    /// `"unknown"` for OIDs missing from `cert::KEY_ALGORITHMS`.
+    UnsupportedKeyAlgorithm { oid: String, name: &'static str },
+    /// A SubjectPublicKeyInfo of the right algorithm holds a key that doesn't decode.
+    MalformedPublicKey(&'static str),
+    /// A JSON Web Key isn't a usable RSA key.
+    InvalidJwk(&'static str),
+    /// An input that should be standard base64, such as a WebCrypto key or signature, isn't.
+    InvalidBase64(&'static str),
+    /// The modulus has more bits than the circuit's `bits_len`. Shorter moduli are zero-padded.
+    ModulusTooLarge { bits: u64, bits_len: usize },
+    /// The public exponent has more bits than `exp_limb_bits` allows a `variable_exponent` circuit.
+    ExponentTooLarge { bits: usize, exp_limb_bits: usize },
+    /// The message doesn't fit any SHA256 slot; `max` is the longest that would.
+    MessageTooLong { len: usize, max: usize },
+    /// The messageDigest of signed attributes isn't the digest of the content they're checked against.
+    ContentDigestMismatch,
+    /// The signature doesn't verify under the given key.
+    InvalidSignature,
+    /// The DigestInfo recovered from the signature names another digest algorithm than the one the
+    /// message was hashed with. `name` is `"unknown"` for OIDs missing from `cert::DIGEST_ALGORITHMS`.
+    DigestAlgorithmMismatch { expected: DigestAlgorithm, oid: String, name: &'static str },
+    /// The params configure more lookup arguments than a backend allows.
+    TooManyLookups { count: usize, max: usize },
+    /// No certificate in the bundle issued the one with this issuer DN, yet other certificates
+    /// were left over.
+    IncompleteChain { issuer: String },
+}
+
+impl fmt::Display for CertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CertError::WeakKey { bits, min_bits } => {
+                write!(f, "modulus is {bits} bits, below the {min_bits}-bit minimum")
+            }
+            CertError::MalformedStructure(reason) => write!(f, "malformed certificate: {reason}"),
+            CertError::NotDer { offset } => {
+                write!(f, "indefinite-length BER encoding at byte {offset}; certificates must be DER")
+            }
+            CertError::DigestLengthMismatch { algorithm, expected, actual } => write!(
+                f,
+                "digest instance has {actual} elements but {algorithm:?} digests are {expected} bytes"
+            ),
+            CertError::InstanceCount { expected, actual } => {
+                write!(f, "expected {expected} instance columns, got {actual}")
+            }
+            CertError::InstanceLength { column, expected, actual } => {
+                write!(f, "instance column `{column}` should have {expected} elements, got {actual}")
+            }
+            CertError::UnsupportedSignatureAlgorithm { oid, name } => {
+                write!(f, "unsupported signature algorithm {name} ({oid})")
+            }
+            CertError::UnsupportedKeyAlgorithm { oid, name } => {
+                write!(f, "key algorithm is {name} ({oid}), not rsaEncryption")
+            }
+            CertError::MalformedPublicKey(reason) => write!(f, "malformed public key: {reason}"),
+            CertError::InvalidJwk(reason) => write!(f, "invalid JWK: {reason}"),
+            CertError::InvalidBase64(what) => write!(f, "{what} is not standard base64"),
+            CertError::ModulusTooLarge { bits, bits_len } => {
+                write!(f, "modulus is {bits} bits, more than the circuit's {bits_len}")
+            }
+            CertError::ExponentTooLarge { bits, exp_limb_bits } => {
+                write!(f, "public exponent is {bits} bits, more than the {exp_limb_bits} of exp_limb_bits")
+            }
+            CertError::MessageTooLong { len, max } => {
+                write!(f, "message is {len} bytes, longer than the {max}-byte maximum")
+            }
+            CertError::ContentDigestMismatch => write!(f, "messageDigest attribute doesn't match the content digest"),
+            CertError::InvalidSignature => write!(f, "signature doesn't verify under the signer key"),
+            CertError::DigestAlgorithmMismatch { expected, oid, name } => {
+                write!(f, "signature is over a {name} ({oid}) digest, but the message was hashed with {expected:?}")
+            }
+            CertError::TooManyLookups { count, max } => {
+                write!(f, "{count} lookup arguments configured, more than the {max} allowed")
+            }
+            CertError::IncompleteChain { issuer } => write!(f, "chain is missing the certificate of {issuer}"),
+        }
+    }
+}
+
+impl std::error::Error for CertError {}
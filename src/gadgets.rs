@@ -0,0 +1,304 @@
+use halo2_base::{
+    gates::{range::RangeConfig, GateInstructions, RangeInstructions},
+    utils::PrimeField,
+    AssignedValue,
+    Context,
+    QuantumCell::{Constant, Existing},
+};
+use halo2_base::halo2_proofs::circuit::Value;
+
+use crate::der::{rsa_spki_prefix, RSA_SPKI_SUFFIX, TAG_SEQUENCE};
+use crate::TBS_V3_HEADER;
+
+/// Selects `bytes[start..start + len]` for a witnessed `start`, zero-filled past the end of `bytes`.
+///
+/// An out-of-range `start` selects all zeros, so callers should anchor the window with a known prefix.
+/// Costs one indicator over `bytes` plus one inner product of `bytes.len()` per output byte.
+pub fn select_window<'v, F: PrimeField>(
+    gate: &impl GateInstructions<F>,
+    ctx: &mut Context<'v, F>,
+    bytes: &[AssignedValue<'v, F>],
+    start: &AssignedValue<'v, F>,
+    len: usize,
+) -> Vec<AssignedValue<'v, F>> {
+    let indicator = gate.idx_to_indicator(ctx, Existing(start), bytes.len());
+    (0..len)
+        .map(|j| {
+            let shifted = (0..bytes.len()).map(|i| match bytes.get(i + j) {
+                Some(byte) => Existing(byte),
+                None => Constant(F::zero()),
+            });
+            gate.select_by_indicator(ctx, shifted, indicator.iter())
+        })
+        .collect()
+}
+
+/// Packs big-endian `bytes` into little-endian limbs of `limb_bytes` (at most 16) bytes each, the
+/// layout of halo2-rsa's assigned integers.
+pub fn pack_be_bytes<'v, F: PrimeField>(
+    gate: &impl GateInstructions<F>,
+    ctx: &mut Context<'v, F>,
+    bytes: &[AssignedValue<'v, F>],
+    limb_bytes: usize,
+) -> Vec<AssignedValue<'v, F>> {
+    let bases = (0..limb_bytes).map(|i| Constant(F::from_u128(1u128 << (8 * i)))).collect::<Vec<_>>();
+    bytes
+        .rchunks(limb_bytes)
+        .map(|chunk| gate.inner_product(ctx, chunk.iter().rev().map(Existing), bases.clone()))
+        .collect()
+}
+
+/// Splits little-endian `limb_bits`-bit limbs into range-checked bytes, least significant first.
+pub fn unpack_le_bytes<'v, F: PrimeField>(
+    range: &RangeConfig<F>,
+    ctx: &mut Context<'v, F>,
+    limbs: &[AssignedValue<'v, F>],
+    limb_bits: usize,
+) -> Vec<AssignedValue<'v, F>> {
+    let gate = range.gate();
+    let limb_bytes = limb_bits / 8;
+    let bases = (0..limb_bytes).map(|i| Constant(F::from_u128(1u128 << (8 * i)))).collect::<Vec<_>>();
+    let mut bytes = vec![];
+    for limb in limbs {
+        let limb_value = limb.value().map(|limb| limb.get_lower_128());
+        let limb_le = (0..limb_bytes)
+            .map(|i| {
+                let byte = gate.load_witness(ctx, limb_value.map(|limb| F::from(((limb >> (8 * i)) & 0xff) as u64)));
+                range.range_check(ctx, &byte, 8);
+                byte
+            })
+            .collect::<Vec<_>>();
+        let packed = gate.inner_product(ctx, limb_le.iter().map(Existing), bases.clone());
+        gate.assert_equal(ctx, Existing(&packed), Existing(limb));
+        bytes.extend(limb_le);
+    }
+    bytes
+}
+
+/// 1 if the byte string `a` sorts strictly before `b` (of the same length), else 0. Strings
+/// zero-padded to that length compare like the unpadded ones, as long as they don't contain zeros.
+pub fn is_lex_less<'v, F: PrimeField>(
+    range: &RangeConfig<F>,
+    ctx: &mut Context<'v, F>,
+    a: &[AssignedValue<'v, F>],
+    b: &[AssignedValue<'v, F>],
+) -> AssignedValue<'v, F> {
+    is_lex_less_bits(range, ctx, a, b, 8)
+}
+
+/// `is_lex_less` over `num_bits`-bit digits rather than bytes, such as big-endian limbs.
+pub fn is_lex_less_bits<'v, F: PrimeField>(
+    range: &RangeConfig<F>,
+    ctx: &mut Context<'v, F>,
+    a: &[AssignedValue<'v, F>],
+    b: &[AssignedValue<'v, F>],
+    num_bits: usize,
+) -> AssignedValue<'v, F> {
+    let gate = range.gate();
+    // `less` at the first differing byte, weighted by whether all earlier bytes are equal
+    let mut terms = vec![];
+    let mut prefix_equal: Option<AssignedValue<'v, F>> = None;
+    for (a, b) in a.iter().zip(b) {
+        let less = range.is_less_than(ctx, Existing(a), Existing(b), num_bits);
+        let equal = gate.is_equal(ctx, Existing(a), Existing(b));
+        prefix_equal = Some(match &prefix_equal {
+            None => {
+                terms.push(less);
+                equal
+            }
+            Some(prefix_equal) => {
+                terms.push(gate.mul(ctx, Existing(prefix_equal), Existing(&less)));
+                gate.mul(ctx, Existing(prefix_equal), Existing(&equal))
+            }
+        });
+    }
+    gate.sum(ctx, terms.iter().map(Existing))
+}
+
+/// Constrains an RSA SubjectPublicKeyInfo (`bits_len`-bit modulus, e = 65537) at the witnessed
+/// `offset` in `msg_bytes` and returns its modulus as little-endian limbs, ready to compare with
+/// an assigned public key. The DER around the modulus is the anchor, as in `select_window`.
+pub fn assign_spki_modulus<'v, F: PrimeField>(
+    gate: &impl GateInstructions<F>,
+    ctx: &mut Context<'v, F>,
+    msg_bytes: &[AssignedValue<'v, F>],
+    offset: usize,
+    bits_len: usize,
+    limb_bits: usize,
+) -> Vec<AssignedValue<'v, F>> {
+    let prefix = rsa_spki_prefix(bits_len);
+    let modulus_len = bits_len / 8;
+    let start = gate.load_witness(ctx, Value::known(F::from(offset as u64)));
+    let window = select_window(gate, ctx, msg_bytes, &start, prefix.len() + modulus_len + RSA_SPKI_SUFFIX.len());
+    let (head, rest) = window.split_at(prefix.len());
+    let (modulus, tail) = rest.split_at(modulus_len);
+    for (byte, expected) in head.iter().zip(&prefix).chain(tail.iter().zip(RSA_SPKI_SUFFIX)) {
+        gate.assert_is_const(ctx, byte, F::from(*expected as u64));
+    }
+    pack_be_bytes(gate, ctx, modulus, limb_bits / 8)
+}
+
+/// Offset of the TBS `signature` AlgorithmIdentifier in `msg_bytes`, fixed by the TBS layout: a
+/// 2-byte long-form header, the v3 version and a short-form serialNumber put it at
+/// `11 + serial length`.
+pub fn assign_tbs_signature_start<'v, F: PrimeField>(
+    range: &RangeConfig<F>,
+    ctx: &mut Context<'v, F>,
+    msg_bytes: &[AssignedValue<'v, F>],
+) -> AssignedValue<'v, F> {
+    let gate = range.gate();
+    gate.assert_is_const(ctx, &msg_bytes[0], F::from(TAG_SEQUENCE as u64));
+    gate.assert_is_const(ctx, &msg_bytes[1], F::from(0x82));
+    for (byte, expected) in msg_bytes[4..].iter().zip(TBS_V3_HEADER) {
+        gate.assert_is_const(ctx, byte, F::from(expected as u64));
+    }
+    let serial_len = &msg_bytes[4 + TBS_V3_HEADER.len()];
+    range.check_less_than_safe(ctx, serial_len, 128);
+    gate.add(ctx, Existing(serial_len), Constant(F::from(5 + TBS_V3_HEADER.len() as u64)))
+}
+
+/// `(offset, length)` of the issuer Name TLV in `msg_bytes`, found by walking the TBS from the
+/// signature AlgorithmIdentifier (short form) over to the issuer (short form or `81 xx`). The
+/// length is the header plus the encoded content length, so neither can point at other bytes.
+pub fn assign_issuer_location<'v, F: PrimeField>(
+    range: &RangeConfig<F>,
+    ctx: &mut Context<'v, F>,
+    msg_bytes: &[AssignedValue<'v, F>],
+) -> (AssignedValue<'v, F>, AssignedValue<'v, F>) {
+    let gate = range.gate();
+    let sig_alg_start = assign_tbs_signature_start(range, ctx, msg_bytes);
+    let sig_alg = select_window(gate, ctx, msg_bytes, &sig_alg_start, 2);
+    gate.assert_is_const(ctx, &sig_alg[0], F::from(TAG_SEQUENCE as u64));
+    range.check_less_than_safe(ctx, &sig_alg[1], 128);
+    let sig_alg_len = gate.add(ctx, Existing(&sig_alg[1]), Constant(F::from(2)));
+    let issuer_start = gate.add(ctx, Existing(&sig_alg_start), Existing(&sig_alg_len));
+
+    let issuer = select_window(gate, ctx, msg_bytes, &issuer_start, 3);
+    gate.assert_is_const(ctx, &issuer[0], F::from(TAG_SEQUENCE as u64));
+    let is_short = range.is_less_than(ctx, Existing(&issuer[1]), Constant(F::from(0x80)), 8);
+    let is_long = gate.is_equal(ctx, Existing(&issuer[1]), Constant(F::from(0x81)));
+    let is_supported = gate.add(ctx, Existing(&is_short), Existing(&is_long));
+    gate.assert_is_const(ctx, &is_supported, F::one());
+    let issuer_content_len = gate.select(ctx, Existing(&issuer[2]), Existing(&issuer[1]), Existing(&is_long));
+    let issuer_header_len = gate.add(ctx, Existing(&is_long), Constant(F::from(2)));
+    let issuer_len = gate.add(ctx, Existing(&issuer_header_len), Existing(&issuer_content_len));
+    (issuer_start, issuer_len)
+}
+
+/// Days before the 1st of each month in a common year.
+const DAYS_BEFORE_MONTH: [u64; 12] = [0, 31, 59, 90, 120, 151, 181, 212, 243, 273, 304, 334];
+
+/// Seconds since the unix epoch of a UTCTime with the range-checked `YYMMDDHHMMSS` `digits`, for
+/// years 1970 to 2049; earlier years fail a range check. Every year in range divisible by 4 is a
+/// leap year, 2000 included. The date isn't validated, so a February 30th counts as March 2nd.
+pub fn utc_time_to_unix<'v, F: PrimeField>(
+    range: &RangeConfig<F>,
+    ctx: &mut Context<'v, F>,
+    digits: &[AssignedValue<'v, F>],
+) -> AssignedValue<'v, F> {
+    let gate = range.gate();
+    let fields = digits
+        .chunks(2)
+        .map(|pair| gate.mul_add(ctx, Existing(&pair[0]), Constant(F::from(10)), Existing(&pair[1])))
+        .collect::<Vec<_>>();
+    let (yy, month, day) = (&fields[0], &fields[1], &fields[2]);
+
+    // UTCTime years below 50 are 20YY, so the year is 1970 + yy + 100 * is_2000s - 70
+    let is_2000s = range.is_less_than(ctx, Existing(yy), Constant(F::from(50)), 7);
+    let years = gate.mul_add(ctx, Existing(&is_2000s), Constant(F::from(100)), Existing(yy));
+    let years = gate.sub(ctx, Existing(&years), Constant(F::from(70)));
+    range.range_check(ctx, &years, 7);
+
+    // There are (years + 1) / 4 leap years from 1970 up to the year, which is one itself if the
+    // remainder is 3
+    let years_plus_one = gate.add(ctx, Existing(&years), Constant(F::one()));
+    let (leap_years, rem) = years_plus_one
+        .value()
+        .map(|years| {
+            let years = years.get_lower_128();
+            (F::from_u128(years / 4), F::from_u128(years % 4))
+        })
+        .unzip();
+    let leap_years = gate.load_witness(ctx, leap_years);
+    let rem = gate.load_witness(ctx, rem);
+    range.range_check(ctx, &leap_years, 5);
+    range.range_check(ctx, &rem, 2);
+    let expected = gate.mul_add(ctx, Existing(&leap_years), Constant(F::from(4)), Existing(&rem));
+    gate.assert_equal(ctx, Existing(&expected), Existing(&years_plus_one));
+    let is_leap = gate.is_equal(ctx, Existing(&rem), Constant(F::from(3)));
+    let after_february = range.is_less_than(ctx, Constant(F::from(2)), Existing(month), 7);
+    let leap_day = gate.mul(ctx, Existing(&is_leap), Existing(&after_february));
+
+    let month_index = gate.sub(ctx, Existing(month), Constant(F::one()));
+    let indicator = gate.idx_to_indicator(ctx, Existing(&month_index), DAYS_BEFORE_MONTH.len());
+    let days_before_month =
+        gate.select_by_indicator(ctx, DAYS_BEFORE_MONTH.iter().map(|days| Constant(F::from(*days))), indicator.iter());
+    let days = gate.sum(ctx, [Existing(&leap_years), Existing(&days_before_month), Existing(&leap_day), Existing(day)]);
+    let days = gate.mul_add(ctx, Existing(&years), Constant(F::from(365)), Existing(&days));
+    // `day` counts from 1
+    let days = gate.sub(ctx, Existing(&days), Constant(F::one()));
+    let seconds = gate.inner_product(
+        ctx,
+        fields[3..].iter().map(Existing),
+        [3600, 60, 1].map(|unit| Constant(F::from(unit))),
+    );
+    gate.mul_add(ctx, Existing(&days), Constant(F::from(86400)), Existing(&seconds))
+}
+
+/// Constrains the `limb_bits`-bit `limb` to be odd, by witnessing `limb = 2 * half + 1` with a
+/// range-checked `half`.
+pub fn assert_odd<'v, F: PrimeField>(
+    range: &RangeConfig<F>,
+    ctx: &mut Context<'v, F>,
+    limb: &AssignedValue<'v, F>,
+    limb_bits: usize,
+) {
+    let gate = range.gate();
+    let half = limb.value().map(|limb| F::from_u128(limb.get_lower_128() >> 1));
+    let half = gate.load_witness(ctx, half);
+    range.range_check(ctx, &half, limb_bits - 1);
+    let expected = gate.mul_add(ctx, Existing(&half), Constant(F::from(2)), Constant(F::one()));
+    gate.assert_equal(ctx, Existing(&expected), Existing(limb));
+}
+
+/// Constrains that none of `primes` divides the integer with little-endian `limbs`.
+///
+/// Per prime, folds the limbs with weights `2^(limb_bits * i) mod p` into a sum congruent to the
+/// integer, then witnesses `sum = q * p + r` with a range-checked nonzero `r < p`.
+pub fn assert_no_small_factors<'v, F: PrimeField>(
+    range: &RangeConfig<F>,
+    ctx: &mut Context<'v, F>,
+    limbs: &[AssignedValue<'v, F>],
+    limb_bits: usize,
+    primes: &[u64],
+) {
+    let gate = range.gate();
+    for &p in primes {
+        let mut weight = 1u64;
+        let mut weights = vec![];
+        for _ in limbs {
+            weights.push(Constant(F::from(weight)));
+            for _ in 0..limb_bits {
+                weight = weight * 2 % p;
+            }
+        }
+        let sum = gate.inner_product(ctx, limbs.iter().map(Existing), weights);
+        // The sum stays below limbs.len() * 2^limb_bits * p, far from wrapping the field
+        let q_bits = limb_bits + (usize::BITS - limbs.len().leading_zeros()) as usize;
+        let (q, r) = sum
+            .value()
+            .map(|sum| {
+                let sum = sum.get_lower_128();
+                (F::from_u128(sum / p as u128), F::from_u128(sum % p as u128))
+            })
+            .unzip();
+        let q = gate.load_witness(ctx, q);
+        let r = gate.load_witness(ctx, r);
+        range.range_check(ctx, &q, q_bits);
+        range.check_less_than_safe(ctx, &r, p);
+        let expected = gate.mul_add(ctx, Existing(&q), Constant(F::from(p)), Existing(&r));
+        gate.assert_equal(ctx, Existing(&expected), Existing(&sum));
+        let divides = gate.is_zero(ctx, &r);
+        gate.assert_is_const(ctx, &divides, F::zero());
+    }
+}
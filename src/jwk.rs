@@ -0,0 +1,78 @@
+//! RSA keys from JSON Web Keys (RFC 7517), as served by OIDC/JWKS endpoints.
+use data_encoding::BASE64URL_NOPAD;
+use num_bigint::BigUint;
+use serde::Deserialize;
+
+use crate::CertError;
+
+/// The members of an RSA JWK the circuit needs. Other members (`alg`, `use`, `x5c`, ..) are ignored.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct JwkRsa {
+    pub kty: String,
+    #[serde(default)]
+    pub kid: Option<String>,
+    /// Modulus, base64url without padding.
+    pub n: String,
+    /// Public exponent, base64url without padding.
+    pub e: String,
+}
+
+impl JwkRsa {
+    pub fn modulus(&self) -> Result<BigUint, CertError> {
+        self.check_kty()?;
+        decode_uint(&self.n).ok_or(CertError::InvalidJwk("n is not base64url"))
+    }
+
+    pub fn exponent(&self) -> Result<BigUint, CertError> {
+        self.check_kty()?;
+        decode_uint(&self.e).ok_or(CertError::InvalidJwk("e is not base64url"))
+    }
+
+    fn check_kty(&self) -> Result<(), CertError> {
+        match self.kty.as_str() {
+            "RSA" => Ok(()),
+            _ => Err(CertError::InvalidJwk("kty is not RSA")),
+        }
+    }
+}
+
+/// Big-endian unsigned integer from base64url. RFC 7518 forbids padding, but some issuers add it.
+fn decode_uint(value: &str) -> Option<BigUint> {
+    let bytes = BASE64URL_NOPAD.decode(value.trim_end_matches('=').as_bytes()).ok()?;
+    Some(BigUint::from_bytes_be(&bytes))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{test_utils::CertFixture, CertificateVerificationCircuit, CircuitParams};
+    use halo2_base::halo2_proofs::{dev::MockProver, halo2curves::bn256::Fr};
+
+    #[test]
+    fn test_from_jwks() {
+        let fixture = CertFixture::load("./certs/san_leaf.pem", "./certs/test_ca.pem");
+        let jwks = serde_json::json!({
+            "keys": [{
+                "kty": "RSA",
+                "kid": "test-ca",
+                "use": "sig",
+                "alg": "RS256",
+                "n": BASE64URL_NOPAD.encode(&fixture.n_big.to_bytes_be()),
+                "e": "AQAB"
+            }]
+        });
+        let jwk: JwkRsa = serde_json::from_value(jwks["keys"][0].clone()).unwrap();
+        assert_eq!(jwk.modulus(), Ok(fixture.n_big.clone()));
+        assert_eq!(jwk.exponent(), Ok(BigUint::from(65537u32)));
+
+        let signature = fixture.sign_big.to_bytes_be();
+        let circuit = CertificateVerificationCircuit::<Fr>::from_jwk(&jwk, &signature, &fixture.msg, CircuitParams::default()).unwrap();
+        assert!(circuit.signature_is_valid());
+        circuit.params.set_current();
+        let prover = MockProver::run(circuit.params.k, &circuit, fixture.public_inputs()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let ec = JwkRsa { kty: "EC".to_string(), ..jwk };
+        assert_eq!(ec.modulus(), Err(CertError::InvalidJwk("kty is not RSA")));
+    }
+}
@@ -0,0 +1,181 @@
+//! Verification under one key of a public set, without revealing which.
+use halo2_base::{
+    gates::{GateInstructions, RangeInstructions},
+    utils::PrimeField,
+    QuantumCell::Existing,
+    halo2_proofs::{
+        plonk::{Circuit, ConstraintSystem, Error, Column, Instance},
+        circuit::{SimpleFloorPlanner, Layouter, Value, Cell},
+    },
+    SKIP_FIRST_PASS
+};
+use halo2_dynamic_sha256::Sha256DynamicConfig;
+use halo2_rsa::{RSAConfig, RSAInstructions, RSAPubE, RSAPublicKey, RSASignature};
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
+
+use crate::{configure_gadgets, modulus_instance, verify_pkcs1v15_signature, CertificateVerificationCircuit, CircuitParams, CircuitStats};
+
+/// Verifies a signature over `msg` under one of `N` candidate `params.bits_len`-bit keys with
+/// e = 65537. Public inputs are `[candidate limbs, message digest bytes]`, the limbs of each
+/// candidate as in `modulus_instance`, one candidate after the other. The index of the signing
+/// key is a private witness, so proofs for different keys of the same set look alike.
+pub struct KeySetCircuit<F: PrimeField, const N: usize> {
+    candidates: [BigUint; N],
+    index: usize,
+    sign_big: BigUint,
+    msg: Vec<u8>,
+    params: CircuitParams,
+    _f: std::marker::PhantomData<F>,
+}
+
+impl<F: PrimeField, const N: usize> KeySetCircuit<F, N> {
+    /// `index` is that of the key in `candidates` the signature verifies under.
+    pub fn new(candidates: [BigUint; N], index: usize, sign_big: BigUint, msg: Vec<u8>, params: CircuitParams) -> Self {
+        assert!(index < N, "index past the {N} candidates");
+        Self { candidates, index, sign_big, msg, params, _f: std::marker::PhantomData }
+    }
+
+    pub fn public_inputs(&self) -> Vec<Vec<F>> {
+        let limbs = self.candidates.iter().flat_map(|n_big| modulus_instance(n_big, &self.params)).collect();
+        let digest = Sha256::digest(&self.msg).iter().map(|byte| F::from(*byte as u64)).collect();
+        vec![limbs, digest]
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct KeySetConfig<F: PrimeField> {
+    rsa_config: RSAConfig<F>,
+    sha256_config: Sha256DynamicConfig<F>,
+    candidates_instance: Column<Instance>,
+    hash_instance: Column<Instance>,
+}
+
+impl<F: PrimeField, const N: usize> Circuit<F> for KeySetCircuit<F, N> {
+    type Config = KeySetConfig<F>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        unimplemented!();
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let params = CircuitParams::current();
+        let (rsa_config, sha256_config) = configure_gadgets(meta, &params, 1);
+        let candidates_instance = meta.instance_column();
+        meta.enable_equality(candidates_instance);
+        let hash_instance = meta.instance_column();
+        meta.enable_equality(hash_instance);
+
+        Self::Config {
+            rsa_config,
+            sha256_config,
+            candidates_instance,
+            hash_instance
+        }
+    }
+
+    fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+        let biguint_config = config.rsa_config.biguint_config();
+        config.sha256_config.load(&mut layouter)?;
+        biguint_config.range().load_lookup_table(&mut layouter)?;
+        let mut first_pass = SKIP_FIRST_PASS;
+        let (candidate_cells, hash_cells) = layouter.assign_region(
+            || "key set verifier",
+            |region| {
+                if first_pass {
+                    first_pass = false;
+                    return Ok((vec![], vec![]));
+                }
+
+                let mut aux = biguint_config.new_context(region);
+                let ctx = &mut aux;
+                let range = biguint_config.range();
+                let gate = range.gate();
+                let mut sha256_config = config.sha256_config.clone();
+
+                // The candidates are bound to the instance, so they need no range checks
+                let candidates = self
+                    .candidates
+                    .iter()
+                    .map(|n_big| {
+                        modulus_instance::<F>(n_big, &self.params)
+                            .into_iter()
+                            .map(|limb| gate.load_witness(ctx, Value::known(limb)))
+                            .collect::<Vec<_>>()
+                    })
+                    .collect::<Vec<_>>();
+                let index = gate.load_witness(ctx, Value::known(F::from(self.index as u64)));
+                range.check_less_than_safe(ctx, &index, N as u64);
+                let indicator = gate.idx_to_indicator(ctx, Existing(&index), N);
+
+                let e_fix = RSAPubE::Fix(BigUint::from(CertificateVerificationCircuit::<F>::DEFAULT_E));
+                let public_key = RSAPublicKey::new(Value::known(self.candidates[self.index].clone()), e_fix);
+                let public_key = config.rsa_config.assign_public_key(ctx, public_key)?;
+                for (i, limb) in public_key.n.limbs().iter().enumerate() {
+                    let selected = gate.select_by_indicator(ctx, candidates.iter().map(|limbs| Existing(&limbs[i])), indicator.iter());
+                    gate.assert_equal(ctx, Existing(limb), Existing(&selected));
+                }
+
+                let signature = RSASignature::new(Value::known(self.sign_big.clone()));
+                let signature = config.rsa_config.assign_signature(ctx, signature)?;
+                let hash_result = sha256_config.digest(ctx, &self.msg, None)?;
+                let is_valid = verify_pkcs1v15_signature(&config.rsa_config, ctx, &public_key, &hash_result.output_bytes, &signature, self.params.montgomery_exp)?;
+                gate.assert_is_const(ctx, &is_valid, F::one());
+
+                range.finalize(ctx);
+                CircuitStats {
+                    total_advice: ctx.total_advice,
+                    total_fixed: ctx.total_fixed,
+                    lookup_cells: ctx.cells_to_lookup.len(),
+                }.record();
+                let candidate_cells = candidates.iter().flatten().map(|v| v.cell()).collect::<Vec<Cell>>();
+                let hash_cells = hash_result.output_bytes.iter().map(|v| v.cell()).collect::<Vec<Cell>>();
+                Ok((candidate_cells, hash_cells))
+            },
+        )?;
+        for (i, cell) in candidate_cells.into_iter().enumerate() {
+            layouter.constrain_instance(cell, config.candidates_instance, i)?;
+        }
+        for (i, cell) in hash_cells.into_iter().enumerate() {
+            layouter.constrain_instance(cell, config.hash_instance, i)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::load_fixture;
+    use halo2_base::halo2_proofs::{dev::MockProver, halo2curves::bn256::Fr};
+
+    #[test]
+    fn test_key_set_membership() {
+        let params = CircuitParams::default();
+        let (issuer_n, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let (cert_1_n, _, _) = load_fixture("./certs/cert_2.pem", "./certs/cert_1.pem");
+        let (test_ca_n, _, _) = load_fixture("./certs/san_leaf.pem", "./certs/test_ca.pem");
+        let candidates = [cert_1_n, issuer_n, test_ca_n];
+
+        let circuit = KeySetCircuit::<Fr, 3>::new(candidates.clone(), 1, sign_big.clone(), tbs.clone(), params.clone());
+        circuit.params.set_current();
+        let public_inputs = circuit.public_inputs();
+        assert_eq!(public_inputs[0].len(), 3 * params.num_limbs());
+        let prover = MockProver::run(params.k, &circuit, public_inputs.clone()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // Another key of the set didn't sign it
+        let circuit = KeySetCircuit::<Fr, 3>::new(candidates.clone(), 2, sign_big.clone(), tbs.clone(), params.clone());
+        let prover = MockProver::run(params.k, &circuit, public_inputs.clone()).unwrap();
+        assert!(prover.verify().is_err());
+
+        // The signing key swapped out of the public set
+        let mut other_set = public_inputs;
+        let num_limbs = params.num_limbs();
+        other_set[0][num_limbs..2 * num_limbs].copy_from_slice(&modulus_instance::<Fr>(&candidates[2], &params));
+        let circuit = KeySetCircuit::<Fr, 3>::new(candidates, 1, sign_big, tbs, params.clone());
+        let prover = MockProver::run(params.k, &circuit, other_set).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
@@ -1,9 +1,11 @@
 use halo2_base::{
     AssignedValue,
-    QuantumCell,
-    utils::PrimeField, 
+    Context,
+    QuantumCell::{Constant, Existing},
+    utils::{biguint_to_fe, PrimeField},
     gates::{
         GateInstructions,
+        RangeInstructions,
         range::{RangeConfig, RangeStrategy}
     },
     halo2_proofs::{
@@ -21,270 +23,3051 @@ use halo2_rsa::{
     RSAPublicKey, 
     RSASignature,
     RSAInstructions,
-    RSASignatureVerifier,
+    AssignedRSAPubE,
+    AssignedRSAPublicKey,
+    AssignedRSASignature,
     BigUintConfig,
     big_uint::decompose_biguint,
     BigUintInstructions
 };
+use std::ops::Range;
+
 use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
+use x509_parser::pem::parse_x509_pem;
+
+pub mod agility;
+pub mod byte_range;
+pub mod cert;
+pub mod chain;
+pub mod commitment;
+pub mod der;
+mod error;
+mod gadgets;
+#[cfg(feature = "jwk")]
+pub mod jwk;
+pub mod key_set;
+pub mod merkle;
+pub mod montgomery;
+pub mod multi_sig;
+mod params;
+pub mod pkcs7;
+#[cfg(feature = "bn256")]
+pub mod prover;
+pub mod report;
+pub mod rsa_exp;
+pub mod same_issuer;
+mod stats;
+pub mod streaming;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
+#[cfg(feature = "webcrypto")]
+pub mod webcrypto;
+
+pub use agility::DigestAgilityCircuit;
+pub use chain::ChainCircuit;
+pub use byte_range::ByteRange;
+pub use error::CertError;
+pub use key_set::KeySetCircuit;
+pub use merkle::MerkleTrustedKeyCircuit;
+pub use multi_sig::MultiSignatureCircuit;
+pub use pkcs7::Pkcs7SignatureCircuit;
+pub use report::{ChainLinkReport, VerificationReport};
+pub use rsa_exp::RsaExpCircuit;
+pub use same_issuer::SameIssuerCircuit;
+pub use streaming::StreamingDigest;
+
+/// Concrete proving field. `bn256` pairs with the KZG backend (`ParamsKZG<Bn256>`, EVM verifiers);
+/// `pasta` pairs with the IPA backend over Vesta (`ParamsIPA<EqAffine>`).
+///
+/// The circuits are generic over any halo2-base field of about 254 bits or more, which both are:
+/// halo2-rsa sums products of two `limb_bits`-bit limbs across a whole integer in one element, and
+/// `DigestEncoding::EvmPacked` packs 128 bits per element. Smaller fields would wrap silently.
+#[cfg(feature = "bn256")]
+pub type Field = halo2_base::halo2_proofs::halo2curves::bn256::Fr;
+#[cfg(all(feature = "pasta", not(feature = "bn256")))]
+pub type Field = halo2_base::halo2_proofs::halo2curves::pasta::Fp;
 
-struct CertificateVerificationCircuit<F: PrimeField> {
+use gadgets::{
+    assert_no_small_factors, assert_odd, assign_issuer_location, assign_spki_modulus, assign_tbs_signature_start, is_lex_less, pack_be_bytes,
+    select_window, unpack_le_bytes, utc_time_to_unix
+};
+use stats::CostTracker;
+
+pub use params::{CircuitParams, DigestAlgorithm, DigestEncoding, InstanceColumn, InstanceColumnSpec, InstanceLayout, ValidityMode, num_limbs, SMALL_PRIMES};
+pub use stats::{assert_near_baseline, assert_within_budget, CircuitStats, CostReport, GadgetCost};
+
+pub struct CertificateVerificationCircuit<F: PrimeField> {
     n_big: BigUint,
     sign_big: BigUint,
     msg: Vec<u8>,
+    /// SHA-256 of `msg` when it was computed while reading it (see `from_streaming_digest`).
+    msg_digest: Option<[u8; 32]>,
+    /// Lengths of the `ByteRange` segments `msg` was concatenated from.
+    segment_lens: Vec<usize>,
+    dns_name: Vec<u8>,
+    /// `YYYYMMDDHHMMSS` to check notAfter against (see `time_instance`).
+    current_time: u64,
+    params: CircuitParams,
+    /// Cleared by `prover::ProvingContext`, whose proving key already holds the lookup tables.
+    load_tables: bool,
     _f: std::marker::PhantomData<F>,
 }
 
 impl<F: PrimeField> CertificateVerificationCircuit<F> {
-    const BITS_LEN:usize = 2048;
-    const LIMB_BITS:usize = 64;
-    const EXP_LIMB_BITS:usize = 5;
+    /// Exponent of the issuer keys found inside certificates (see `der::RSA_SPKI_SUFFIX`).
     const DEFAULT_E: u128 = 65537;
-    const NUM_ADVICE:usize = 40;
-    const NUM_FIXED:usize = 1;
-    const NUM_LOOKUP_ADVICE:usize = 4;
-    const LOOKUP_BITS:usize = 12;
 
-    const MSG_LEN: usize = 1280;
-    const SHA256_LOOKUP_BITS: usize = 8;        // is this enough?
-    const SHA256_LOOKUP_ADVICE: usize = 8;      // might need to increase this   
+    /// `msg` is moved in when it's a `Vec<u8>`, so large messages aren't copied; slices are copied once.
+    pub fn new(n_big: BigUint, sign_big: BigUint, msg: impl Into<Vec<u8>>, params: CircuitParams) -> Self {
+        let msg = msg.into();
+        let segment_lens = vec![msg.len()];
+        Self {
+            n_big,
+            sign_big,
+            msg,
+            msg_digest: None,
+            segment_lens,
+            dns_name: vec![],
+            current_time: 0,
+            params,
+            load_tables: true,
+            _f: std::marker::PhantomData
+        }
+    }
+
+    /// Like `new`, but rejects a `msg` longer than `params.max_supported_msg_len()` up front
+    /// instead of leaving the SHA256 chip to fail during synthesis.
+    pub fn try_new(n_big: BigUint, sign_big: BigUint, msg: impl Into<Vec<u8>>, params: CircuitParams) -> Result<Self, CertError> {
+        let msg = msg.into();
+        let max = params.max_supported_msg_len();
+        if msg.len() > max {
+            return Err(CertError::MessageTooLong { len: msg.len(), max });
+        }
+        if n_big.bits() > params.bits_len as u64 {
+            return Err(CertError::ModulusTooLarge { bits: n_big.bits(), bits_len: params.bits_len });
+        }
+        params.check_exponent()?;
+        Ok(Self::new(n_big, sign_big, msg, params))
+    }
+
+    /// Like `try_new`, but `msg` may be the whole DER certificate rather than its tbsCertificate, a
+    /// common mistake: when `msg` doesn't verify natively but is a certificate, its TBS is signed
+    /// over instead. `params.signed_range` circuits take the whole certificate and are left alone.
+    ///
+    /// With `ValidityMode::RequireValid` no proof exists for a message that still doesn't verify,
+    /// so that is the error of `check_signature` here instead of a failed proof later.
+    pub fn from_tbs_or_certificate(n_big: BigUint, sign_big: BigUint, msg: impl Into<Vec<u8>>, params: CircuitParams) -> Result<Self, CertError> {
+        let mut circuit = Self::try_new(n_big, sign_big, msg, params)?;
+        if !circuit.params.signed_range && !circuit.signature_is_valid() {
+            if let Ok((tbs, _)) = cert::signed_region(&circuit.msg) {
+                circuit = Self::try_new(circuit.n_big, circuit.sign_big, tbs, circuit.params)?;
+            }
+        }
+        if circuit.params.validity == ValidityMode::RequireValid {
+            circuit.check_signature()?;
+        }
+        Ok(circuit)
+    }
+
+    /// Like `try_new`, with the message and its digest from a `StreamingDigest`, so the message
+    /// isn't hashed again for the public inputs.
+    pub fn from_streaming_digest(
+        n_big: BigUint,
+        sign_big: BigUint,
+        stream: StreamingDigest,
+        params: CircuitParams
+    ) -> Result<Self, CertError> {
+        let (digest, msg) = stream.finalize();
+        Ok(Self { msg_digest: Some(digest), ..Self::try_new(n_big, sign_big, msg, params)? })
+    }
+
+    /// Signs over the concatenated `ranges` of `document`, as for a PDF `/ByteRange`. At most
+    /// `params.byte_range_segments` ranges.
+    pub fn from_byte_ranges(
+        n_big: BigUint,
+        sign_big: BigUint,
+        document: &[u8],
+        ranges: &[ByteRange],
+        params: CircuitParams
+    ) -> Result<Self, CertError> {
+        if ranges.len() > params.byte_range_segments {
+            return Err(CertError::InstanceLength { column: InstanceColumn::ByteRange.name(), expected: params.byte_range_segments, actual: ranges.len() });
+        }
+        let msg = byte_range::concat(document, ranges)?;
+        let segment_lens = ranges.iter().map(|range| range.len).collect();
+        Ok(Self { segment_lens, ..Self::try_new(n_big, sign_big, msg, params)? })
+    }
+
+    /// Verifies the DER certificate `cert_der` under the subject key of its issuer's DER
+    /// certificate, for callers holding the issuer certificate rather than its modulus. Errors
+    /// if either certificate is malformed, or the issuer key isn't RSA (see `cert::issuer_modulus`).
+    pub fn from_issuer_certificate(cert_der: &[u8], issuer_der: &[u8], params: CircuitParams) -> Result<Self, CertError> {
+        let n_big = cert::issuer_modulus(issuer_der)?;
+        let parts = cert::parse_der_certificate(cert_der)?;
+        Self::try_new(n_big, parts.signature, parts.tbs, params)
+    }
+
+    /// Rejects moduli shorter than `min_bits`, even when `bits_len` could fit them.
+    pub fn reject_weak_keys(self, min_bits: u64) -> Result<Self, CertError> {
+        let bits = self.n_big.bits();
+        if bits < min_bits {
+            return Err(CertError::WeakKey { bits, min_bits });
+        }
+        Ok(self)
+    }
+
+    /// Verifies the big-endian signature `sign` over `msg` under a key of the `rsa` crate. The
+    /// circuit takes the key's exponent, replacing `params.public_exponent`.
+    pub fn from_rsa_public_key(key: &rsa::RsaPublicKey, sign: &[u8], msg: &[u8], params: CircuitParams) -> Self {
+        use rsa::PublicKeyParts;
+
+        let params = CircuitParams { public_exponent: BigUint::from_bytes_be(&key.e().to_bytes_be()), ..params };
+        Self::new(BigUint::from_bytes_be(&key.n().to_bytes_be()), BigUint::from_bytes_be(sign), msg, params)
+    }
+
+    /// Verifies the big-endian signature `sign` over `msg` under an RSA key from a JWKS document.
+    /// The circuit takes the key's exponent, replacing `params.public_exponent`.
+    #[cfg(feature = "jwk")]
+    pub fn from_jwk(jwk: &jwk::JwkRsa, sign: &[u8], msg: &[u8], params: CircuitParams) -> Result<Self, CertError> {
+        let params = CircuitParams { public_exponent: jwk.exponent()?, ..params };
+        Self::try_new(jwk.modulus()?, BigUint::from_bytes_be(sign), msg, params)
+    }
+
+    /// Verifies a WebCrypto RSASSA-PKCS1-v1_5 signature over `msg`, from the standard base64 of
+    /// `exportKey('spki')` (or of the bare big-endian modulus) and of the `sign` output. With an
+    /// SPKI, the circuit takes the key's exponent, replacing `params.public_exponent`.
+    #[cfg(feature = "webcrypto")]
+    pub fn from_webcrypto(key: &str, sign: &str, msg: &[u8], params: CircuitParams) -> Result<Self, CertError> {
+        let (n_big, public_exponent) = match webcrypto::decode_public_key(key)? {
+            (n_big, Some(e)) => (n_big, e),
+            (n_big, None) => (n_big, params.public_exponent.clone()),
+        };
+        let params = CircuitParams { public_exponent, ..params };
+        Self::try_new(n_big, webcrypto::decode_uint(sign, "signature")?, msg, params)
+    }
+
+    /// Sets the SAN dNSName to prove membership of when `params.dns_name_max_len` is set.
+    pub fn with_dns_name(mut self, dns_name: &str) -> Self {
+        self.dns_name = dns_name.as_bytes().to_vec();
+        self
+    }
+
+    /// Sets the time, in seconds since the unix epoch, to prove the certificate unexpired at when
+    /// `params.expiry_check` is set.
+    pub fn with_current_time(mut self, unix_time: i64) -> Self {
+        self.current_time = time_instance(unix_time);
+        self
+    }
+
+    /// Checks the digest column of `public_inputs` against `params.digest`'s output length (in
+    /// `params.digest_encoding`). Trivially passes with `hide_digest`.
+    pub fn validate_digest_instance(&self, public_inputs: &[Vec<F>]) -> Result<(), CertError> {
+        let digest_index = match self.params.digest_index() {
+            Some(digest_index) => digest_index,
+            None => return Ok(()),
+        };
+        let expected = self.params.digest_instance_len();
+        let actual = public_inputs.get(digest_index).map_or(0, |column| column.len());
+        if actual != expected {
+            return Err(CertError::DigestLengthMismatch { algorithm: self.params.digest, expected, actual });
+        }
+        Ok(())
+    }
+
+    /// Checks `public_inputs` has one vector per instance column, each of the right length,
+    /// before handing it to a prover or verifier.
+    pub fn validate_public_inputs(&self, public_inputs: &[Vec<F>]) -> Result<(), CertError> {
+        let columns = self.params.instance_columns();
+        if public_inputs.len() != columns.len() {
+            return Err(CertError::InstanceCount { expected: columns.len(), actual: public_inputs.len() });
+        }
+        for ((column, expected), inputs) in columns.into_iter().zip(public_inputs) {
+            if inputs.len() != expected {
+                return Err(CertError::InstanceLength { column: column.name(), expected, actual: inputs.len() });
+            }
+        }
+        Ok(())
+    }
+
+    /// Expected instance values, in the order of `params.instance_columns()`.
+    pub fn public_inputs(&self) -> Vec<Vec<F>> {
+        self.params
+            .instance_columns()
+            .into_iter()
+            .map(|(column, max_len)| match column {
+                InstanceColumn::Modulus => modulus_instance(&self.n_big, &self.params),
+                InstanceColumn::IssuerKeyHash => vec![issuer_key_hash(&self.n_big, &self.params)],
+                InstanceColumn::Digest => {
+                    let digest = self.msg_digest();
+                    match self.params.digest_encoding {
+                        DigestEncoding::Bytes => digest.iter().map(|byte| F::from(*byte as u64)).collect(),
+                        DigestEncoding::EvmPacked => evm_digest_instance(&digest),
+                    }
+                }
+                InstanceColumn::San => san_instance(self.tbs(), max_len - 1).expect("subjectAltName missing or longer than san_max_len"),
+                InstanceColumn::DnsName => dns_name_instance(&self.dns_name, max_len - 1),
+                InstanceColumn::DnsNames => {
+                    let (max_names, max_name_len) = self.params.dns_names_commitment.unwrap();
+                    let commitment = dns_names_commitment(self.tbs(), max_names, max_name_len)
+                        .expect("subjectAltName missing, not only distinct dNSNames, or too large for dns_names_commitment");
+                    commitment.iter().map(|byte| F::from(*byte as u64)).collect()
+                }
+                InstanceColumn::SigAlg => sig_alg_instance(self.tbs(), max_len - 1).expect("signature algorithm OID missing or longer than sig_alg_max_len"),
+                InstanceColumn::SignedRange => {
+                    let range = self.signed_range().expect("message is not a DER certificate");
+                    vec![F::from(range.start as u64), F::from(range.end as u64)]
+                }
+                InstanceColumn::TbsDigest => Sha256::digest(self.tbs()).iter().map(|byte| F::from(*byte as u64)).collect(),
+                InstanceColumn::Exponent => vec![exponent_instance(&self.params.public_exponent)],
+                InstanceColumn::ValidityDates => validity_dates_instance(self.tbs()).expect("validity not two UTCTimes from 1970 on"),
+                InstanceColumn::NotExpired => {
+                    let not_after = not_after_time(self.tbs()).expect("notAfter missing or not a UTCTime");
+                    vec![F::from(self.current_time), F::from((self.current_time <= not_after) as u64)]
+                }
+                InstanceColumn::Valid => vec![F::from(self.signature_is_valid() as u64)],
+                InstanceColumn::ByteRange => {
+                    let mut lens = self.segment_lens.iter().map(|len| F::from(*len as u64)).collect::<Vec<_>>();
+                    lens.resize(max_len, F::zero());
+                    lens
+                }
+            })
+            .collect()
+    }
+
+    /// The instance columns `params` configure, in public-input order: what verifier tooling needs
+    /// to build or split the public inputs without a circuit instance of its own.
+    pub fn public_input_columns(&self) -> Vec<InstanceColumnSpec> {
+        self.params
+            .instance_columns()
+            .into_iter()
+            .map(|(column, len)| InstanceColumnSpec { column, name: column.name(), len })
+            .collect()
+    }
+
+    /// `public_inputs()` concatenated, the layout on-chain verifiers take. The canonical order is
+    /// column by column as in `params.instance_columns()` (by default the 64-bit modulus limbs,
+    /// least significant first, then the digest bytes in digest order), then any SAN/dNSName
+    /// and validity columns. `CircuitParams::reshape_public_inputs` undoes it.
+    pub fn flatten_public_inputs(&self) -> Vec<F> {
+        self.public_inputs().concat()
+    }
+
+    /// `flatten_public_inputs()` as the 32-byte big-endian words a generated Solidity verifier
+    /// takes as its `uint256[]` instances, one word per field element in the same order. With
+    /// default params the words are:
+    ///
+    /// - `0..num_limbs`: the modulus limbs, least significant first, each a `limb_bits`-bit integer
+    /// - the digest: 32 words of one byte each, or with `DigestEncoding::EvmPacked` two words, the
+    ///   high then the low 16 bytes of the digest as in `evm_digest_instance`
+    ///
+    /// followed by the other columns enabled in `params`, in `instance_columns()` order.
+    pub fn solidity_public_inputs(&self) -> Vec<[u8; 32]> {
+        use halo2_base::halo2_proofs::halo2curves::group::ff::PrimeField as _;
+
+        self.flatten_public_inputs()
+            .iter()
+            .map(|value| {
+                let repr = value.to_repr();
+                let mut word = [0; 32];
+                word[..repr.as_ref().len()].copy_from_slice(repr.as_ref());
+                word.reverse();
+                word
+            })
+            .collect()
+    }
+
+    /// With `params.signed_range`, the range of the TBS within the certificate in `msg`.
+    pub fn signed_range(&self) -> Option<Range<usize>> {
+        if !self.params.signed_range {
+            return None;
+        }
+        cert::signed_region(&self.msg).ok().map(|(_, range)| range)
+    }
+
+    /// SHA-256 of `msg`.
+    fn msg_digest(&self) -> [u8; 32] {
+        self.msg_digest.unwrap_or_else(|| Sha256::digest(&self.msg).into())
+    }
+
+    /// The signed bytes: the TBS of the certificate with `params.signed_range`, else the whole
+    /// message (or the whole message if it isn't a certificate).
+    fn tbs(&self) -> &[u8] {
+        match self.signed_range() {
+            Some(range) => &self.msg[range],
+            None => &self.msg,
+        }
+    }
+
+    /// Synthesizes the circuit with `MockProver` and reports where its cells go, per gadget: the RSA
+    /// inputs, SHA256, the exponentiation and padding check, and the optional certificate fields.
+    /// halo2's `CircuitCost` estimates proof size from the shape of columns and gates, which doesn't
+    /// say which gadget a cell belongs to, so this counts the cells of the main context instead.
+    /// Cells the SHA256 chip assigns in its own columns, outside that context, aren't counted.
+    ///
+    /// # Panics
+    /// If the circuit doesn't fit `params.k`.
+    pub fn cost_report(&self) -> CostReport {
+        self.params.set_current();
+        MockProver::run(self.params.k, self, self.public_inputs()).expect("circuit doesn't fit params.k");
+        CostReport {
+            k: self.params.k,
+            total: CircuitStats::last().expect("synthesis records stats"),
+            gadgets: GadgetCost::last(),
+        }
+    }
+
+    /// Checks the PKCS#1 v1.5 SHA-256 signature natively, as the circuit does.
+    pub fn signature_is_valid(&self) -> bool {
+        let n_big = self.params.fixed_modulus.as_ref().unwrap_or(&self.n_big);
+        // The EM is as long as the modulus, which may fall short of `bits_len` by whole bytes
+        let em_len = ((n_big.bits() + 7) / 8) as usize;
+        if em_len + MAX_MODULUS_SHORTFALL < self.params.bits_len / 8 {
+            return false;
+        }
+        let digest: [u8; 32] = match self.signed_range() {
+            Some(range) => Sha256::digest(&self.msg[range]).into(),
+            None => self.msg_digest(),
+        };
+        let digest = &digest[..self.params.signed_digest_len()];
+        let digest_info_prefix = digest_info_prefix(digest.len());
+        if em_len < digest_info_prefix.len() + digest.len() + 11 {
+            return false;
+        }
+        let mut em = vec![0x00, 0x01];
+        em.resize(em_len - digest_info_prefix.len() - digest.len() - 1, 0xff);
+        em.push(0x00);
+        em.extend_from_slice(&digest_info_prefix);
+        em.extend_from_slice(digest);
+        self.sign_big < *n_big
+            && self.sign_big.modpow(&self.params.public_exponent, n_big) == BigUint::from_bytes_be(&em)
+    }
+
+    /// Like `signature_is_valid`, but says why a signature fails: `DigestAlgorithmMismatch` when
+    /// the recovered DigestInfo names another digest than `params.digest`, as when a signature over
+    /// a SHA-512 DigestInfo is checked against a SHA-256 digest, else `InvalidSignature`. The
+    /// circuit fixes every DigestInfo byte, the OID included, so it rejects both alike.
+    pub fn check_signature(&self) -> Result<(), CertError> {
+        if self.signature_is_valid() {
+            return Ok(());
+        }
+        let n_big = self.params.fixed_modulus.as_ref().unwrap_or(&self.n_big);
+        // `00 01 ff.. 00 DigestInfo`, less the leading zero
+        let em = self.sign_big.modpow(&self.params.public_exponent, n_big).to_bytes_be();
+        let digest_info = match em.split_first() {
+            Some((&0x01, rest)) => rest.iter().position(|byte| *byte != 0xff).filter(|i| rest[*i] == 0x00).map(|i| &rest[i + 1..]),
+            _ => None,
+        };
+        match digest_info.and_then(cert::digest_info_algorithm) {
+            Some(oid) if oid != self.params.digest.oid() => Err(CertError::DigestAlgorithmMismatch {
+                expected: self.params.digest,
+                name: cert::lookup(cert::DIGEST_ALGORITHMS, &oid),
+                oid,
+            }),
+            _ => Err(CertError::InvalidSignature),
+        }
+    }
+}
+
+/// DER of the SHA-256 DigestInfo up to the digest, as prepended to it in a PKCS#1 v1.5 signature.
+const SHA256_DIGEST_INFO_PREFIX: [u8; 19] = [
+    0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01, 0x05, 0x00, 0x04, 0x20,
+];
+
+/// `SHA256_DIGEST_INFO_PREFIX` with its lengths adjusted for a digest truncated to `digest_len`
+/// bytes; the unchanged prefix for 32.
+fn digest_info_prefix(digest_len: usize) -> [u8; 19] {
+    let mut prefix = SHA256_DIGEST_INFO_PREFIX;
+    prefix[1] = (prefix.len() - 2 + digest_len) as u8;
+    prefix[prefix.len() - 1] = digest_len as u8;
+    prefix
+}
+
+/// DigestInfo prefix of a full `algorithm` digest, which differs from the SHA-256 one only in the
+/// lengths and the last byte of the OID.
+pub(crate) fn algorithm_digest_info_prefix(algorithm: DigestAlgorithm) -> [u8; 19] {
+    let mut prefix = digest_info_prefix(algorithm.output_len());
+    prefix[14] = match algorithm {
+        DigestAlgorithm::Sha256 => 0x01,
+        DigestAlgorithm::Sha384 => 0x02,
+        DigestAlgorithm::Sha512 => 0x03,
+    };
+    prefix
+}
+
+/// Modulus limbs as constrained to the `n_instance` column.
+pub fn modulus_instance<F: PrimeField>(n_big: &BigUint, params: &CircuitParams) -> Vec<F> {
+    decompose_biguint::<F>(n_big, params.num_limbs(), params.limb_bits)
+}
+
+/// The `bits_len / 8` big-endian bytes of the modulus `n_big`, the `issuer_key_hash` preimage.
+pub(crate) fn modulus_bytes(n_big: &BigUint, bits_len: usize) -> Vec<u8> {
+    let bytes = n_big.to_bytes_be();
+    let mut padded = vec![0; (bits_len / 8).saturating_sub(bytes.len())];
+    padded.extend(bytes);
+    padded
+}
+
+/// The `issuer_key_hash` commitment to the modulus `n_big`: the `commitment::commitment_digest` of
+/// its `bits_len / 8` big-endian bytes, packed into one field element by `pack_commitment`.
+pub fn issuer_key_hash<F: PrimeField>(n_big: &BigUint, params: &CircuitParams) -> F {
+    commitment::pack_commitment(&commitment::commitment_digest(&modulus_bytes(n_big, params.bits_len)))
+}
+
+/// The `variable_exponent` commitment to the exponent `e`: `e` itself, packed into one field element.
+pub fn exponent_instance<F: PrimeField>(e: &BigUint) -> F {
+    biguint_to_fe(e)
+}
+
+/// Modulus limbs for the `n_instance` column from an issuer's `PUBLIC KEY` (SPKI) PEM.
+///
+/// # Panics
+/// If `pem` isn't an RSA SubjectPublicKeyInfo.
+pub fn modulus_instance_from_pem<F: PrimeField>(pem: &str, params: &CircuitParams) -> Vec<F> {
+    let (_, pem) = parse_x509_pem(pem.as_bytes()).expect("Failed to parse PEM");
+    let n_big = cert::spki_rsa_modulus(&pem.contents).unwrap_or_else(|err| panic!("Failed to grab modulus: {err}"));
+    modulus_instance(&n_big, params)
+}
+
+/// `MockProver::run` of `circuit` at `k`, with its params made current. `configure` sizes the
+/// range chip's lookup table for `circuit.params.k`, so a run at another `k` fails, if at all,
+/// with unrelated constraint errors; this checks it up front.
+///
+/// # Panics
+/// If `k` isn't `circuit.params.k`.
+pub fn mock_run<F: PrimeField>(k: u32, circuit: &CertificateVerificationCircuit<F>, instances: Vec<Vec<F>>) -> Result<MockProver<F>, Error> {
+    assert_eq!(
+        k, circuit.params.k,
+        "MockProver run at k = {k} ({} rows), but the circuit is configured for k = {} ({} rows)",
+        1usize << k, circuit.params.k, circuit.params.rows()
+    );
+    circuit.params.set_current();
+    MockProver::run(k, circuit, instances)
+}
+
+/// Smallest `k` at which the default circuit, with `msg_len` bytes of SHA256 capacity and a
+/// `bits_len`-bit modulus, fits its rows: the SHA256 blocks, the RSA and range gates and the
+/// lookup tables. Row counts don't depend on the witness, so this synthesizes a placeholder
+/// certificate with `MockProver` at increasing `k`, a few seconds each. The chips report running
+/// out of rows by panicking, so expect their messages on stderr for the `k` that don't fit.
+/// Leaves `CircuitParams::current()` as it found it.
+///
+/// # Panics
+/// If nothing up to `k = 26` fits.
+pub fn min_k_for(msg_len: usize, bits_len: usize) -> u32 {
+    let base = CircuitParams { msg_len, bits_len, validity: ValidityMode::ExposeResult, ..Default::default() };
+    // An odd full-length modulus; under `ExposeResult` the signature needn't verify
+    let n_big = (BigUint::from(1u8) << bits_len) - 1u8;
+    let msg = vec![0u8; base.max_supported_msg_len()];
+    let previous = CircuitParams::current();
+    let lookup_bits = base.lookup_bits.max(base.sha256_lookup_bits) as u32;
+    let k = (lookup_bits + 1..=26)
+        .find(|&k| {
+            let params = CircuitParams { k, ..base.clone() };
+            params.set_current();
+            let circuit = CertificateVerificationCircuit::<Fr>::new(n_big.clone(), BigUint::from(2u8), msg.as_slice(), params);
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                MockProver::run(k, &circuit, circuit.public_inputs()).map_or(false, |prover| prover.verify().is_ok())
+            }))
+            .unwrap_or(false)
+        })
+        .expect("no k up to 26 fits the circuit");
+    previous.set_current();
+    k
+}
+
+/// Digest instance under `DigestEncoding::EvmPacked`: each 16 bytes of `digest` as a big-endian
+/// integer, so the elements equal the `uint256` words of `abi.encode(uint128(hi), uint128(lo))`
+/// for the two halves of a SHA-256 digest.
+pub fn evm_digest_instance<F: PrimeField>(digest: &[u8]) -> Vec<F> {
+    assert_eq!(digest.len() % 16, 0, "digest length must be a multiple of 16 bytes");
+    digest.chunks(16).map(|half| F::from_u128(u128::from_be_bytes(half.try_into().unwrap()))).collect()
+}
+
+/// Expected target-name instance: `[len, name bytes.., 0 padding]` of length `max_len + 1`.
+pub fn dns_name_instance<F: PrimeField>(dns_name: &[u8], max_len: usize) -> Vec<F> {
+    assert!(dns_name.len() <= max_len, "dNSName longer than dns_name_max_len");
+    let mut instance = vec![F::from(dns_name.len() as u64)];
+    instance.extend(dns_name.iter().map(|byte| F::from(*byte as u64)));
+    instance.resize(max_len + 1, F::zero());
+    instance
+}
+
+/// Preimage of `dns_names_commitment`: for each of `max_names` slots, a length byte and the name
+/// zero-padded to `max_name_len` bytes, with the SAN's dNSNames sorted first and empty slots after.
+/// `None` unless the SAN holds only distinct dNSNames that fit.
+pub fn dns_names_preimage(tbs: &[u8], max_names: usize, max_name_len: usize) -> Option<Vec<u8>> {
+    let san = &tbs[der::find_extension(tbs, der::OID_SUBJECT_ALT_NAME)?];
+    let mut names = der::san_dns_names(san)?;
+    // Only dNSNames (and a short-form SEQUENCE header) when their TLVs add up to the SAN
+    if san.len() != 2 + names.iter().map(|name| 2 + name.len()).sum::<usize>() {
+        return None;
+    }
+    names.sort();
+    let distinct = names.windows(2).all(|pair| pair[0] != pair[1]);
+    let fits = names.len() <= max_names && names.iter().all(|name| !name.is_empty() && name.len() <= max_name_len);
+    if !distinct || !fits {
+        return None;
+    }
+    let mut preimage = Vec::with_capacity(max_names * (max_name_len + 1));
+    for i in 0..max_names {
+        let name = names.get(i).map_or(&[][..], |name| name.as_bytes());
+        preimage.push(name.len() as u8);
+        preimage.extend_from_slice(name);
+        preimage.resize(preimage.len() + max_name_len - name.len(), 0);
+    }
+    Some(preimage)
+}
+
+/// `commitment::commitment_digest` of `dns_names_preimage`: a commitment to the set of SAN
+/// dNSNames that doesn't depend on their order in the certificate.
+pub fn dns_names_commitment(tbs: &[u8], max_names: usize, max_name_len: usize) -> Option<[u8; 32]> {
+    dns_names_preimage(tbs, max_names, max_name_len).map(|preimage| commitment::commitment_digest(&preimage))
+}
+
+/// `extnID` TLV of subjectAltName followed by the `extnValue` OCTET STRING tag.
+const SAN_PREFIX: [u8; 6] = [0x06, 0x03, 0x55, 0x1d, 0x11, 0x04];
+
+/// Expected SAN instance: `[len, extnValue bytes.., 0 padding]` of length `max_len + 1`.
+pub fn san_instance<F: PrimeField>(tbs: &[u8], max_len: usize) -> Option<Vec<F>> {
+    let ext = der::find_extension_entry(tbs, der::OID_SUBJECT_ALT_NAME)?;
+    if ext.critical || ext.value.len() > max_len {
+        return None;
+    }
+    let mut instance = vec![F::from(ext.value.len() as u64)];
+    instance.extend(tbs[ext.value].iter().map(|byte| F::from(*byte as u64)));
+    instance.resize(max_len + 1, F::zero());
+    Some(instance)
+}
+
+/// Expected signature algorithm instance: `[len, OID content bytes.., 0 padding]` of length
+/// `max_len + 1`, from the TBS copy of the AlgorithmIdentifier.
+pub fn sig_alg_instance<F: PrimeField>(tbs: &[u8], max_len: usize) -> Option<Vec<F>> {
+    let oid = der::tbs_signature_algorithm_oid(tbs).filter(|oid| oid.len <= max_len)?;
+    let mut instance = vec![F::from(oid.len as u64)];
+    instance.extend(tbs[oid.content()].iter().map(|byte| F::from(*byte as u64)));
+    instance.resize(max_len + 1, F::zero());
+    Some(instance)
+}
+
+/// The `YYMMDDHHMMSS` digits of notBefore and notAfter, if both are UTCTimes (years 1950 to 2049).
+fn utc_validity_digits(tbs: &[u8]) -> Option<[&[u8]; 2]> {
+    let (not_before, not_after) = der::validity(tbs)?;
+    let digits = |time: der::Tlv| {
+        let bytes = &tbs[time.content()];
+        let is_utc = time.tag == der::TAG_UTC_TIME && time.len == 13 && bytes[12] == b'Z';
+        (is_utc && bytes[..12].iter().all(u8::is_ascii_digit)).then(|| &bytes[..12])
+    };
+    Some([digits(not_before)?, digits(not_after)?])
+}
+
+/// notAfter of `tbs` as the decimal number `YYYYMMDDHHMMSS`, the form in which `expiry_check`
+/// compares times. `None` unless notBefore and notAfter are UTCTimes.
+pub fn not_after_time(tbs: &[u8]) -> Option<u64> {
+    let [_, not_after] = utc_validity_digits(tbs)?;
+    let digits = not_after.iter().fold(0u64, |acc, digit| acc * 10 + (digit - b'0') as u64);
+    // UTCTime years below 50 are 20YY
+    let century = if digits < 50 * 10u64.pow(10) { 20 } else { 19 };
+    Some(century * 10u64.pow(12) + digits)
+}
+
+/// Seconds since the unix epoch of UTCTime `YYMMDDHHMMSS` digits.
+fn utc_unix_time(digits: &[u8]) -> i64 {
+    let field = |i: usize| ((digits[i] - b'0') * 10 + (digits[i + 1] - b'0')) as i64;
+    let year = field(0) + if field(0) < 50 { 2000 } else { 1900 };
+    let (month, day) = (field(2), field(4));
+    // Howard Hinnant's `days_from_civil`, over 400-year eras starting on March 1st
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+    days * 86400 + field(6) * 3600 + field(8) * 60 + field(10)
 }
 
-const DEGREE: usize = 16;
+/// Expected `validity_dates` instance: `[notBefore, notAfter]` as unix timestamps. `None` unless
+/// both are UTCTimes from 1970 on.
+pub fn validity_dates_instance<F: PrimeField>(tbs: &[u8]) -> Option<Vec<F>> {
+    let times = utc_validity_digits(tbs)?.map(utc_unix_time);
+    times.iter().map(|time| u64::try_from(*time).ok().map(F::from)).collect()
+}
+
+/// Whether the circuit's walk from the TBS header to the validity (see
+/// `CertificateVerificationConfig::assign_validity_digits`) covers the layout of `tbs`.
+fn validity_walkable(tbs: &[u8]) -> bool {
+    let fields = match der::tbs_fields(tbs) {
+        Some(fields) if fields.len() > der::TBS_VALIDITY => fields,
+        _ => return false,
+    };
+    let (sig_alg, issuer, validity) = (fields[der::TBS_SIGNATURE], fields[der::TBS_SIGNATURE + 1], fields[der::TBS_VALIDITY]);
+    let expected_start = tbs.get(4 + TBS_V3_HEADER.len()).map(|len| 5 + TBS_V3_HEADER.len() + *len as usize);
+    tbs[1] == 0x82
+        && Some(sig_alg.start) == expected_start
+        && sig_alg.header_len == 2
+        && (issuer.header_len == 2 || tbs[issuer.start + 1] == 0x81)
+        && validity.header_len == 2
+        && validity.len == 30
+        && utc_validity_digits(tbs).is_some()
+}
+
+/// The unix timestamp `unix_time` as the decimal number `YYYYMMDDHHMMSS` (UTC), the current time
+/// of the `not_expired` instance column. Times before year 0 aren't supported.
+pub fn time_instance(unix_time: i64) -> u64 {
+    let (days, secs) = (unix_time.div_euclid(86400), unix_time.rem_euclid(86400));
+    // Howard Hinnant's `civil_from_days`, over 400-year eras starting on March 1st
+    let z = days + 719468;
+    let (era, day_of_era) = (z.div_euclid(146097), z.rem_euclid(146097));
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    let date = (year * 100 + month) * 100 + day;
+    let time = (secs / 3600) * 10000 + (secs / 60 % 60) * 100 + secs % 60;
+    (date * 1_000_000 + time) as u64
+}
+
+/// Validity header through the notBefore UTCTime tag, and the bytes between the two times: both
+/// UTCTimes, so the SEQUENCE is 30 bytes.
+const UTC_VALIDITY_HEADER: [u8; 4] = [0x30, 0x1e, 0x17, 0x0d];
+const UTC_NOT_AFTER_HEADER: [u8; 3] = [b'Z', 0x17, 0x0d];
 
+/// TBS bytes from offset 4 up to the serialNumber length: the `[0]` v3 version and the INTEGER tag
+/// of serialNumber, after a `30 82 xx xx` header.
+pub(crate) const TBS_V3_HEADER: [u8; 6] = [0xa0, 0x03, 0x02, 0x01, 0x02, 0x02];
 
 #[derive(Debug,Clone)]
-struct CertificateVerificationConfig<F: PrimeField> {
+pub struct CertificateVerificationConfig<F: PrimeField> {
     rsa_config: RSAConfig<F>,
     sha256_config: Sha256DynamicConfig<F>,
-    n_instance: Column<Instance>,
-    hash_instance: Column<Instance>
+    /// Each exposed instance column with its length, in creation order.
+    instances: Vec<(InstanceColumn, Column<Instance>, usize)>,
+    params: CircuitParams,
 }
 
+/// Checks the PKCS#1 v1.5 signature over the SHA-256 `hashed_msg` (whole, or truncated to its
+/// leading bytes, see `CircuitParams::digest_truncation`) byte by byte: the EM recovered
+/// with the public exponent must be `00 01 ff.. 00 DigestInfo digest`. Every byte, the `00` separator
+/// between the padding and the DigestInfo included, is its own constraint rather than part of a
+/// comparison of whole limbs; lax checks of that boundary are what signature forgeries against
+/// e = 3 verifiers exploited. `montgomery_exp` is `CircuitParams::montgomery_exp`. Returns 1 if
+/// the signature is valid, 0 otherwise.
+pub(crate) fn verify_pkcs1v15_signature<'v, F: PrimeField>(
+    rsa_config: &RSAConfig<F>,
+    ctx: &mut Context<'v, F>,
+    public_key: &AssignedRSAPublicKey<'v, F>,
+    hashed_msg: &[AssignedValue<'v, F>],
+    signature: &AssignedRSASignature<'v, F>,
+    montgomery_exp: bool
+) -> Result<AssignedValue<'v, F>, Error> {
+    let biguint_config = rsa_config.biguint_config();
+    let range = biguint_config.range();
+    let gate = range.gate();
+    let em = assign_modpow(rsa_config, ctx, public_key, signature, montgomery_exp)?;
+    let mut em_bytes = unpack_le_bytes(range, ctx, &em, biguint_config.limb_bits);
+    em_bytes.reverse();
+    let short_by = assign_modulus_shortfall(range, ctx, public_key.n.limbs(), biguint_config.limb_bits);
+    Ok(check_pkcs1v15_em(gate, ctx, &em_bytes, &short_by, &digest_info_prefix(hashed_msg.len()), hashed_msg))
+}
 
-impl<F: PrimeField> Circuit<F> for CertificateVerificationCircuit<F> {
-    type Config = CertificateVerificationConfig<F>;
-    type FloorPlanner = SimpleFloorPlanner;
+/// Limbs of `signature^e mod n` under `public_key`, computed with halo2-rsa's `mul_mod` or, with
+/// `montgomery_exp` and a fixed exponent, in Montgomery form (see `montgomery`).
+pub(crate) fn assign_modpow<'v, F: PrimeField>(
+    rsa_config: &RSAConfig<F>,
+    ctx: &mut Context<'v, F>,
+    public_key: &AssignedRSAPublicKey<'v, F>,
+    signature: &AssignedRSASignature<'v, F>,
+    montgomery_exp: bool
+) -> Result<Vec<AssignedValue<'v, F>>, Error> {
+    let biguint_config = rsa_config.biguint_config();
+    match (&public_key.e, montgomery_exp) {
+        (AssignedRSAPubE::Fix(e), true) => Ok(montgomery::modpow_fixed_exp(
+            biguint_config.range(),
+            ctx,
+            signature.c.limbs(),
+            e,
+            public_key.n.limbs(),
+            biguint_config.limb_bits
+        )),
+        (AssignedRSAPubE::Var(_), true) => Err(Error::Synthesis),
+        (_, false) => Ok(rsa_config.modpow_public_key(ctx, &signature.c, public_key)?.limbs().to_vec()),
+    }
+}
 
-    fn without_witnesses(&self) -> Self {
-        unimplemented!();
+/// Most whole bytes a modulus may be shorter than `bits_len / 8`, such as 1 for a 2040-bit key
+/// under the default 2048 `bits_len`. Its EM is that much shorter, so the `00 01` header of the
+/// PKCS#1 v1.5 encoding moves right by as many zero bytes. Moduli shorter still fail the check.
+pub const MAX_MODULUS_SHORTFALL: usize = 8;
+
+/// Flags `short_by[i]`, 1 when the leading `i + 1` bytes of the modulus with little-endian
+/// `limbs` are all zero, for `i < MAX_MODULUS_SHORTFALL`. Only the top limbs are unpacked.
+fn assign_modulus_shortfall<'v, F: PrimeField>(
+    range: &RangeConfig<F>,
+    ctx: &mut Context<'v, F>,
+    limbs: &[AssignedValue<'v, F>],
+    limb_bits: usize
+) -> Vec<AssignedValue<'v, F>> {
+    let gate = range.gate();
+    let top_limbs = num_limbs(MAX_MODULUS_SHORTFALL * 8, limb_bits).min(limbs.len());
+    let mut top_bytes = unpack_le_bytes(range, ctx, &limbs[limbs.len() - top_limbs..], limb_bits);
+    top_bytes.reverse();
+    let mut short_by: Vec<AssignedValue<'v, F>> = vec![];
+    for byte in top_bytes.iter().take(MAX_MODULUS_SHORTFALL) {
+        let is_zero = gate.is_zero(ctx, byte);
+        let flag = match short_by.last() {
+            Some(previous) => gate.mul(ctx, Existing(previous), Existing(&is_zero)),
+            None => is_zero,
+        };
+        short_by.push(flag);
     }
+    short_by
+}
 
-    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
-        let range_config = RangeConfig::configure(
-            meta, RangeStrategy::Vertical, 
-            &[Self::NUM_ADVICE], 
-            &[Self::NUM_LOOKUP_ADVICE], 
-            Self::NUM_FIXED, 
-            Self::LOOKUP_BITS, 
-            0, 
-            DEGREE  // Degree set to 13
-        );
-        let biguint_config = BigUintConfig::construct(range_config.clone(), Self::LIMB_BITS);
-        let rsa_config = RSAConfig::construct(
-            biguint_config, 
-            Self::BITS_LEN, 
-            Self::EXP_LIMB_BITS
+/// The byte checks of `verify_pkcs1v15_signature` on the big-endian EM bytes, with the DigestInfo
+/// up to the digest given as `digest_info_prefix`. `short_by` are the flags of
+/// `assign_modulus_shortfall`, which shift the `00 01` header right past as many leading zeros;
+/// none for a full-length modulus. Returns 1 if `em_bytes` is the encoding of `hashed_msg`,
+/// 0 otherwise.
+pub(crate) fn check_pkcs1v15_em<'v, F: PrimeField>(
+    gate: &impl GateInstructions<F>,
+    ctx: &mut Context<'v, F>,
+    em_bytes: &[AssignedValue<'v, F>],
+    short_by: &[AssignedValue<'v, F>],
+    digest_info_prefix: &[u8],
+    hashed_msg: &[AssignedValue<'v, F>]
+) -> AssignedValue<'v, F> {
+    let separator = em_bytes.len() - hashed_msg.len() - digest_info_prefix.len() - 1;
+    let (padding, rest) = em_bytes.split_at(separator);
+    let (separator_byte, rest) = rest.split_at(1);
+    let (digest_info, digest) = rest.split_at(digest_info_prefix.len());
+
+    // Byte i of `00.. 00 01 ff..` is 0xff - 0xfe * a(i) - a(i + 1), with a(0) = a(1) = 1 and
+    // a(j) = short_by[j - 2]: 0 up to the header, 1 just after it and 0xff from there on.
+    let a = |j: usize| match j {
+        0 | 1 => Constant(F::one()),
+        _ => short_by.get(j - 2).map_or(Constant(F::zero()), Existing),
+    };
+    let header_len = (short_by.len() + 2).min(padding.len());
+    let (header, padding) = padding.split_at(header_len);
+    let mut checks = vec![];
+    for (i, byte) in header.iter().enumerate() {
+        let shifted = gate.mul_add(ctx, a(i), Constant(-F::from(0xfe)), Constant(F::from(0xff)));
+        let expected = gate.sub(ctx, Existing(&shifted), a(i + 1));
+        checks.push(gate.is_equal(ctx, Existing(byte), Existing(&expected)));
+    }
+    let expected_constants = std::iter::repeat(&0xff)
+        .zip(padding)
+        .chain(digest_info_prefix.iter().zip(digest_info));
+    checks.extend(expected_constants.map(|(expected, byte)| gate.is_equal(ctx, Existing(byte), Constant(F::from(*expected as u64)))));
+    checks.push(gate.is_zero(ctx, &separator_byte[0]));
+    for (byte, hashed) in digest.iter().zip(hashed_msg) {
+        checks.push(gate.is_equal(ctx, Existing(byte), Existing(hashed)));
+    }
+    let num_checks = checks.len();
+    let passed = gate.sum(ctx, checks.iter().map(Existing));
+    gate.is_equal(ctx, Existing(&passed), Constant(F::from(num_checks as u64)))
+}
+
+/// Range, RSA and SHA256 configs shared by the circuits, with one `msg_len` SHA256 slot per
+/// message to hash.
+pub(crate) fn configure_gadgets<F: PrimeField>(
+    meta: &mut ConstraintSystem<F>,
+    params: &CircuitParams,
+    num_msgs: usize
+) -> (RSAConfig<F>, Sha256DynamicConfig<F>) {
+    assert_eq!(params.digest, DigestAlgorithm::Sha256, "only SHA-256 is supported in-circuit");
+    let rsa_config = configure_rsa(meta, params);
+    let range_config = rsa_config.biguint_config().range().clone();
+    let sha256_config = build_sha256_config(meta, params, range_config, num_msgs);
+    (rsa_config, sha256_config)
+}
+
+/// The range and RSA configs of `configure_gadgets`, for circuits that hash nothing.
+pub(crate) fn configure_rsa<F: PrimeField>(meta: &mut ConstraintSystem<F>, params: &CircuitParams) -> RSAConfig<F> {
+    assert!(!(params.montgomery_exp && params.variable_exponent), "montgomery_exp needs a fixed public_exponent");
+    let range_config = RangeConfig::configure(
+        meta, RangeStrategy::Vertical, 
+        &[params.num_advice], 
+        &[params.num_lookup_advice], 
+        params.num_fixed, 
+        params.lookup_bits, 
+        0, 
+        params.k as usize
+    );
+    let biguint_config = BigUintConfig::construct(range_config, params.limb_bits);
+    RSAConfig::construct(
+        biguint_config, 
+        params.bits_len, 
+        params.exp_limb_bits
+    )
+}
+
+/// The one call to `Sha256DynamicConfig::configure`, so changes to its signature upstream touch
+/// only this function: `num_msgs` slots of `params.msg_len` bytes, with lookups on `range_config`.
+pub(crate) fn build_sha256_config<F: PrimeField>(
+    meta: &mut ConstraintSystem<F>,
+    params: &CircuitParams,
+    range_config: RangeConfig<F>,
+    num_msgs: usize
+) -> Sha256DynamicConfig<F> {
+    // The trailing flag is halo2-dynamic-sha256's `is_zk`: lay the SHA256 rows out for real,
+    // blinded proofs rather than for MockProver runs only
+    let is_zk = true;
+    Sha256DynamicConfig::configure(
+        meta,
+        vec![params.msg_len; num_msgs],
+        range_config,
+        params.sha256_lookup_bits,
+        params.sha256_lookup_advice,
+        is_zk
+    )
+}
+
+impl<F: PrimeField> CertificateVerificationConfig<F> {
+    /// `Circuit::configure` for `num_certs` verifications in one circuit (see
+    /// `CertificateVerificationCircuit::verify_into_context`), with one set of instance columns.
+    pub fn configure(meta: &mut ConstraintSystem<F>, params: CircuitParams, num_certs: usize) -> Self {
+        // Per certificate, the whole certificate, the TBS, the dNSName commitment preimage and then
+        // the issuer key
+        let slots_per_cert = 1
+            + params.signed_range as usize
+            + params.dns_names_commitment.is_some() as usize
+            + params.issuer_key_hash as usize;
+        let num_msgs = num_certs * slots_per_cert;
+        let (rsa_config, sha256_config) = configure_gadgets(meta, &params, num_msgs);
+        if let Some(max_len) = params.san_max_len {
+            assert!(max_len < 128, "san_max_len must fit a short-form DER length");
+        }
+        match (params.san_max_len, params.dns_name_max_len) {
+            (None, Some(_)) => panic!("dns_name_max_len requires san_max_len"),
+            (_, Some(max_name_len)) => assert!(max_name_len < 128, "dns_name_max_len must fit a short-form DER length"),
+            _ => {}
+        }
+        if let Some((max_names, max_name_len)) = params.dns_names_commitment {
+            assert!(params.san_max_len.is_some(), "dns_names_commitment requires san_max_len");
+            assert!(max_name_len < 128, "dns_names_commitment names must fit a short-form DER length");
+            assert!(max_names * (max_name_len + 1) + 9 <= params.msg_len, "dns_names_commitment preimage must fit msg_len");
+        }
+        if let Some(max_len) = params.sig_alg_max_len {
+            assert!(max_len < 128, "sig_alg_max_len must fit a short-form DER length");
+        }
+        assert_eq!(params.bits_len % params.limb_bits, 0, "bits_len must be a multiple of limb_bits");
+        let e = &params.public_exponent;
+        assert!(
+            e.bit(0) && *e >= BigUint::from(3u8) && e.bits() < params.bits_len as u64,
+            "public_exponent must be odd, at least 3 and shorter than the modulus"
         );
-        let sha256_config = Sha256DynamicConfig::configure(
-            meta, 
-            vec![Self::MSG_LEN], 
-            range_config, 
-            Self::SHA256_LOOKUP_BITS, 
-            Self::SHA256_LOOKUP_ADVICE, 
-            true
+        if let Err(err) = params.check_exponent() {
+            panic!("{err}");
+        }
+        assert!(!params.tbs_digest || params.signed_range, "tbs_digest requires signed_range");
+        assert!(
+            !(params.signed_range && params.byte_range_segments > 0),
+            "signed_range and byte_range_segments both say what the message is"
         );
-        let n_instance = meta.instance_column();
-        let hash_instance = meta.instance_column();
-        meta.enable_equality(n_instance);   
-        meta.enable_equality(hash_instance);
+        if params.issuer_key_hash {
+            assert!(params.fixed_modulus.is_none(), "issuer_key_hash and fixed_modulus both say how the modulus is bound");
+            assert!(params.bits_len / 8 <= params.max_supported_msg_len(), "issuer_key_hash preimage must fit msg_len");
+        }
+        if let Some(truncation) = params.digest_truncation {
+            assert!((1..=params.digest.output_len()).contains(&truncation), "digest_truncation must be 1..=digest length");
+        }
+        // Instance columns are indexed in creation order
+        let instances = params
+            .instance_columns()
+            .into_iter()
+            .map(|(column, len)| {
+                let instance = meta.instance_column();
+                meta.enable_equality(instance);
+                (column, instance, len)
+            })
+            .collect();
 
-        Self::Config {
+        Self {
             rsa_config,
             sha256_config,
-            n_instance,
-            hash_instance
+            instances,
+            params
         }
     }
 
-    fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
-        let biguint_config = config.rsa_config.biguint_config();
-        config.sha256_config.load(&mut layouter)?;
-        biguint_config.range().load_lookup_table(& mut layouter)?;
-        let mut first_pass = SKIP_FIRST_PASS;        
-        let (public_key_cells, hashed_msg_cells) = layouter.assign_region(
-            || "certificat chain verifier", 
-            |region| {
-                if first_pass {
-                    first_pass = false;
-                    return Ok((vec![], vec![]));
-                }
-    
-                let mut aux = biguint_config.new_context(region);
-                let ctx = &mut aux;
-                let e_fix = RSAPubE::Fix(BigUint::from(Self::DEFAULT_E));
-                
-                // Verify Cert
-                let public_key = RSAPublicKey::new(Value::known(self.n_big.clone()), e_fix);     // cloning might be slow
-                let public_key = config.rsa_config.assign_public_key(ctx, public_key)?;
-    
-                let signature = RSASignature::new(Value::known(self.sign_big.clone()));             // cloning might be slow
-                let signature = config.rsa_config.assign_signature(ctx, signature)?;
-    
-                let mut verifier = RSASignatureVerifier::new(
-                    config.rsa_config.clone(),
-                    config.sha256_config.clone()
-                );
-    
-                let (is_valid, hashed_msg) = verifier.verify_pkcs1v15_signature(ctx, &public_key, &self.msg, &signature)?;
-                biguint_config.gate().assert_is_const(ctx, &is_valid, F::one());
-                biguint_config.range().finalize(ctx);
-                {
-                    println!("total advice cells: {}", ctx.total_advice);
-                    let const_rows = ctx.total_fixed + 1;
-                    println!("maximum rows used by a fixed column: {const_rows}");
-                    println!("lookup cells used: {}", ctx.cells_to_lookup.len());
-                }                
-                let public_key_cells = public_key
-                    .n
-                    .limbs()
-                    .into_iter()
-                    .map(|v| v.cell())
-                    .collect::<Vec<Cell>>();
-                let hashed_msg_cells = hashed_msg
-                    .into_iter()
-                    .map(|v| v.cell())
-                    .collect::<Vec<Cell>>();
-                
-                Ok((public_key_cells, hashed_msg_cells))
-            },
-        )?;
-        for (i, cell) in public_key_cells.into_iter().enumerate() {
-            layouter.constrain_instance(cell, config.n_instance, i)?;
-        }
-        for (i, cell) in hashed_msg_cells.into_iter().enumerate() {
-            layouter.constrain_instance(cell, config.hash_instance, i)?;
+    /// The instance column backing `column`, `None` unless the params expose it.
+    pub fn instance_column(&self, column: InstanceColumn) -> Option<Column<Instance>> {
+        self.instances.iter().find(|(exposed, _, _)| *exposed == column).map(|(_, instance, _)| *instance)
+    }
+
+    /// The instance column and row of the `offset`th value of `column`.
+    ///
+    /// # Panics
+    /// If the params don't expose `column`, or `offset` is past its declared length. Public inputs
+    /// are sized by `CircuitParams::instance_columns`, so such a cell would be tied to a row no
+    /// verifier fills in.
+    pub fn instance_cell(&self, column: InstanceColumn, offset: usize) -> (Column<Instance>, usize) {
+        let (_, instance, len) = self
+            .instances
+            .iter()
+            .find(|(exposed, _, _)| *exposed == column)
+            .unwrap_or_else(|| panic!("instance column {} not exposed by the params", column.name()));
+        assert!(offset < *len, "offset {offset} past the {len} values of instance column {}", column.name());
+        (*instance, offset)
+    }
+
+    /// Constrains `cells` to the values of `column`, in order.
+    pub fn constrain_instance(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        column: InstanceColumn,
+        cells: impl IntoIterator<Item = Cell>
+    ) -> Result<(), Error> {
+        for (i, cell) in cells.into_iter().enumerate() {
+            let (instance, row) = self.instance_cell(column, i);
+            layouter.constrain_instance(cell, instance, row)?;
         }
         Ok(())
+    }
 
-    } 
-}
+    /// Loads the SHA256 and range lookup tables, once per circuit.
+    pub fn load_lookup_tables(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        self.sha256_config.load(layouter)?;
+        self.range().load_lookup_table(layouter)
+    }
 
+    pub fn new_context<'v>(&self, region: Region<'v, F>) -> Context<'v, F> {
+        self.rsa_config.biguint_config().new_context(region)
+    }
 
-#[cfg(test)]
-mod test {
-    use super::*;
-    use std::fs::File;
-    use std::str::FromStr;
-    use std::io::Read;
-    use x509_parser::pem::parse_x509_pem;
-    use x509_parser::certificate::X509Certificate;
-    use x509_parser::public_key::PublicKey;
-    use sha2::{Digest, Sha256};
+    pub fn range(&self) -> &RangeConfig<F> {
+        self.rsa_config.biguint_config().range()
+    }
 
-    pub fn check_signature(cert: &X509Certificate<'_>, issuer: &X509Certificate<'_>) -> bool {
-        let issuer_public_key = issuer.public_key();
-        cert.verify_signature(Some(&issuer_public_key)).is_ok()
+    /// A fresh copy of the SHA256 config, to share across the `verify_into_context` calls of one context.
+    pub fn sha256_config(&self) -> Sha256DynamicConfig<F> {
+        self.sha256_config.clone()
     }
 
-    macro_rules! impl_individual_cert_verification_test_circuit {
-        ($verify_cert_path:expr, $issuer_cert_path: expr, $should_err: expr) => {
-            // Read the PEM certificate from a file
-            let mut cert_file = File::open($verify_cert_path).expect("Failed to open PEM file");
-            let mut cert_pem_buffer = Vec::new();
-            cert_file.read_to_end(&mut cert_pem_buffer).expect("Failed to read PEM file");
-
-            // Parse the PEM certificate using x509-parser
-            let cert_pem = parse_x509_pem(&cert_pem_buffer).expect("Failed to parse cert 3 PEM").1;
-            let cert = cert_pem.parse_x509().expect("Failed to parse PEM certificate");
-
-            // Extract the TBS (To-Be-Signed) data from the certificate 3
-            let tbs = &cert.tbs_certificate.as_ref();
-            // println!("TBS (To-Be-Signed): {:x?}", tbs);
-
-            // Extract the signature from cert 3
-            let signature_bytes = &cert.signature_value;
-            let signature_bigint = BigUint::from_bytes_be(&signature_bytes.data);
-            // println!("Signature: {:?}", signature_bigint);
-
-            let mut issuer_cert_file = File::open($issuer_cert_path).expect("Failed to open cert 2PEM file");
-            let mut issuer_cert_pem_buffer = Vec::new();
-            issuer_cert_file.read_to_end(&mut issuer_cert_pem_buffer).expect("Failed to read cert 2 PEM file");
-
-            // Parse the PEM certificate using x509-parser
-            let issuer_cert_pem = parse_x509_pem(&issuer_cert_pem_buffer).expect("Failed to parse cert 3 PEM").1;
-            let issuer_cert = issuer_cert_pem.parse_x509().expect("Failed to parse PEM certificate");
-            
-            // Extract the public key of cert 2
-            let public_key_modulus = match issuer_cert.public_key().parsed().unwrap() {
-                PublicKey::RSA(pub_key) => {
-                    let modulus = BigUint::from_bytes_be(pub_key.modulus);
-                    // println!("Public Key modulus: {:?}", modulus);
-                    modulus
-                },
-                _ => panic!("Failed to grab modulus. Not RSA")
-            };
+    /// One-line summary of the effective dimensions, to check params before a long proving run.
+    pub fn describe(&self) -> String {
+        let params = &self.params;
+        let instance_columns = self.instances.len();
+        format!(
+            "k={} bits_len={} limbs={}x{}-bit msg_len={} (messages up to {} bytes) digest={:?} \
+             advice={} lookup_advice={} fixed={} sha256_lookup_advice={} instance_columns={}",
+            params.k,
+            params.bits_len,
+            params.num_limbs(),
+            params.limb_bits,
+            params.msg_len,
+            params.max_supported_msg_len(),
+            params.digest,
+            params.num_advice,
+            params.num_lookup_advice,
+            params.num_fixed,
+            params.sha256_lookup_advice,
+            instance_columns
+        )
+    }
 
-            // // Verify Cert3 in Rust
-            // let is_valid = check_signature(&cert, &issuer_cert);
+    /// Constrains `[len, extnValue bytes..]` of the subjectAltName extension whose `extnID` starts at
+    /// `offset` in `msg_bytes`. The offset is a witness, so the `SAN_PREFIX` anchor is what ties the
+    /// window to the SAN extension.
+    fn assign_san<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        msg_bytes: &[AssignedValue<'v, F>],
+        offset: usize,
+        max_len: usize
+    ) -> Vec<AssignedValue<'v, F>> {
+        let range = self.rsa_config.biguint_config().range();
+        let gate = range.gate();
+        let start = gate.load_witness(ctx, Value::known(F::from(offset as u64)));
+        let window = select_window(gate, ctx, msg_bytes, &start, SAN_PREFIX.len() + 1 + max_len);
+        for (byte, expected) in window.iter().zip(SAN_PREFIX) {
+            gate.assert_is_const(ctx, byte, F::from(expected as u64));
+        }
 
-            // Verify using circuit
-            let circuit = CertificateVerificationCircuit::<Fr> {
-                n_big: public_key_modulus.clone(),
-                sign_big: signature_bigint,
-                msg: tbs.to_vec(),
-                _f: std::marker::PhantomData,
-            };
-            
-            let hashed_msg = Sha256::digest(&tbs);
-            let num_limbs = 2048 / 64;
-            let limb_bits = 64;
-            let n_fes = decompose_biguint::<Fr>(&public_key_modulus, num_limbs, limb_bits);
-            
-            let hash_fes = hashed_msg.iter().map(|byte| Fr::from(*byte as u64)).collect::<Vec<Fr>>();
-            let public_inputs = vec![n_fes,hash_fes];
-            
-            let k = DEGREE as u32;
-
-            let prover = match MockProver::run(k, &circuit, public_inputs) {
-                Ok(prover) => prover,
-                Err(e) => panic!("{:?}", e),
-            };
+        // Short-form length, so the extnValue starts right after it
+        let san_len = window[SAN_PREFIX.len()].clone();
+        range.check_less_than_safe(ctx, &san_len, max_len as u64 + 1);
+        let mut san = vec![san_len.clone()];
+        for (j, byte) in window[SAN_PREFIX.len() + 1..].iter().enumerate() {
+            let in_san = range.is_less_than(ctx, Constant(F::from(j as u64)), Existing(&san_len), 8);
+            san.push(gate.mul(ctx, Existing(byte), Existing(&in_san)));
+        }
+        san
+    }
 
-            if $should_err {
-                assert!(prover.verify().is_err());
-            } else {
-                assert_eq!(prover.verify(), Ok(()));
-            }
-        };
+    /// Constrains `[len, OID bytes..]` of the TBS `signature` AlgorithmIdentifier. Unlike the SAN, its
+    /// position is fixed by the TBS layout (see `assign_tbs_signature_start`), so the window can't
+    /// be moved to another OID (such as the SPKI's rsaEncryption).
+    fn assign_sig_alg<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        msg_bytes: &[AssignedValue<'v, F>],
+        max_len: usize
+    ) -> Vec<AssignedValue<'v, F>> {
+        let range = self.rsa_config.biguint_config().range();
+        let gate = range.gate();
+        let start = assign_tbs_signature_start(range, ctx, msg_bytes);
+
+        // AlgorithmIdentifier SEQUENCE header, then the OID tag and its short-form length
+        let window = select_window(gate, ctx, msg_bytes, &start, 4 + max_len);
+        gate.assert_is_const(ctx, &window[0], F::from(der::TAG_SEQUENCE as u64));
+        gate.assert_is_const(ctx, &window[2], F::from(der::TAG_OID as u64));
+        let oid_len = window[3].clone();
+        range.check_less_than_safe(ctx, &oid_len, max_len as u64 + 1);
+        let mut oid = vec![oid_len.clone()];
+        for (j, byte) in window[4..].iter().enumerate() {
+            let in_oid = range.is_less_than(ctx, Constant(F::from(j as u64)), Existing(&oid_len), 8);
+            oid.push(gate.mul(ctx, Existing(byte), Existing(&in_oid)));
+        }
+        oid
     }
-    
-    #[test]
-    fn test_individual_certificate_verification2() {
-        impl_individual_cert_verification_test_circuit!(
-            "./certs/cert_3.pem",
-            "./certs/cert_2.pem",
-            false
-        );
+
+    /// Constrains and returns `issuer_key_hash` of the modulus `n_big` with the assigned little-endian
+    /// `limbs`: its bytes are hashed in the next SHA256 slot, whose input is constrained to them.
+    fn assign_issuer_key_hash<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        sha256_config: &mut Sha256DynamicConfig<F>,
+        limbs: &[AssignedValue<'v, F>],
+        n_big: &BigUint
+    ) -> Result<AssignedValue<'v, F>, Error> {
+        let range = self.rsa_config.biguint_config().range();
+        let gate = range.gate();
+        let mut n_bytes = unpack_le_bytes(range, ctx, limbs, self.params.limb_bits);
+        n_bytes.reverse();
+
+        let preimage = modulus_bytes(n_big, self.params.bits_len);
+        let (preimage_cells, digest) = commitment::assign_commitment_digest(gate, ctx, sha256_config, &preimage)?;
+        for (byte, cell) in preimage_cells.iter().zip(&n_bytes) {
+            gate.assert_equal(ctx, Existing(byte), Existing(cell));
+        }
+        Ok(commitment::assign_packed_commitment(gate, ctx, &digest))
     }
 
-    #[test]
-    fn test_individual_certificate_verification3() {
-        impl_individual_cert_verification_test_circuit!(
-            "./certs/cert_2.pem",
-            "./certs/cert_1.pem",
-            false
-        );
+    /// Constrains and returns the range-checked `YYMMDDHHMMSS` digits of the notBefore and notAfter
+    /// UTCTimes. The validity follows the issuer of `assign_issuer_location`, so like
+    /// `assign_sig_alg` the times can't be taken from other bytes, or swapped.
+    fn assign_validity_digits<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        msg_bytes: &[AssignedValue<'v, F>]
+    ) -> [Vec<AssignedValue<'v, F>>; 2] {
+        let range = self.rsa_config.biguint_config().range();
+        let gate = range.gate();
+        let (issuer_start, issuer_len) = assign_issuer_location(range, ctx, msg_bytes);
+        let validity_start = gate.add(ctx, Existing(&issuer_start), Existing(&issuer_len));
+
+        let validity = select_window(gate, ctx, msg_bytes, &validity_start, 32);
+        let anchors = UTC_VALIDITY_HEADER.iter().zip(&validity[..4]).chain(UTC_NOT_AFTER_HEADER.iter().zip(&validity[16..19]));
+        for (expected, byte) in anchors {
+            gate.assert_is_const(ctx, byte, F::from(*expected as u64));
+        }
+        gate.assert_is_const(ctx, &validity[31], F::from(b'Z' as u64));
+        [&validity[4..16], &validity[19..31]].map(|time| {
+            time.iter()
+                .map(|byte| {
+                    let digit = gate.sub(ctx, Existing(byte), Constant(F::from(b'0' as u64)));
+                    range.check_less_than_safe(ctx, &digit, 10);
+                    digit
+                })
+                .collect()
+        })
     }
 
+    /// Constrains `[current_time, not expired]`: notAfter, with the `digits` of
+    /// `assign_validity_digits`, as `YYYYMMDDHHMMSS` (see `not_after_time`) is at least the
+    /// witnessed `current_time`, or not.
+    fn assign_not_expired<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        digits: &[AssignedValue<'v, F>],
+        current_time: u64
+    ) -> Vec<AssignedValue<'v, F>> {
+        let range = self.rsa_config.biguint_config().range();
+        let gate = range.gate();
+        let powers = (0..12).rev().map(|i| Constant(F::from(10u64.pow(i)))).collect::<Vec<_>>();
+        let utc_time = gate.inner_product(ctx, digits.iter().map(Existing), powers);
+        // UTCTime years below 50 are 20YY
+        let year = gate.mul_add(ctx, Existing(&digits[0]), Constant(F::from(10)), Existing(&digits[1]));
+        let is_2000s = range.is_less_than(ctx, Existing(&year), Constant(F::from(50)), 7);
+        let century = gate.add(ctx, Existing(&is_2000s), Constant(F::from(19)));
+        let not_after = gate.mul_add(ctx, Existing(&century), Constant(F::from(10u64.pow(12))), Existing(&utc_time));
 
-    #[test]
-    fn test_individual_certificate_verification4() {
-        impl_individual_cert_verification_test_circuit!(
-            "./certs/cert_3.pem",
-            "./certs/cert_1.pem",
-            true
-        );
+        // `YYYYMMDDHHMMSS` stays below 10^14 < 2^47
+        let current_time = gate.load_witness(ctx, Value::known(F::from(current_time)));
+        range.range_check(ctx, &current_time, 47);
+        let not_after_plus_one = gate.add(ctx, Existing(&not_after), Constant(F::one()));
+        let not_expired = range.is_less_than(ctx, Existing(&current_time), Existing(&not_after_plus_one), 48);
+        vec![current_time, not_expired]
     }
-}
 
+    /// Constrains the TBS hash input to the TBS of the certificate hash input and returns the
+    /// `[start, end)` of the TBS. Like `assign_sig_alg`, this relies on the DER layout: a certificate
+    /// and TBS with 2-byte long-form lengths put the TBS at byte 4, and its own length fixes its end,
+    /// so the range can't be moved to other bytes of the certificate.
+    fn assign_signed_range<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        cert_bytes: &[AssignedValue<'v, F>],
+        cert_len: &AssignedValue<'v, F>,
+        tbs_bytes: &[AssignedValue<'v, F>],
+        tbs_len: &AssignedValue<'v, F>
+    ) -> Vec<AssignedValue<'v, F>> {
+        let range = self.rsa_config.biguint_config().range();
+        let gate = range.gate();
+        for bytes in [cert_bytes, tbs_bytes] {
+            gate.assert_is_const(ctx, &bytes[0], F::from(der::TAG_SEQUENCE as u64));
+            gate.assert_is_const(ctx, &bytes[1], F::from(0x82));
+        }
+        let tbs_content_len = gate.mul_add(ctx, Existing(&tbs_bytes[2]), Constant(F::from(256)), Existing(&tbs_bytes[3]));
+        let expected_len = gate.add(ctx, Existing(&tbs_content_len), Constant(F::from(4)));
+        gate.assert_equal(ctx, Existing(tbs_len), Existing(&expected_len));
+
+        let len_bits = (usize::BITS - self.params.msg_len.leading_zeros()) as usize;
+        let start = gate.load_witness(ctx, Value::known(F::from(4)));
+        gate.assert_is_const(ctx, &start, F::from(4));
+        let end = gate.add(ctx, Existing(tbs_len), Constant(F::from(4)));
+        let cert_len_plus_one = gate.add(ctx, Existing(cert_len), Constant(F::one()));
+        let within_cert = range.is_less_than(ctx, Existing(&end), Existing(&cert_len_plus_one), len_bits + 1);
+        gate.assert_is_const(ctx, &within_cert, F::one());
+        // So the loop below covers every TBS byte
+        range.check_less_than_safe(ctx, tbs_len, (cert_bytes.len() - 4) as u64 + 1);
+
+        for (i, (tbs_byte, cert_byte)) in tbs_bytes.iter().zip(&cert_bytes[4..]).enumerate() {
+            let in_tbs = range.is_less_than(ctx, Constant(F::from(i as u64)), Existing(tbs_len), len_bits);
+            let diff = gate.sub(ctx, Existing(tbs_byte), Existing(cert_byte));
+            let masked = gate.mul(ctx, Existing(&diff), Existing(&in_tbs));
+            gate.assert_is_const(ctx, &masked, F::zero());
+        }
+        vec![start, end]
+    }
+
+    /// Constrains that the public `[len, name..]` target equals one of the dNSName entries of `san`
+    /// (as returned by `assign_san`) and returns the assigned target.
+    ///
+    /// Every offset of the SAN is tried as `0x82 len name`, so only the matching entry is ever
+    /// compared against the target. Entries aren't tied to GeneralName boundaries, which is fine as
+    /// long as no other entry kind embeds that byte pattern (dNSName/rfc822Name bytes are IA5, so
+    /// they can't contain `0x82`). Matching is exact: a `*.example.com` entry only matches that
+    /// literal string.
+    fn assign_dns_name_match<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        san: &[AssignedValue<'v, F>],
+        dns_name: &[u8],
+        max_name_len: usize
+    ) -> Vec<AssignedValue<'v, F>> {
+        let range = self.rsa_config.biguint_config().range();
+        let gate = range.gate();
+        let target = dns_name_instance::<F>(dns_name, max_name_len)
+            .into_iter()
+            .map(|fe| gate.load_witness(ctx, Value::known(fe)))
+            .collect::<Vec<_>>();
+        let (name_len, name) = (&target[0], &target[1..]);
+        range.check_less_than_safe(ctx, name_len, max_name_len as u64 + 1);
+        let is_empty = gate.is_zero(ctx, name_len);
+        gate.assert_is_const(ctx, &is_empty, F::zero());
+        let past_name = (0..max_name_len)
+            .map(|j| {
+                let in_name = range.is_less_than(ctx, Constant(F::from(j as u64)), Existing(name_len), 8);
+                gate.not(ctx, Existing(&in_name))
+            })
+            .collect::<Vec<_>>();
+
+        let num_matches = self.count_dns_name_matches(ctx, &san[1..], name_len, name, &past_name);
+        let no_match = gate.is_zero(ctx, &num_matches);
+        gate.assert_is_const(ctx, &no_match, F::zero());
+        target
+    }
+
+    /// Number of offsets in the SAN `extnValue` `san_bytes` holding `0x82 name_len name`, comparing
+    /// only the bytes of the zero-padded `name` where `past_name` is 0.
+    fn count_dns_name_matches<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        san_bytes: &[AssignedValue<'v, F>],
+        name_len: &AssignedValue<'v, F>,
+        name: &[AssignedValue<'v, F>],
+        past_name: &[AssignedValue<'v, F>]
+    ) -> AssignedValue<'v, F> {
+        let gate = self.rsa_config.biguint_config().gate();
+        // Starting at 2 skips the GeneralNames SEQUENCE header
+        let mut matches = vec![];
+        for p in 2..san_bytes.len() {
+            let mut checks = vec![
+                gate.is_equal(ctx, Existing(&san_bytes[p - 2]), Constant(F::from(der::TAG_DNS_NAME as u64))),
+                gate.is_equal(ctx, Existing(&san_bytes[p - 1]), Existing(name_len)),
+            ];
+            for (j, (name_byte, past_name)) in name.iter().zip(past_name).enumerate() {
+                let byte = match san_bytes.get(p + j) {
+                    Some(byte) => Existing(byte),
+                    None => Constant(F::zero()),
+                };
+                let is_eq = gate.is_equal(ctx, byte, Existing(name_byte));
+                checks.push(gate.or(ctx, Existing(&is_eq), Existing(past_name)));
+            }
+            let num_checks = checks.len();
+            let passed = gate.sum(ctx, checks.iter().map(Existing));
+            matches.push(gate.is_equal(ctx, Existing(&passed), Constant(F::from(num_checks as u64))));
+        }
+        gate.sum(ctx, matches.iter().map(Existing))
+    }
+
+    /// Constrains the `dns_names_commitment` of `san` (as returned by `assign_san`) and returns it.
+    ///
+    /// The prover supplies `preimage`, the sorted names. Each name must be a dNSName entry of the SAN
+    /// (see `assign_dns_name_match`), the names must be strictly increasing, hence distinct, and their
+    /// `0x82 len name` TLVs must add up to the GeneralNames contents; so they are exactly the SAN's
+    /// entries, and a SAN with anything but distinct dNSNames can't be committed to.
+    fn assign_dns_names_commitment<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        sha256_config: &mut Sha256DynamicConfig<F>,
+        san: &[AssignedValue<'v, F>],
+        preimage: &[u8],
+        max_name_len: usize
+    ) -> Result<Vec<AssignedValue<'v, F>>, Error> {
+        let range = self.rsa_config.biguint_config().range();
+        let gate = range.gate();
+        let preimage_cells = preimage
+            .iter()
+            .map(|byte| gate.load_witness(ctx, Value::known(F::from(*byte as u64))))
+            .collect::<Vec<_>>();
+        let entries = preimage_cells.chunks(max_name_len + 1).collect::<Vec<_>>();
+
+        let mut is_empty = vec![];
+        for entry in &entries {
+            let (name_len, name) = (&entry[0], &entry[1..]);
+            range.check_less_than_safe(ctx, name_len, max_name_len as u64 + 1);
+            let past_name = (0..max_name_len)
+                .map(|j| {
+                    let in_name = range.is_less_than(ctx, Constant(F::from(j as u64)), Existing(name_len), 8);
+                    gate.not(ctx, Existing(&in_name))
+                })
+                .collect::<Vec<_>>();
+            // Zero padding, so the preimage is canonical and padded names sort like the names
+            for (byte, past_name) in name.iter().zip(&past_name) {
+                let padding = gate.mul(ctx, Existing(byte), Existing(past_name));
+                gate.assert_is_const(ctx, &padding, F::zero());
+            }
+            let num_matches = self.count_dns_name_matches(ctx, &san[1..], name_len, name, &past_name);
+            let no_match = gate.is_zero(ctx, &num_matches);
+            let empty = gate.is_zero(ctx, name_len);
+            let is_used = gate.not(ctx, Existing(&empty));
+            let unmatched = gate.mul(ctx, Existing(&no_match), Existing(&is_used));
+            gate.assert_is_const(ctx, &unmatched, F::zero());
+            is_empty.push(empty);
+        }
+        for i in 1..entries.len() {
+            // Names first, then empty slots
+            let is_used = gate.not(ctx, Existing(&is_empty[i]));
+            let gap = gate.mul(ctx, Existing(&is_used), Existing(&is_empty[i - 1]));
+            gate.assert_is_const(ctx, &gap, F::zero());
+            let is_less = is_lex_less(range, ctx, &entries[i - 1][1..], &entries[i][1..]);
+            let not_less = gate.not(ctx, Existing(&is_less));
+            let unordered = gate.mul(ctx, Existing(&is_used), Existing(&not_less));
+            gate.assert_is_const(ctx, &unordered, F::zero());
+        }
+
+        // `[len, 30 L entries..]`: the name TLVs fill the short-form GeneralNames SEQUENCE
+        gate.assert_is_const(ctx, &san[1], F::from(der::TAG_SEQUENCE as u64));
+        let seq_len = gate.add(ctx, Existing(&san[2]), Constant(F::from(2)));
+        gate.assert_equal(ctx, Existing(&seq_len), Existing(&san[0]));
+        let names_len = gate.sum(ctx, entries.iter().map(|entry| Existing(&entry[0])));
+        let num_empty = gate.sum(ctx, is_empty.iter().map(Existing));
+        let num_names = gate.sub(ctx, Constant(F::from(entries.len() as u64)), Existing(&num_empty));
+        let tlvs_len = gate.mul_add(ctx, Existing(&num_names), Constant(F::from(2)), Existing(&names_len));
+        gate.assert_equal(ctx, Existing(&tlvs_len), Existing(&san[2]));
+
+        let (hashed_cells, digest) = commitment::assign_commitment_digest(gate, ctx, sha256_config, preimage)?;
+        for (byte, cell) in hashed_cells.iter().zip(&preimage_cells) {
+            gate.assert_equal(ctx, Existing(byte), Existing(cell));
+        }
+        Ok(digest)
+    }
+}
+
+
+/// Cells of one certificate verification, for the caller to constrain to instances.
+pub struct AssignedCertificate<'v, F: PrimeField> {
+    /// Modulus limbs (constants with `fixed_modulus`).
+    pub modulus: Vec<AssignedValue<'v, F>>,
+    /// SHA-256 output bytes of the message. These are the very cells the signature check reads
+    /// (their leading bytes with `digest_truncation`), not a second computation of the digest, so
+    /// the exposed digest and the validity can't refer to different messages. With
+    /// `signed_range`, the digest of the whole certificate, whose TBS bytes are constrained equal to
+    /// the ones the signature check hashes.
+    pub digest: Vec<AssignedValue<'v, F>>,
+    /// Cells of the optional instance columns, each with its column.
+    pub extra: Vec<(InstanceColumn, Vec<AssignedValue<'v, F>>)>,
+    /// Cells each gadget of the verification used.
+    pub costs: Vec<GadgetCost>,
+}
+
+impl<F: PrimeField> CertificateVerificationCircuit<F> {
+    /// Verifies the certificate in `ctx` without finalizing the range chip, so it can be composed
+    /// with other gadgets (or other certificates) in a larger circuit: the caller loads the lookup
+    /// tables, calls `config.range().finalize(ctx)` once after the last gadget, and constrains the
+    /// returned cells. Each call takes the next slot(s) of `sha256_config`, which the caller shares
+    /// across calls in the same context.
+    pub fn verify_into_context<'v>(
+        &self,
+        config: &CertificateVerificationConfig<F>,
+        ctx: &mut Context<'v, F>,
+        sha256_config: &mut Sha256DynamicConfig<F>
+    ) -> Result<AssignedCertificate<'v, F>, Error> {
+        let biguint_config = config.rsa_config.biguint_config();
+        let public_exponent = &config.params.public_exponent;
+        let mut costs = CostTracker::new(ctx);
+
+        // Verify Cert
+        let public_key = match &config.params.fixed_modulus {
+            // Constant limbs need neither the range checks of `assign_public_key` nor an instance
+            Some(n_fixed) => {
+                let n = biguint_config.assign_constant(ctx, n_fixed.clone())?;
+                let e = if config.params.variable_exponent {
+                    let e = biguint_config.gate().load_witness(ctx, Value::known(biguint_to_fe(public_exponent)));
+                    AssignedRSAPubE::Var(e)
+                } else {
+                    AssignedRSAPubE::Fix(public_exponent.clone())
+                };
+                AssignedRSAPublicKey::new(n, e)
+            }
+            None => {
+                let e = if config.params.variable_exponent {
+                    RSAPubE::Var(Value::known(biguint_to_fe(public_exponent)))
+                } else {
+                    RSAPubE::Fix(public_exponent.clone())
+                };
+                let public_key = RSAPublicKey::new(Value::known(self.n_big.clone()), e);     // cloning might be slow
+                config.rsa_config.assign_public_key(ctx, public_key)?
+            }
+        };
+
+        // A product of two odd primes is odd, so an even modulus is garbage whatever the signature
+        assert_odd(biguint_config.range(), ctx, &public_key.n.limbs()[0], config.params.limb_bits);
+        // The exponentiation decomposes a variable exponent into `exp_limb_bits` bits, which bounds it
+        let exponent = match &public_key.e {
+            AssignedRSAPubE::Var(e) => {
+                assert_odd(biguint_config.range(), ctx, e, config.params.exp_limb_bits);
+                let is_one = biguint_config.gate().is_equal(ctx, Existing(e), Constant(F::one()));
+                biguint_config.gate().assert_is_const(ctx, &is_one, F::zero());
+                Some(e.clone())
+            }
+            AssignedRSAPubE::Fix(_) => None,
+        };
+        if config.params.check_small_factors {
+            let limbs = public_key.n.limbs();
+            assert_no_small_factors(biguint_config.range(), ctx, &limbs, config.params.limb_bits, &SMALL_PRIMES);
+        }
+
+        let signature = RSASignature::new(Value::known(self.sign_big.clone()));             // cloning might be slow
+        let signature = config.rsa_config.assign_signature(ctx, signature)?;
+        costs.mark(ctx, "rsa inputs");
+
+        // With `signed_range`, the certificate is hashed first and the TBS slot is constrained to
+        // the bytes of its TBS, so everything below reads the TBS either way
+        let mut signed_range = None;
+        let hash_result = match self.signed_range() {
+            Some(range) => {
+                let cert_hash = sha256_config.digest(ctx, &self.msg, None)?;
+                let tbs_hash = sha256_config.digest(ctx, &self.msg[range], None)?;
+                let bounds = config.assign_signed_range(ctx, &cert_hash.input_bytes, &cert_hash.input_len, &tbs_hash.input_bytes, &tbs_hash.input_len);
+                signed_range = Some((cert_hash.output_bytes, bounds));
+                tbs_hash
+            }
+            None if config.params.signed_range => return Err(Error::Synthesis),
+            None => sha256_config.digest(ctx, &self.msg, None)?,
+        };
+        costs.mark(ctx, "sha256");
+        let tbs = self.tbs();
+        let hashed_msg = hash_result.output_bytes;
+        // Without `signed_range`, a slice of the same cells returned as `digest` below: the digest
+        // instance and the signature check are bound without a copy constraint
+        let signed_digest = &hashed_msg[..config.params.signed_digest_len()];
+        let is_valid = verify_pkcs1v15_signature(&config.rsa_config, ctx, &public_key, signed_digest, &signature, config.params.montgomery_exp)?;
+        costs.mark(ctx, "rsa");
+        if config.params.not_self_signed {
+            // The same SPKI layout a chain link certifies its issuer key with
+            let (offset, _) = chain::rsa_spki(tbs, config.params.bits_len).ok_or(Error::Synthesis)?;
+            let gate = biguint_config.gate();
+            let spki_limbs = assign_spki_modulus(gate, ctx, &hash_result.input_bytes, offset, config.params.bits_len, config.params.limb_bits);
+            let limb_eqs = spki_limbs
+                .iter()
+                .zip(public_key.n.limbs().iter())
+                .map(|(spki_limb, key_limb)| gate.is_equal(ctx, Existing(spki_limb), Existing(key_limb)))
+                .collect::<Vec<_>>();
+            let equal_limbs = gate.sum(ctx, limb_eqs.iter().map(Existing));
+            let self_signed = gate.is_equal(ctx, Existing(&equal_limbs), Constant(F::from(limb_eqs.len() as u64)));
+            gate.assert_is_const(ctx, &self_signed, F::zero());
+        }
+
+        // Additional (column, cells) pairs to constrain to instances
+        let exposes = |column| config.instance_column(column).is_some();
+        let mut extra = vec![];
+        if let Some(max_len) = config.params.san_max_len {
+            let ext = der::find_extension_entry(tbs, der::OID_SUBJECT_ALT_NAME)
+                .filter(|ext| !ext.critical && ext.value.len() <= max_len)
+                .ok_or(Error::Synthesis)?;
+            // OIDs use a short-form length, so the extnID TLV starts 2 bytes before its contents
+            let san = config.assign_san(ctx, &hash_result.input_bytes, ext.oid.start - 2, max_len);
+            if let (true, Some(max_name_len)) = (exposes(InstanceColumn::DnsName), config.params.dns_name_max_len) {
+                let target = config.assign_dns_name_match(ctx, &san, &self.dns_name, max_name_len);
+                extra.push((InstanceColumn::DnsName, target));
+            }
+            if let (true, Some((max_names, max_name_len))) = (exposes(InstanceColumn::DnsNames), config.params.dns_names_commitment) {
+                let preimage = dns_names_preimage(tbs, max_names, max_name_len).ok_or(Error::Synthesis)?;
+                let commitment = config.assign_dns_names_commitment(ctx, sha256_config, &san, &preimage, max_name_len)?;
+                extra.push((InstanceColumn::DnsNames, commitment));
+            }
+            if exposes(InstanceColumn::San) {
+                extra.push((InstanceColumn::San, san));
+            }
+        }
+        if let (true, Some(max_len)) = (exposes(InstanceColumn::SigAlg), config.params.sig_alg_max_len) {
+            // Fail early, rather than with an unsatisfied constraint, on TBS layouts the
+            // fixed-position check doesn't cover
+            let oid = der::tbs_signature_algorithm_oid(tbs).filter(|oid| oid.len <= max_len);
+            let expected_start = tbs.get(4 + TBS_V3_HEADER.len()).map(|len| 5 + TBS_V3_HEADER.len() + *len as usize);
+            match oid {
+                Some(oid) if tbs[1] == 0x82 && Some(oid.start - 2) == expected_start => {}
+                _ => return Err(Error::Synthesis),
+            }
+            let sig_alg = config.assign_sig_alg(ctx, &hash_result.input_bytes, max_len);
+            extra.push((InstanceColumn::SigAlg, sig_alg));
+        }
+        let digest = match signed_range {
+            Some((cert_digest, bounds)) => {
+                extra.push((InstanceColumn::SignedRange, bounds));
+                if exposes(InstanceColumn::TbsDigest) {
+                    extra.push((InstanceColumn::TbsDigest, hashed_msg));
+                }
+                cert_digest
+            }
+            None => hashed_msg,
+        };
+        let validity_digits = if exposes(InstanceColumn::NotExpired) || exposes(InstanceColumn::ValidityDates) {
+            // Fail early on TBS layouts the walk to the validity doesn't cover
+            if !validity_walkable(tbs) {
+                return Err(Error::Synthesis);
+            }
+            Some(config.assign_validity_digits(ctx, &hash_result.input_bytes))
+        } else {
+            None
+        };
+        if let (true, Some([_, not_after])) = (exposes(InstanceColumn::NotExpired), &validity_digits) {
+            let not_expired = config.assign_not_expired(ctx, not_after, self.current_time);
+            extra.push((InstanceColumn::NotExpired, not_expired));
+        }
+        if let (true, Some(digits)) = (exposes(InstanceColumn::ValidityDates), &validity_digits) {
+            let dates = digits.iter().map(|digits| utc_time_to_unix(biguint_config.range(), ctx, digits)).collect();
+            extra.push((InstanceColumn::ValidityDates, dates));
+        }
+        if let (true, Some(exponent)) = (exposes(InstanceColumn::Exponent), exponent) {
+            extra.push((InstanceColumn::Exponent, vec![exponent]));
+        }
+        if exposes(InstanceColumn::IssuerKeyHash) {
+            let key_hash = config.assign_issuer_key_hash(ctx, sha256_config, &public_key.n.limbs(), &self.n_big)?;
+            extra.push((InstanceColumn::IssuerKeyHash, vec![key_hash]));
+        }
+        match exposes(InstanceColumn::Valid) {
+            true => extra.push((InstanceColumn::Valid, vec![is_valid])),
+            false => biguint_config.gate().assert_is_const(ctx, &is_valid, F::one()),
+        }
+        if exposes(InstanceColumn::ByteRange) {
+            let range = biguint_config.range();
+            let len_bits = (usize::BITS - config.params.msg_len.leading_zeros()) as usize;
+            let mut segment_lens = self.segment_lens.clone();
+            segment_lens.resize(config.params.byte_range_segments, 0);
+            let segment_lens = segment_lens
+                .into_iter()
+                .map(|len| {
+                    let len = range.gate().load_witness(ctx, Value::known(F::from(len as u64)));
+                    range.range_check(ctx, &len, len_bits);
+                    len
+                })
+                .collect::<Vec<_>>();
+            let total = range.gate().sum(ctx, segment_lens.iter().map(Existing));
+            range.gate().assert_equal(ctx, Existing(&total), Existing(&hash_result.input_len));
+            extra.push((InstanceColumn::ByteRange, segment_lens));
+        }
+
+        costs.mark(ctx, "certificate fields");
+        Ok(AssignedCertificate { modulus: public_key.n.limbs().to_vec(), digest, extra, costs: costs.finish() })
+    }
+}
+
+impl<F: PrimeField> Circuit<F> for CertificateVerificationCircuit<F> {
+    type Config = CertificateVerificationConfig<F>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        unimplemented!();
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        CertificateVerificationConfig::configure(meta, CircuitParams::current(), 1)
+    }
+
+    fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+        // `configure` laid the circuit out for `CircuitParams::current()`, while witnesses follow
+        // `self.params`. Stale thread-local params would only show up as unrelated constraint
+        // failures. The `k` of the run itself is checked by `mock_run` and `prover::keygen`.
+        assert!(
+            self.params == config.params,
+            "circuit params differ from those the circuit was configured with ({}); call set_current() first",
+            self.params.diff(&config.params).join(", ")
+        );
+        let biguint_config = config.rsa_config.biguint_config();
+        if self.load_tables {
+            config.load_lookup_tables(&mut layouter)?;
+        }
+        let mut first_pass = SKIP_FIRST_PASS;        
+        let (public_key_cells, hashed_msg_cells, extra_cells) = layouter.assign_region(
+            || "certificat chain verifier", 
+            |region| {
+                if first_pass {
+                    first_pass = false;
+                    return Ok((vec![], vec![], vec![]));
+                }
+    
+                let mut aux = biguint_config.new_context(region);
+                let ctx = &mut aux;
+                let mut sha256_config = config.sha256_config.clone();
+                let cert = self.verify_into_context(&config, ctx, &mut sha256_config)?;
+                let digest = match config.params.digest_encoding {
+                    DigestEncoding::Bytes => cert.digest,
+                    DigestEncoding::EvmPacked => {
+                        // Limbs come least significant first
+                        let mut halves = pack_be_bytes(biguint_config.gate(), ctx, &cert.digest, 16);
+                        halves.reverse();
+                        halves
+                    }
+                };
+                biguint_config.range().finalize(ctx);
+                GadgetCost::record_all(cert.costs.clone());
+                CircuitStats {
+                    total_advice: ctx.total_advice,
+                    total_fixed: ctx.total_fixed,
+                    lookup_cells: ctx.cells_to_lookup.len(),
+                }.record();
+                let public_key_cells = cert
+                    .modulus
+                    .into_iter()
+                    .map(|v| v.cell())
+                    .collect::<Vec<Cell>>();
+                let hashed_msg_cells = digest
+                    .into_iter()
+                    .map(|v| v.cell())
+                    .collect::<Vec<Cell>>();
+                let extra_cells = cert
+                    .extra
+                    .into_iter()
+                    .map(|(column, values)| (column, values.into_iter().map(|v| v.cell()).collect::<Vec<Cell>>()))
+                    .collect::<Vec<_>>();
+                
+                Ok((public_key_cells, hashed_msg_cells, extra_cells))
+            },
+        )?;
+        if config.instance_column(InstanceColumn::Modulus).is_some() {
+            config.constrain_instance(&mut layouter, InstanceColumn::Modulus, public_key_cells)?;
+        }
+        if config.instance_column(InstanceColumn::Digest).is_some() {
+            config.constrain_instance(&mut layouter, InstanceColumn::Digest, hashed_msg_cells)?;
+        }
+        for (column, cells) in extra_cells {
+            config.constrain_instance(&mut layouter, column, cells)?;
+        }
+        Ok(())
+
+    } 
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+    use x509_parser::certificate::X509Certificate;
+    use halo2_base::halo2_proofs::{arithmetic::FieldExt, dev::VerifyFailure};
+    use crate::test_utils::{fixture_path, CertFixture};
+
+    pub fn check_signature(cert: &X509Certificate<'_>, issuer: &X509Certificate<'_>) -> bool {
+        let issuer_public_key = issuer.public_key();
+        cert.verify_signature(Some(&issuer_public_key)).is_ok()
+    }
+
+    /// Returns `(issuer modulus, signature, tbs)` for `verify_cert_path` signed by `issuer_cert_path`.
+    pub(crate) fn load_fixture(verify_cert_path: &str, issuer_cert_path: &str) -> (BigUint, BigUint, Vec<u8>) {
+        let fixture = CertFixture::load(verify_cert_path, issuer_cert_path);
+        (fixture.n_big, fixture.sign_big, fixture.msg)
+    }
+
+    /// Runs the `MockProver` with public inputs derived from `n_big` and the SHA256 of `msg`.
+    fn mock_verify(n_big: &BigUint, sign_big: BigUint, msg: Vec<u8>) -> Result<(), Vec<VerifyFailure>> {
+        let fixture = CertFixture::new(n_big.clone(), sign_big, msg);
+        let circuit = fixture.circuit::<Fr>();
+        circuit.params.set_current();
+        let prover = match MockProver::run(circuit.params.k, &circuit, fixture.public_inputs()) {
+            Ok(prover) => prover,
+            Err(e) => panic!("{:?}", e),
+        };
+        prover.verify()
+    }
+
+    macro_rules! impl_individual_cert_verification_test_circuit {
+        ($verify_cert_path:expr, $issuer_cert_path: expr, $should_err: expr) => {
+            let (public_key_modulus, signature_bigint, tbs) = load_fixture($verify_cert_path, $issuer_cert_path);
+
+            // // Verify Cert3 in Rust
+            // let is_valid = check_signature(&cert, &issuer_cert);
+
+            // Verify using circuit
+            let result = mock_verify(&public_key_modulus, signature_bigint, tbs);
+            if $should_err {
+                assert!(result.is_err());
+            } else {
+                assert_eq!(result, Ok(()));
+            }
+        };
+    }
+    
+    #[test]
+    fn test_individual_certificate_verification2() {
+        // cert_3 (DocuSign) under cert_2
+        let vector = test_utils::load_test_vector("docusign");
+        assert_eq!(mock_verify(&vector.n_big, vector.sign_big, vector.msg), Ok(()));
+    }
+
+    #[test]
+    fn test_individual_certificate_verification3() {
+        impl_individual_cert_verification_test_circuit!(
+            "./certs/cert_2.pem",
+            "./certs/cert_1.pem",
+            false
+        );
+    }
+
+
+    #[test]
+    fn test_individual_certificate_verification4() {
+        impl_individual_cert_verification_test_circuit!(
+            "./certs/cert_3.pem",
+            "./certs/cert_1.pem",
+            true
+        );
+    }
+
+    #[test]
+    fn test_tampered_tbs_byte_flip() {
+        let (n_big, sign_big, mut tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        // The digest instance follows the tampered bytes, so only the signature binding can catch this.
+        let idx = tbs.len() / 2;
+        tbs[idx] ^= 0x01;
+        assert!(mock_verify(&n_big, sign_big, tbs).is_err());
+    }
+
+    #[test]
+    fn test_tampered_tbs_length_change() {
+        let (n_big, sign_big, mut tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        tbs.push(0x00);
+        assert!(mock_verify(&n_big, sign_big.clone(), tbs.clone()).is_err());
+
+        tbs.truncate(tbs.len() - 2);
+        assert!(mock_verify(&n_big, sign_big, tbs).is_err());
+    }
+
+    #[test]
+    fn test_reversed_instance_layout() {
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let params = CircuitParams { instance_layout: InstanceLayout::DigestThenModulus, ..Default::default() };
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big, sign_big, tbs, params);
+        circuit.params.set_current();
+
+        let public_inputs = circuit.public_inputs();
+        let prover = MockProver::run(circuit.params.k, &circuit, public_inputs.clone()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // Inputs in the default order must not satisfy the reversed layout
+        let swapped = vec![public_inputs[1].clone(), public_inputs[0].clone()];
+        let prover = MockProver::run(circuit.params.k, &circuit, swapped).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_instance_cells() {
+        let optional = CircuitParams { sig_alg_max_len: Some(16), expiry_check: true, validity: ValidityMode::ExposeResult, ..Default::default() };
+        for instance_layout in [InstanceLayout::ModulusThenDigest, InstanceLayout::DigestThenModulus] {
+            let params = CircuitParams { instance_layout, ..optional.clone() };
+            let mut meta = ConstraintSystem::<Fr>::default();
+            let config = CertificateVerificationConfig::configure(&mut meta, params.clone(), 1);
+            assert_eq!(meta.num_instance_columns(), params.instance_columns().len());
+            for (i, (column, len)) in params.instance_columns().into_iter().enumerate() {
+                // Columns are created in public-input order, so the i-th vector fills the i-th column
+                let (instance, row) = config.instance_cell(column, len - 1);
+                assert_eq!((instance.index(), row), (i, len - 1));
+                assert_eq!(config.instance_column(column), Some(instance));
+                let past_end = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| config.instance_cell(column, len)));
+                assert!(past_end.is_err());
+            }
+            assert_eq!(config.instance_column(InstanceColumn::San), None);
+        }
+    }
+
+    #[test]
+    fn test_san_exposure() {
+        let (n_big, sign_big, tbs) = load_fixture("./certs/san_leaf.pem", "./certs/test_ca.pem");
+        let params = CircuitParams { san_max_len: Some(64), ..Default::default() };
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big, sign_big, tbs, params);
+        circuit.params.set_current();
+
+        let public_inputs = circuit.public_inputs();
+        let san_len = public_inputs[2][0].get_lower_32() as usize;
+        let san = public_inputs[2][1..=san_len].iter().map(|fe| fe.get_lower_32() as u8).collect::<Vec<u8>>();
+        assert_eq!(
+            der::san_dns_names(&san).unwrap(),
+            vec!["example.com", "www.example.com", "api.example.com"]
+        );
+        let prover = MockProver::run(circuit.params.k, &circuit, public_inputs.clone()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // Claiming a different name must fail
+        let mut forged = public_inputs;
+        forged[2][5] = Fr::from(b'x' as u64);
+        let prover = MockProver::run(circuit.params.k, &circuit, forged).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_san_with_reordered_extensions() {
+        let (n_big, sign_big, tbs) = load_fixture("./certs/san_leaf.pem", "./certs/test_ca.pem");
+        let params = CircuitParams { k: 17, san_max_len: Some(64), dns_names_commitment: Some((4, 16)), ..Default::default() };
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big, sign_big, tbs.clone(), params.clone());
+        let expected = circuit.public_inputs()[2].clone();
+
+        // SAN last instead of in the middle, re-signed by test_ca as its issuer would
+        let reordered = test_utils::permute_extensions(&tbs, |exts| {
+            let san = exts.remove(2);
+            exts.push(san);
+        });
+        assert_ne!(der::find_extension(&reordered, der::OID_SUBJECT_ALT_NAME), der::find_extension(&tbs, der::OID_SUBJECT_ALT_NAME));
+        let (n_big, sign_big) = sign_raw_em(&Sha256::digest(&reordered), 0x00);
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big, sign_big, reordered, params);
+        let public_inputs = circuit.public_inputs();
+        assert_eq!(public_inputs[2], expected);
+        circuit.params.set_current();
+        let prover = MockProver::run(circuit.params.k, &circuit, public_inputs).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_num_limbs() {
+        assert_eq!(num_limbs(2048, 64), 32);
+        assert_eq!(num_limbs(3072, 64), 48);
+        assert_eq!(num_limbs(4096, 64), 64);
+        assert_eq!(CircuitParams::rsa_4096_sha256().num_limbs(), 64);
+    }
+
+    #[test]
+    fn test_modulus_instance_from_pem() {
+        let (n_big, _, _) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let n_fes = decompose_biguint::<Fr>(&n_big, 2048 / 64, 64);
+        let pem = std::fs::read_to_string(fixture_path("./certs/cert_2_pubkey.pem")).unwrap();
+        assert_eq!(modulus_instance_from_pem::<Fr>(&pem, &CircuitParams::default()), n_fes);
+    }
+
+    #[test]
+    fn test_dns_name_membership() {
+        let params = CircuitParams { san_max_len: Some(64), dns_name_max_len: Some(32), ..Default::default() };
+        for (dns_name, present) in [("www.example.com", true), ("example.com", true), ("mail.example.com", false), ("example.co", false)] {
+            let (n_big, sign_big, tbs) = load_fixture("./certs/san_leaf.pem", "./certs/test_ca.pem");
+            let circuit = CertificateVerificationCircuit::<Fr>::new(n_big, sign_big, tbs, params.clone())
+                .with_dns_name(dns_name);
+            circuit.params.set_current();
+            let public_inputs = circuit.public_inputs();
+            assert_eq!(public_inputs.len(), 3);
+            let prover = MockProver::run(circuit.params.k, &circuit, public_inputs).unwrap();
+            assert_eq!(prover.verify().is_ok(), present, "{dns_name}");
+        }
+    }
+
+    #[test]
+    fn test_dns_names_commitment() {
+        let (n_big, sign_big, tbs) = load_fixture("./certs/san_leaf.pem", "./certs/test_ca.pem");
+        // The SAN lists example.com, www.example.com, api.example.com in that order
+        let mut preimage = vec![];
+        for name in ["api.example.com", "example.com", "www.example.com", ""] {
+            preimage.push(name.len() as u8);
+            preimage.extend_from_slice(name.as_bytes());
+            preimage.resize(preimage.len() + 16 - name.len(), 0);
+        }
+        let expected: [u8; 32] = Sha256::digest(&preimage).into();
+        assert_eq!(dns_names_commitment(&tbs, 4, 16), Some(expected));
+        // Too few slots for all the names
+        assert_eq!(dns_names_commitment(&tbs, 2, 16), None);
+
+        let params = CircuitParams { k: 17, san_max_len: Some(64), dns_names_commitment: Some((4, 16)), ..Default::default() };
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big, sign_big, tbs, params);
+        circuit.params.set_current();
+        let public_inputs = circuit.public_inputs();
+        assert_eq!(public_inputs.len(), 3);
+        assert_eq!(public_inputs[2], expected.iter().map(|byte| Fr::from(*byte as u64)).collect::<Vec<_>>());
+        let prover = MockProver::run(circuit.params.k, &circuit, public_inputs.clone()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let mut wrong = public_inputs;
+        wrong[2][0] += Fr::from(1u64);
+        let prover = MockProver::run(circuit.params.k, &circuit, wrong).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    /// Two certificates verified in one context, finalized once. Instances are the concatenated
+    /// moduli and digests.
+    struct TwoCertificatesCircuit([CertificateVerificationCircuit<Fr>; 2]);
+
+    impl Circuit<Fr> for TwoCertificatesCircuit {
+        type Config = CertificateVerificationConfig<Fr>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!();
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            CertificateVerificationConfig::configure(meta, CircuitParams::current(), 2)
+        }
+
+        fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<Fr>) -> Result<(), Error> {
+            config.load_lookup_tables(&mut layouter)?;
+            let mut first_pass = SKIP_FIRST_PASS;
+            let (modulus_cells, digest_cells) = layouter.assign_region(
+                || "two certificates",
+                |region| {
+                    if first_pass {
+                        first_pass = false;
+                        return Ok((vec![], vec![]));
+                    }
+                    let mut aux = config.new_context(region);
+                    let ctx = &mut aux;
+                    let mut sha256_config = config.sha256_config();
+                    let (mut modulus_cells, mut digest_cells) = (vec![], vec![]);
+                    for circuit in &self.0 {
+                        let cert = circuit.verify_into_context(&config, ctx, &mut sha256_config)?;
+                        modulus_cells.extend(cert.modulus.iter().map(|v| v.cell()));
+                        digest_cells.extend(cert.digest.iter().map(|v| v.cell()));
+                    }
+                    config.range().finalize(ctx);
+                    Ok((modulus_cells, digest_cells))
+                },
+            )?;
+            config.constrain_instance(&mut layouter, InstanceColumn::Modulus, modulus_cells)?;
+            config.constrain_instance(&mut layouter, InstanceColumn::Digest, digest_cells)?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_verify_into_context_composes() {
+        let params = CircuitParams { k: 17, ..Default::default() };
+        let circuits = [("./certs/cert_3.pem", "./certs/cert_2.pem"), ("./certs/san_leaf.pem", "./certs/test_ca.pem")].map(|(cert, issuer)| {
+            let (n_big, sign_big, tbs) = load_fixture(cert, issuer);
+            CertificateVerificationCircuit::<Fr>::new(n_big, sign_big, tbs, params.clone())
+        });
+        let public_inputs = (0..2)
+            .map(|column| circuits.iter().flat_map(|circuit| circuit.public_inputs().swap_remove(column)).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let circuit = TwoCertificatesCircuit(circuits);
+        params.set_current();
+        let prover = MockProver::run(params.k, &circuit, public_inputs.clone()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // The second certificate's digest first
+        let mut swapped = public_inputs;
+        swapped[1].rotate_left(32);
+        let prover = MockProver::run(params.k, &circuit, swapped).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    /// Hashes a message with the gadgets of `configure_gadgets` alone.
+    struct Sha256Circuit(Vec<u8>);
+
+    impl Circuit<Fr> for Sha256Circuit {
+        type Config = (RSAConfig<Fr>, Sha256DynamicConfig<Fr>, Column<Instance>);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!();
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let (rsa_config, sha256_config) = configure_gadgets(meta, &CircuitParams::current(), 1);
+            let instance = meta.instance_column();
+            meta.enable_equality(instance);
+            (rsa_config, sha256_config, instance)
+        }
+
+        fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<Fr>) -> Result<(), Error> {
+            let (rsa_config, sha256_config, instance) = config;
+            let biguint_config = rsa_config.biguint_config();
+            sha256_config.load(&mut layouter)?;
+            biguint_config.range().load_lookup_table(&mut layouter)?;
+            let mut first_pass = SKIP_FIRST_PASS;
+            let digest_cells = layouter.assign_region(
+                || "sha256",
+                |region| {
+                    if first_pass {
+                        first_pass = false;
+                        return Ok(vec![]);
+                    }
+                    let mut aux = biguint_config.new_context(region);
+                    let ctx = &mut aux;
+                    let hash_result = sha256_config.clone().digest(ctx, &self.0, None)?;
+                    biguint_config.range().finalize(ctx);
+                    Ok(hash_result.output_bytes.iter().map(|v| v.cell()).collect::<Vec<_>>())
+                },
+            )?;
+            for (i, cell) in digest_cells.into_iter().enumerate() {
+                layouter.constrain_instance(cell, instance, i)?;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_build_sha256_config() {
+        let (_, _, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let params = CircuitParams::default();
+        params.set_current();
+        let digest = Sha256::digest(&tbs).iter().map(|byte| Fr::from(*byte as u64)).collect::<Vec<_>>();
+        let circuit = Sha256Circuit(tbs);
+        let prover = MockProver::run(params.k, &circuit, vec![digest.clone()]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let mut wrong = digest;
+        wrong[31] += Fr::from(1u64);
+        let prover = MockProver::run(params.k, &circuit, vec![wrong]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_large_public_exponent() {
+        use rsa::{Hash, PaddingScheme, PublicKeyParts, RsaPrivateKey};
+
+        let e = 2147483647u64; // 2^31 - 1
+        let key = RsaPrivateKey::new_with_exp(&mut rand::thread_rng(), 2048, &rsa::BigUint::from(e)).unwrap();
+        let msg = b"signed under a large exponent".to_vec();
+        let signature = key.sign(PaddingScheme::new_pkcs1v15_sign(Some(Hash::SHA2_256)), &Sha256::digest(&msg)).unwrap();
+        let n_big = BigUint::from_bytes_be(&key.n().to_bytes_be());
+        let sign_big = BigUint::from_bytes_be(&signature);
+
+        let params = CircuitParams { public_exponent: BigUint::from(e), ..Default::default() };
+        let fixture = CertFixture::new(n_big.clone(), sign_big.clone(), msg.clone()).with_params(params);
+        let circuit = fixture.circuit::<Fr>();
+        assert!(circuit.signature_is_valid());
+        circuit.params.set_current();
+        let prover = MockProver::run(circuit.params.k, &circuit, fixture.public_inputs()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // The same signature checked with e = 65537
+        assert!(mock_verify(&n_big, sign_big, msg).is_err());
+    }
+
+    #[test]
+    fn test_short_modulus() {
+        use rsa::{Hash, PaddingScheme, PublicKeyParts, RsaPrivateKey};
+
+        let msg = b"signed under a 2040-bit key".to_vec();
+        let sign = |bits: usize| {
+            let key = RsaPrivateKey::new(&mut rand::thread_rng(), bits).unwrap();
+            let signature = key.sign(PaddingScheme::new_pkcs1v15_sign(Some(Hash::SHA2_256)), &Sha256::digest(&msg)).unwrap();
+            (BigUint::from_bytes_be(&key.n().to_bytes_be()), BigUint::from_bytes_be(&signature))
+        };
+
+        // A byte short of bits_len: the EM is 255 bytes, one zero byte before its header
+        let (n_big, sign_big) = sign(2040);
+        assert_eq!(n_big.bits(), 2040);
+        let circuit = CertificateVerificationCircuit::<Fr>::try_new(n_big.clone(), sign_big.clone(), msg.clone(), CircuitParams::default()).unwrap();
+        assert!(circuit.signature_is_valid());
+        assert_eq!(mock_verify(&n_big, sign_big.clone(), msg.clone()), Ok(()));
+        assert!(mock_verify(&n_big, sign_big + 1u32, msg.clone()).is_err());
+
+        let (n_big, sign_big) = sign(2056);
+        assert_eq!(
+            CertificateVerificationCircuit::<Fr>::try_new(n_big, sign_big, msg, CircuitParams::default()).err(),
+            Some(CertError::ModulusTooLarge { bits: 2056, bits_len: 2048 })
+        );
+    }
+
+    #[test]
+    fn test_from_tbs_or_certificate() {
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let cert_der = crate::cert::test::der_from_pem("./certs/cert_3.pem");
+        for msg in [tbs.clone(), cert_der.clone()] {
+            let circuit = CertificateVerificationCircuit::<Fr>::from_tbs_or_certificate(n_big.clone(), sign_big.clone(), msg, CircuitParams::default()).unwrap();
+            assert_eq!(circuit.tbs(), tbs);
+            circuit.params.set_current();
+            let prover = MockProver::run(circuit.params.k, &circuit, circuit.public_inputs()).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+
+        // Neither the certificate nor its TBS verifies under another key
+        let (other_n, _, _) = load_fixture("./certs/cert_2.pem", "./certs/cert_1.pem");
+        let result = CertificateVerificationCircuit::<Fr>::from_tbs_or_certificate(other_n.clone(), sign_big.clone(), cert_der.clone(), CircuitParams::default());
+        assert_eq!(result.err(), Some(CertError::InvalidSignature));
+        let params = CircuitParams { validity: ValidityMode::ExposeResult, ..Default::default() };
+        let circuit = CertificateVerificationCircuit::<Fr>::from_tbs_or_certificate(other_n, sign_big, cert_der, params).unwrap();
+        assert!(!circuit.signature_is_valid());
+    }
+
+    #[test]
+    fn test_synthesis_writes_no_stdout() {
+        const INNER: &str = "ZKCERT_STDOUT_TEST_INNER";
+        if std::env::var_os(INNER).is_some() {
+            let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+            assert_eq!(mock_verify(&n_big, sign_big, tbs), Ok(()));
+            return;
+        }
+        // Rerun this test alone, uncaptured, and look for anything besides the harness's own lines
+        let output = std::process::Command::new(std::env::current_exe().unwrap())
+            .args(["--exact", "test::test_synthesis_writes_no_stdout", "--nocapture", "-q", "--test-threads=1"])
+            .env(INNER, "1")
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        let stray = stdout
+            .lines()
+            .filter(|line| !(line.is_empty() || *line == "." || line.starts_with("running ") || line.starts_with("test result: ")))
+            .collect::<Vec<_>>();
+        assert!(stray.is_empty(), "synthesis wrote to stdout: {stray:?}");
+    }
+
+    #[test]
+    fn test_reject_weak_keys() {
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big, sign_big.clone(), tbs.clone(), CircuitParams::default());
+        assert!(circuit.reject_weak_keys(2048).is_ok());
+
+        let weak_n = (BigUint::from(1u8) << 1023usize) | BigUint::from(1u8);
+        let circuit = CertificateVerificationCircuit::<Fr>::new(weak_n, sign_big, tbs, CircuitParams::default());
+        assert_eq!(
+            circuit.reject_weak_keys(2048).err(),
+            Some(CertError::WeakKey { bits: 1024, min_bits: 2048 })
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "bn256")]
+    fn test_bn256_field_selected() {
+        assert_eq!(std::any::TypeId::of::<Field>(), std::any::TypeId::of::<Fr>());
+    }
+
+    #[test]
+    fn test_from_rsa_public_key() {
+        let (parts, public_key) = test_utils::make_cert(test_utils::TestCertParams::default());
+        let circuit = CertificateVerificationCircuit::<Fr>::from_rsa_public_key(
+            &public_key,
+            &parts.signature.to_bytes_be(),
+            &parts.tbs,
+            CircuitParams::default(),
+        );
+        assert_eq!(circuit.params.public_exponent, BigUint::from(65537u32));
+        assert!(circuit.signature_is_valid());
+        circuit.params.set_current();
+        let prover = MockProver::run(circuit.params.k, &circuit, circuit.public_inputs()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_slot_padding_does_not_affect_digest() {
+        // The SHA256 chip pads the message out to `msg_len` itself and hashes up to the constrained
+        // input length, so the slot size must not leak into the digest
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let public_inputs = [1280, 1536].map(|msg_len| {
+            let params = CircuitParams { k: 17, msg_len, validity: ValidityMode::ExposeResult, ..Default::default() };
+            let circuit = CertificateVerificationCircuit::<Fr>::new(n_big.clone(), sign_big.clone(), tbs.clone(), params);
+            circuit.params.set_current();
+            let public_inputs = circuit.public_inputs();
+            let prover = MockProver::run(circuit.params.k, &circuit, public_inputs.clone()).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+            public_inputs
+        });
+        assert_eq!(public_inputs[0], public_inputs[1]);
+        assert_eq!(public_inputs[0][2], vec![Fr::from(1u64)]);
+
+        // Trailing zeros are message bytes, not padding: they change the digest and the result
+        let params = CircuitParams { k: 17, validity: ValidityMode::ExposeResult, ..Default::default() };
+        let zero_extended = [tbs.as_slice(), &[0, 0]].concat();
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big, sign_big, zero_extended, params);
+        circuit.params.set_current();
+        let extended_inputs = circuit.public_inputs();
+        assert_ne!(extended_inputs[1], public_inputs[0][1]);
+        assert_eq!(extended_inputs[2], vec![Fr::from(0u64)]);
+        let prover = MockProver::run(circuit.params.k, &circuit, public_inputs[0].clone()).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_pasta_mock_prover() {
+        // Vesta's scalar field is the Pallas base field
+        use halo2_base::halo2_proofs::halo2curves::pasta::Fp;
+
+        let fixture = CertFixture::load("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let circuit = fixture.circuit::<Fp>();
+        circuit.params.set_current();
+        let public_inputs = fixture.public_inputs::<Fp>();
+        assert_eq!(circuit.public_inputs(), public_inputs);
+        let prover = MockProver::run(circuit.params.k, &circuit, public_inputs.clone()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let mut wrong = public_inputs;
+        wrong[1][0] += Fp::from(1u64);
+        let prover = MockProver::run(circuit.params.k, &circuit, wrong).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    #[cfg(all(feature = "pasta", not(feature = "bn256")))]
+    fn test_pasta_field_selected() {
+        use halo2_base::halo2_proofs::halo2curves::pasta::Fp;
+        assert_eq!(std::any::TypeId::of::<Field>(), std::any::TypeId::of::<Fp>());
+        let pem = std::fs::read_to_string(fixture_path("./certs/cert_2_pubkey.pem")).unwrap();
+        assert_eq!(modulus_instance_from_pem::<Field>(&pem, &CircuitParams::default()).len(), 32);
+    }
+
+    #[test]
+    fn test_presets_prove_matching_fixtures() {
+        let presets = [
+            (CircuitParams::rsa_2048_sha256(), "./certs/cert_3.pem", "./certs/cert_2.pem"),
+            (CircuitParams::rsa_4096_sha256(), "./certs/leaf_4096.pem", "./certs/test_ca_4096.pem"),
+        ];
+        for (params, cert_path, issuer_path) in presets {
+            let (n_big, sign_big, tbs) = load_fixture(cert_path, issuer_path);
+            assert_eq!(n_big.bits() as usize, params.bits_len);
+            let circuit = CertificateVerificationCircuit::<Fr>::new(n_big, sign_big, tbs, params);
+            circuit.params.set_current();
+            let prover = MockProver::run(circuit.params.k, &circuit, circuit.public_inputs()).unwrap();
+            assert_eq!(prover.verify(), Ok(()), "{cert_path}");
+        }
+    }
+
+    #[test]
+    fn test_validate_digest_instance() {
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big.clone(), sign_big.clone(), tbs.clone(), CircuitParams::default());
+        let public_inputs = circuit.public_inputs();
+        assert_eq!(public_inputs[1].len(), 32);
+        assert!(circuit.validate_digest_instance(&public_inputs).is_ok());
+
+        let params = CircuitParams { digest: DigestAlgorithm::Sha384, ..Default::default() };
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big, sign_big, tbs, params);
+        assert_eq!(
+            circuit.validate_digest_instance(&public_inputs),
+            Err(CertError::DigestLengthMismatch { algorithm: DigestAlgorithm::Sha384, expected: 48, actual: 32 })
+        );
+        let sha384_inputs = vec![public_inputs[0].clone(), vec![Fr::from(0u64); 48]];
+        assert!(circuit.validate_digest_instance(&sha384_inputs).is_ok());
+    }
+
+    #[test]
+    fn test_fixed_modulus_mode() {
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big.clone(), sign_big.clone(), tbs.clone(), CircuitParams::default());
+        circuit.params.set_current();
+        let prover = MockProver::run(circuit.params.k, &circuit, circuit.public_inputs()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+        let standard = CircuitStats::last().unwrap();
+
+        let params = CircuitParams { fixed_modulus: Some(n_big.clone()), ..Default::default() };
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big, sign_big.clone(), tbs.clone(), params);
+        circuit.params.set_current();
+        let public_inputs = circuit.public_inputs();
+        assert_eq!(public_inputs.len(), 1);
+        let prover = MockProver::run(circuit.params.k, &circuit, public_inputs).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+        let fixed = CircuitStats::last().unwrap();
+        assert!(fixed.total_advice < standard.total_advice);
+
+        // A circuit fixed to another issuer rejects the signature
+        let (other_n, _, _) = load_fixture("./certs/cert_2.pem", "./certs/cert_1.pem");
+        let params = CircuitParams { fixed_modulus: Some(other_n.clone()), ..Default::default() };
+        let circuit = CertificateVerificationCircuit::<Fr>::new(other_n, sign_big, tbs, params);
+        circuit.params.set_current();
+        let prover = MockProver::run(circuit.params.k, &circuit, circuit.public_inputs()).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    /// Cell budget of the cert_3 fixture under the default params. Not measured yet: these are
+    /// just under the capacity of the default layout (40 advice, 1 fixed and 4 lookup columns of
+    /// 2^16 rows), too loose to catch an extra region. To set it, or to move it with a change that
+    /// grows or shrinks the circuit on purpose, set each count to `CircuitStats::last()` of this
+    /// test plus about 5%, in the same commit as the change.
+    const DEFAULT_BUDGET: CircuitStats = CircuitStats { total_advice: 2_500_000, total_fixed: 60_000, lookup_cells: 250_000 };
+
+    #[test]
+    fn test_cell_budget() {
+        let circuit = CertFixture::load("./certs/cert_3.pem", "./certs/cert_2.pem").circuit::<Fr>();
+        circuit.params.set_current();
+        let prover = MockProver::run(circuit.params.k, &circuit, circuit.public_inputs()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+        let stats = CircuitStats::last().unwrap();
+        assert_within_budget(&stats, &DEFAULT_BUDGET);
+
+        let over = CircuitStats { total_advice: stats.total_advice - 1, ..stats };
+        let result = std::panic::catch_unwind(|| assert_within_budget(&stats, &over));
+        assert!(result.is_err());
+    }
+
+    /// Cell counts of the cert_3 fixture under the default params. These were never measured on
+    /// this tree: run `cargo test test_constraint_snapshot -- --ignored`, copy the measured stats
+    /// from the `assert_near_baseline` failure, then drop the `#[ignore]`.
+    const DEFAULT_BASELINE: CircuitStats = CircuitStats { total_advice: 2_400_000, total_fixed: 50_000, lookup_cells: 230_000 };
+    /// How far the counts may drift from `DEFAULT_BASELINE` before the snapshot fails.
+    const BASELINE_TOLERANCE_PERCENT: usize = 2;
+
+    #[test]
+    #[ignore = "DEFAULT_BASELINE is a placeholder until recorded from a run"]
+    fn test_constraint_snapshot() {
+        let circuit = CertFixture::load("./certs/cert_3.pem", "./certs/cert_2.pem").circuit::<Fr>();
+        circuit.params.set_current();
+        let prover = MockProver::run(circuit.params.k, &circuit, circuit.public_inputs()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+        let stats = CircuitStats::last().unwrap();
+        assert_near_baseline(&stats, &DEFAULT_BASELINE, BASELINE_TOLERANCE_PERCENT);
+    }
+
+    #[test]
+    fn test_baseline_tolerance() {
+        let baseline = CircuitStats { total_advice: 1000, total_fixed: 100, lookup_cells: 500 };
+        assert_near_baseline(&CircuitStats { total_advice: 1020, total_fixed: 98, ..baseline }, &baseline, 2);
+        for moved in [CircuitStats { total_advice: 1021, ..baseline }, CircuitStats { lookup_cells: 489, ..baseline }] {
+            let result = std::panic::catch_unwind(|| assert_near_baseline(&moved, &baseline, 2));
+            assert!(result.is_err());
+        }
+    }
+
+    #[test]
+    fn test_validity_modes() {
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let mut tampered = tbs.clone();
+        tampered[100] ^= 0x01;
+        for (tbs, valid) in [(tbs, true), (tampered, false)] {
+            let circuit = CertificateVerificationCircuit::<Fr>::new(n_big.clone(), sign_big.clone(), tbs.clone(), CircuitParams::default());
+            assert_eq!(circuit.signature_is_valid(), valid);
+            circuit.params.set_current();
+            let prover = MockProver::run(circuit.params.k, &circuit, circuit.public_inputs()).unwrap();
+            assert_eq!(prover.verify().is_ok(), valid);
+
+            let params = CircuitParams { validity: ValidityMode::ExposeResult, ..Default::default() };
+            let circuit = CertificateVerificationCircuit::<Fr>::new(n_big.clone(), sign_big.clone(), tbs, params);
+            circuit.params.set_current();
+            let public_inputs = circuit.public_inputs();
+            assert_eq!(public_inputs[2], vec![Fr::from(valid as u64)]);
+            let prover = MockProver::run(circuit.params.k, &circuit, public_inputs.clone()).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+
+            // The exposed result can't be flipped
+            let mut flipped = public_inputs;
+            flipped[2][0] = Fr::from(!valid as u64);
+            let prover = MockProver::run(circuit.params.k, &circuit, flipped).unwrap();
+            assert!(prover.verify().is_err());
+        }
+    }
+
+    #[test]
+    fn test_digest_bound_to_validity() {
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let params = CircuitParams { validity: ValidityMode::ExposeResult, ..Default::default() };
+        let honest = CertificateVerificationCircuit::<Fr>::new(n_big.clone(), sign_big.clone(), tbs.clone(), params.clone());
+        honest.params.set_current();
+        let honest_inputs = honest.public_inputs();
+
+        let mut tampered = tbs;
+        tampered[100] ^= 0x01;
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big, sign_big, tampered, params);
+        let public_inputs = circuit.public_inputs();
+        assert_ne!(public_inputs[1], honest_inputs[1]);
+        assert_eq!(public_inputs[2], vec![Fr::from(0u64)]);
+        let prover = MockProver::run(circuit.params.k, &circuit, public_inputs.clone()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // Neither the honest digest nor the honest result can be claimed for the tampered message
+        for column in [1, 2] {
+            let mut claimed = public_inputs.clone();
+            claimed[column] = honest_inputs[column].clone();
+            let prover = MockProver::run(circuit.params.k, &circuit, claimed).unwrap();
+            assert!(prover.verify().is_err());
+        }
+    }
+
+    #[test]
+    fn test_min_k_for() {
+        let k = min_k_for(1280, 2048);
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let run = |k| {
+            let params = CircuitParams { k, ..Default::default() };
+            params.set_current();
+            let circuit = CertificateVerificationCircuit::<Fr>::new(n_big.clone(), sign_big.clone(), tbs.clone(), params);
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                MockProver::run(k, &circuit, circuit.public_inputs()).map_or(false, |prover| prover.verify().is_ok())
+            }))
+            .unwrap_or(false)
+        };
+        assert!(run(k));
+        assert!(!run(k - 1));
+    }
+
+    #[test]
+    fn test_signed_range() {
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let (_, pem) = parse_x509_pem(&std::fs::read(fixture_path("./certs/cert_3.pem")).unwrap()).unwrap();
+        let cert = pem.contents;
+        let params = CircuitParams { k: 17, msg_len: 1536, signed_range: true, ..Default::default() };
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big, sign_big, cert.clone(), params);
+        assert_eq!(circuit.signed_range(), Some(4..4 + tbs.len()));
+        assert!(circuit.signature_is_valid());
+
+        circuit.params.set_current();
+        let public_inputs = circuit.public_inputs();
+        let cert_digest = Sha256::digest(&cert).iter().map(|byte| Fr::from(*byte as u64)).collect::<Vec<_>>();
+        assert_eq!(public_inputs[1], cert_digest);
+        assert_eq!(public_inputs[2], vec![Fr::from(4u64), Fr::from(4 + tbs.len() as u64)]);
+        let prover = MockProver::run(circuit.params.k, &circuit, public_inputs.clone()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // The signed range can't be claimed to end earlier
+        let mut moved = public_inputs;
+        moved[2][1] = Fr::from(3 + tbs.len() as u64);
+        let prover = MockProver::run(circuit.params.k, &circuit, moved).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_tbs_digest_with_cert_digest() {
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let cert = crate::cert::test::der_from_pem("./certs/cert_3.pem");
+        let params = CircuitParams { k: 17, msg_len: 1536, signed_range: true, tbs_digest: true, ..Default::default() };
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big, sign_big, cert.clone(), params);
+        circuit.params.set_current();
+        let public_inputs = circuit.public_inputs();
+        let to_fes = |digest: &[u8]| digest.iter().map(|byte| Fr::from(*byte as u64)).collect::<Vec<_>>();
+        // The whole DER, signature included, next to what the signature covers
+        assert_eq!(public_inputs[1], to_fes(&Sha256::digest(&cert)));
+        assert_eq!(public_inputs[3], to_fes(&Sha256::digest(&tbs)));
+        let prover = MockProver::run(circuit.params.k, &circuit, public_inputs.clone()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let mut swapped = public_inputs.clone();
+        swapped.swap(1, 3);
+        let prover = MockProver::run(circuit.params.k, &circuit, swapped).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_cost_report() {
+        let report = CertFixture::load("./certs/cert_3.pem", "./certs/cert_2.pem").circuit::<Fr>().cost_report();
+        let gadgets = report.gadgets.iter().map(|cost| cost.gadget).collect::<Vec<_>>();
+        assert_eq!(gadgets, ["rsa inputs", "sha256", "rsa", "certificate fields"]);
+        assert!(report.gadgets.iter().map(|cost| cost.advice_cells).sum::<usize>() <= report.total.total_advice);
+        // 17 modular multiplications of 2048-bit integers dwarf everything else
+        assert!(report.advice_share("rsa") > 0.5);
+    }
+
+    #[test]
+    fn test_evm_packed_digest() {
+        use halo2_base::halo2_proofs::halo2curves::group::ff::PrimeField as _;
+
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let digest = Sha256::digest(&tbs);
+        let packed = evm_digest_instance::<Fr>(&digest);
+        // abi.encode(uint128(hi), uint128(lo)): each half right-aligned in a big-endian 32-byte word
+        let abi_encoded = digest.chunks(16).flat_map(|half| [[0u8; 16].as_slice(), half].concat()).collect::<Vec<_>>();
+        let words = packed
+            .iter()
+            .flat_map(|fe| {
+                let mut word = fe.to_repr();
+                word.reverse();
+                word
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(words, abi_encoded);
+
+        let params = CircuitParams { digest_encoding: DigestEncoding::EvmPacked, ..Default::default() };
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big, sign_big, tbs, params);
+        let public_inputs = circuit.public_inputs();
+        assert_eq!(public_inputs[1], packed);
+        assert!(circuit.validate_digest_instance(&public_inputs).is_ok());
+        circuit.params.set_current();
+        let prover = MockProver::run(circuit.params.k, &circuit, public_inputs.clone()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // The halves in the other order
+        let mut swapped = public_inputs;
+        swapped[1].reverse();
+        let prover = MockProver::run(circuit.params.k, &circuit, swapped).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_hide_digest() {
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        for (fixed_modulus, num_columns) in [(None, 1), (Some(n_big.clone()), 0)] {
+            let params = CircuitParams { hide_digest: true, fixed_modulus, ..Default::default() };
+            let circuit = CertificateVerificationCircuit::<Fr>::new(n_big.clone(), sign_big.clone(), tbs.clone(), params);
+            circuit.params.set_current();
+            let public_inputs = circuit.public_inputs();
+            assert_eq!(public_inputs.len(), num_columns);
+            assert_eq!(circuit.params.digest_index(), None);
+            assert!(circuit.validate_public_inputs(&public_inputs).is_ok());
+            let prover = MockProver::run(circuit.params.k, &circuit, public_inputs).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+
+        // The validity is still asserted
+        let mut tampered = tbs;
+        tampered[100] ^= 0x01;
+        let params = CircuitParams { hide_digest: true, ..Default::default() };
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big, sign_big, tampered, params);
+        circuit.params.set_current();
+        let prover = MockProver::run(circuit.params.k, &circuit, circuit.public_inputs()).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_sig_alg_instance() {
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let params = CircuitParams { sig_alg_max_len: Some(16), ..Default::default() };
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big, sign_big, tbs, params);
+        let public_inputs = circuit.public_inputs();
+        let sig_alg = &public_inputs[2];
+        let oid = sig_alg[1..=sig_alg[0].get_lower_128() as usize].iter().map(|fe| fe.get_lower_128() as u8).collect::<Vec<_>>();
+        assert_eq!(der::oid_to_string(&oid).as_deref(), Some("1.2.840.113549.1.1.11"));
+
+        circuit.params.set_current();
+        let prover = MockProver::run(circuit.params.k, &circuit, public_inputs.clone()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // Claiming the SPKI's rsaEncryption OID instead
+        let mut wrong = public_inputs;
+        wrong[2][9] = Fr::from(0x01);
+        let prover = MockProver::run(circuit.params.k, &circuit, wrong).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_issuer_key_hash() {
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let (other_n, _, _) = load_fixture("./certs/san_leaf.pem", "./certs/test_ca.pem");
+        let params = CircuitParams { k: 17, issuer_key_hash: true, ..Default::default() };
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big.clone(), sign_big, tbs, params);
+        let public_inputs = circuit.public_inputs();
+        assert_eq!(public_inputs[0], vec![issuer_key_hash::<Fr>(&n_big, &circuit.params)]);
+        assert_eq!(public_inputs[1].len(), 32);
+        circuit.params.set_current();
+        let prover = MockProver::run(circuit.params.k, &circuit, public_inputs.clone()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let mut wrong = public_inputs;
+        wrong[0] = vec![issuer_key_hash(&other_n, &circuit.params)];
+        let prover = MockProver::run(circuit.params.k, &circuit, wrong).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_variable_exponent() {
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let params = CircuitParams { k: 17, exp_limb_bits: 17, variable_exponent: true, ..Default::default() };
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big, sign_big, tbs, params);
+        let public_inputs = circuit.public_inputs();
+        assert_eq!(circuit.params.instance_columns().last(), Some(&(InstanceColumn::Exponent, 1)));
+        assert_eq!(public_inputs[2], vec![Fr::from(65537)]);
+        assert_eq!(exponent_instance::<Fr>(&BigUint::from(65537u32)), Fr::from(65537));
+        circuit.params.set_current();
+        let prover = MockProver::run(circuit.params.k, &circuit, public_inputs.clone()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let mut wrong = public_inputs;
+        wrong[2] = vec![Fr::from(3)];
+        let prover = MockProver::run(circuit.params.k, &circuit, wrong).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_exp_limb_bits_sizing() {
+        for (e, bits) in [(3u64, 2), (65537, 17), (2147483647, 31)] {
+            let params = CircuitParams { variable_exponent: true, ..Default::default() }.with_exponent(BigUint::from(e));
+            assert_eq!((params.required_exp_limb_bits(), params.exp_limb_bits), (bits, bits));
+            assert_eq!(params.check_exponent(), Ok(()));
+        }
+
+        // The default 5 bits only fit fixed exponents
+        let params = CircuitParams::default();
+        assert_eq!(params.check_exponent(), Ok(()));
+        let params = CircuitParams { variable_exponent: true, ..params };
+        assert_eq!(params.check_exponent(), Err(CertError::ExponentTooLarge { bits: 17, exp_limb_bits: 5 }));
+        let params = CircuitParams { exp_limb_bits: 17, public_exponent: BigUint::from(2147483647u64), ..params };
+        assert_eq!(params.check_exponent(), Err(CertError::ExponentTooLarge { bits: 31, exp_limb_bits: 17 }));
+
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let result = CertificateVerificationCircuit::<Fr>::try_new(n_big, sign_big, tbs, params);
+        assert_eq!(result.err(), Some(CertError::ExponentTooLarge { bits: 31, exp_limb_bits: 17 }));
+    }
+
+    #[test]
+    fn test_time_instance() {
+        assert_eq!(time_instance(0), 19700101000000);
+        assert_eq!(time_instance(951827696), 20000229123456);
+        assert_eq!(time_instance(1671580070), 20221220234750);
+    }
+
+    #[test]
+    fn test_not_expired() {
+        // cert_3's issuer Name has a `81 xx` header, san_leaf's a short-form one
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let not_after = not_after_time(&tbs).unwrap();
+        assert_eq!(not_after, time_instance(1671580070));
+        let params = CircuitParams { expiry_check: true, ..Default::default() };
+        for (now, not_expired) in [(1609459200, true), (1671580070, true), (1792108800, false)] {
+            let circuit = CertificateVerificationCircuit::<Fr>::new(n_big.clone(), sign_big.clone(), tbs.clone(), params.clone())
+                .with_current_time(now);
+            let public_inputs = circuit.public_inputs();
+            assert_eq!(public_inputs[2], vec![Fr::from(time_instance(now)), Fr::from(not_expired as u64)]);
+            // Only the digest could give notAfter away
+            assert!(!public_inputs.concat().contains(&Fr::from(not_after)));
+            circuit.params.set_current();
+            let prover = MockProver::run(circuit.params.k, &circuit, public_inputs.clone()).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+
+            let mut wrong = public_inputs;
+            wrong[2][1] = Fr::from(!not_expired as u64);
+            let prover = MockProver::run(circuit.params.k, &circuit, wrong).unwrap();
+            assert!(prover.verify().is_err());
+        }
+
+        let (n_big, sign_big, tbs) = load_fixture("./certs/san_leaf.pem", "./certs/test_ca.pem");
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big, sign_big, tbs, params).with_current_time(1792108800);
+        let public_inputs = circuit.public_inputs();
+        assert_eq!(public_inputs[2][1], Fr::from(1));
+        circuit.params.set_current();
+        let prover = MockProver::run(circuit.params.k, &circuit, public_inputs).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_validity_dates() {
+        assert_eq!(utc_unix_time(b"700101000000"), 0);
+        assert_eq!(utc_unix_time(b"000229123456"), 951827696);
+        assert_eq!(utc_unix_time(b"491231235959"), 2524607999);
+
+        let pem = std::fs::read(fixture_path("./certs/cert_3.pem")).unwrap();
+        let (_, pem) = parse_x509_pem(&pem).unwrap();
+        let cert = pem.parse_x509().unwrap();
+        let expected = [cert.validity().not_before.timestamp(), cert.validity().not_after.timestamp()]
+            .map(|time| Fr::from(time as u64))
+            .to_vec();
+        assert_eq!(expected[1], Fr::from(1671580070));
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        assert_eq!(validity_dates_instance::<Fr>(&tbs), Some(expected.clone()));
+
+        let params = CircuitParams { expiry_check: true, validity_dates: true, ..Default::default() };
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big, sign_big, tbs, params).with_current_time(1609459200);
+        let public_inputs = circuit.public_inputs();
+        assert_eq!(public_inputs[3], expected);
+        circuit.params.set_current();
+        let prover = MockProver::run(circuit.params.k, &circuit, public_inputs.clone()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let mut wrong = public_inputs;
+        wrong[3].reverse();
+        let prover = MockProver::run(circuit.params.k, &circuit, wrong).unwrap();
+        assert!(prover.verify().is_err());
+
+        // Leap days in the 2000s and the last second the gadget covers
+        use rsa::PublicKeyParts;
+        let (parts, public_key) = test_utils::make_cert(test_utils::TestCertParams {
+            not_before: "000229123456Z".to_string(),
+            not_after: "491231235959Z".to_string(),
+            ..Default::default()
+        });
+        let params = CircuitParams { validity_dates: true, ..Default::default() };
+        let circuit = CertFixture::new(BigUint::from_bytes_be(&public_key.n().to_bytes_be()), parts.signature, parts.tbs)
+            .with_params(params)
+            .circuit::<Fr>();
+        let public_inputs = circuit.public_inputs();
+        assert_eq!(public_inputs[2], vec![Fr::from(951827696), Fr::from(2524607999)]);
+        circuit.params.set_current();
+        let prover = MockProver::run(circuit.params.k, &circuit, public_inputs).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_k_mismatch() {
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big, sign_big, tbs, CircuitParams::default());
+        // Configured for another k than the one proven with, or for other instance columns
+        let stale = [
+            (CircuitParams { k: 17, ..Default::default() }, "(k: 16 vs 17)"),
+            (CircuitParams { hide_digest: true, ..Default::default() }, "(hide_digest: false vs true)"),
+        ];
+        for (params, expected) in stale {
+            params.set_current();
+            let run = std::panic::AssertUnwindSafe(|| MockProver::run(circuit.params.k, &circuit, circuit.public_inputs()).is_ok());
+            let panic = std::panic::catch_unwind(run).unwrap_err();
+            let message = panic.downcast_ref::<String>().unwrap();
+            assert!(message.contains(expected), "{message}");
+        }
+
+        // Configured right, but run at a `k` other than the one configured for
+        let run = std::panic::AssertUnwindSafe(|| mock_run(17, &circuit, circuit.public_inputs()).is_ok());
+        let panic = std::panic::catch_unwind(run).unwrap_err();
+        let message = panic.downcast_ref::<String>().unwrap();
+        assert!(message.contains("run at k = 17 (131072 rows), but the circuit is configured for k = 16"), "{message}");
+        assert_eq!(mock_run(16, &circuit, circuit.public_inputs()).unwrap().verify(), Ok(()));
+    }
+
+    /// Signs a raw EM `00 01 ff.. separator DigestInfo digest` with test_ca's key, to control every
+    /// byte of the padding. Returns `(modulus, signature)`.
+    fn sign_raw_em(digest: &[u8], separator: u8) -> (BigUint, BigUint) {
+        sign_raw_em_with_prefix(digest, separator, &digest_info_prefix(digest.len()))
+    }
+
+    /// `sign_raw_em` with the DigestInfo up to the digest given as `prefix`.
+    fn sign_raw_em_with_prefix(digest: &[u8], separator: u8, prefix: &[u8]) -> (BigUint, BigUint) {
+        let key_pem = std::fs::read(fixture_path("./certs/test_ca.key")).unwrap();
+        let key = openssl::rsa::Rsa::private_key_from_pem(&key_pem).unwrap();
+        let n_big = BigUint::from_bytes_be(&key.n().to_vec());
+        let d_big = BigUint::from_bytes_be(&key.d().to_vec());
+        let mut em = vec![0x00, 0x01];
+        em.resize(256 - prefix.len() - digest.len() - 1, 0xff);
+        em.push(separator);
+        em.extend_from_slice(prefix);
+        em.extend_from_slice(digest);
+        let sign_big = BigUint::from_bytes_be(&em).modpow(&d_big, &n_big);
+        (n_big, sign_big)
+    }
+
+    #[test]
+    fn test_em_separator() {
+        let msg = b"signed message".to_vec();
+        let (n_big, sign_big) = sign_raw_em(&Sha256::digest(&msg), 0x00);
+        assert_eq!(mock_verify(&n_big, sign_big, msg.clone()), Ok(()));
+
+        let (n_big, sign_big) = sign_raw_em(&Sha256::digest(&msg), 0x01);
+        assert!(mock_verify(&n_big, sign_big, msg).is_err());
+    }
+
+    #[test]
+    fn test_digest_algorithm_mismatch() {
+        let msg = b"signed message".to_vec();
+        let (n_big, sign_big) = sign_raw_em(&Sha256::digest(&msg), 0x00);
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big, sign_big, msg.clone(), CircuitParams::default());
+        assert_eq!(circuit.check_signature(), Ok(()));
+
+        // The SHA-256 digest of the message, under a DigestInfo that claims SHA-512
+        let mut sha512_prefix = SHA256_DIGEST_INFO_PREFIX;
+        sha512_prefix[14] = 0x03;
+        let (n_big, sign_big) = sign_raw_em_with_prefix(&Sha256::digest(&msg), 0x00, &sha512_prefix);
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big.clone(), sign_big.clone(), msg.clone(), CircuitParams::default());
+        assert_eq!(
+            circuit.check_signature(),
+            Err(CertError::DigestAlgorithmMismatch {
+                expected: DigestAlgorithm::Sha256,
+                oid: "2.16.840.1.101.3.4.2.3".to_string(),
+                name: "sha512",
+            })
+        );
+        assert!(mock_verify(&n_big, sign_big, msg.clone()).is_err());
+
+        // A signature that is merely wrong
+        let (n_big, sign_big) = sign_raw_em(&Sha256::digest(b"another message"), 0x00);
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big, sign_big, msg, CircuitParams::default());
+        assert_eq!(circuit.check_signature(), Err(CertError::InvalidSignature));
+    }
+
+    #[test]
+    fn test_digest_truncation() {
+        let msg = b"signed message".to_vec();
+        let digest = Sha256::digest(&msg);
+        let params = CircuitParams { digest_truncation: Some(28), ..Default::default() };
+        let verify = |(n_big, sign_big): (BigUint, BigUint), msg: &[u8]| {
+            let fixture = CertFixture::new(n_big, sign_big, msg.to_vec()).with_params(params.clone());
+            let circuit = fixture.circuit::<Fr>();
+            circuit.params.set_current();
+            // The digest instance stays the full 32 bytes
+            let result = MockProver::run(circuit.params.k, &circuit, fixture.public_inputs()).unwrap().verify();
+            assert_eq!(result.is_ok(), circuit.signature_is_valid());
+            result
+        };
+
+        assert_eq!(verify(sign_raw_em(&digest[..28], 0x00), &msg), Ok(()));
+        // Still bound to the message, and not to a signature over the whole digest
+        assert!(verify(sign_raw_em(&digest[..28], 0x00), b"other message").is_err());
+        assert!(verify(sign_raw_em(&digest, 0x00), &msg).is_err());
+    }
+
+    #[test]
+    fn test_from_issuer_certificate() {
+        let [cert_3, cert_2] = ["./certs/cert_3.pem", "./certs/cert_2.pem"].map(crate::cert::test::der_from_pem);
+        let circuit = CertificateVerificationCircuit::<Fr>::from_issuer_certificate(&cert_3, &cert_2, CircuitParams::default()).unwrap();
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        assert_eq!((&circuit.n_big, &circuit.sign_big, &circuit.msg), (&n_big, &sign_big, &tbs));
+        circuit.params.set_current();
+        let prover = MockProver::run(circuit.params.k, &circuit, circuit.public_inputs()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let truncated = CertificateVerificationCircuit::<Fr>::from_issuer_certificate(&cert_3, &cert_2[..100], CircuitParams::default());
+        assert!(matches!(truncated, Err(CertError::MalformedStructure(_))));
+    }
+
+    #[test]
+    fn test_montgomery_exp() {
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let mut tampered = tbs.clone();
+        tampered[100] ^= 0x01;
+        let params = CircuitParams { montgomery_exp: true, ..Default::default() };
+        for (msg, is_valid) in [(tbs, true), (tampered, false)] {
+            let circuit = CertificateVerificationCircuit::<Fr>::new(n_big.clone(), sign_big.clone(), msg, params.clone());
+            circuit.params.set_current();
+            let prover = MockProver::run(circuit.params.k, &circuit, circuit.public_inputs()).unwrap();
+            assert_eq!(prover.verify().is_ok(), is_valid);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "montgomery_exp needs a fixed public_exponent")]
+    fn test_montgomery_exp_variable_exponent() {
+        let mut meta = ConstraintSystem::<Fr>::default();
+        let params = CircuitParams { montgomery_exp: true, variable_exponent: true, ..Default::default() };
+        CertificateVerificationConfig::configure(&mut meta, params, 1);
+    }
+
+    #[test]
+    fn test_small_factor_check() {
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let params = CircuitParams { check_small_factors: true, ..Default::default() };
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big.clone(), sign_big.clone(), tbs.clone(), params);
+        circuit.params.set_current();
+        let prover = MockProver::run(circuit.params.k, &circuit, circuit.public_inputs()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // Exposing the result isolates the factor check: an odd multiple of 3 alone is a valid witness
+        let multiple_of_3 = &n_big + (9u32 - (&n_big % 6u32)) % 6u32;
+        assert_eq!(&multiple_of_3 % 6u32, BigUint::from(3u32));
+        for check_small_factors in [false, true] {
+            let params = CircuitParams { check_small_factors, validity: ValidityMode::ExposeResult, ..Default::default() };
+            let circuit = CertificateVerificationCircuit::<Fr>::new(multiple_of_3.clone(), sign_big.clone(), tbs.clone(), params);
+            circuit.params.set_current();
+            let prover = MockProver::run(circuit.params.k, &circuit, circuit.public_inputs()).unwrap();
+            assert_eq!(prover.verify().is_ok(), !check_small_factors);
+        }
+    }
+
+    #[test]
+    fn test_not_self_signed() {
+        // test_ca signed itself: a valid signature, but under the certificate's own key
+        let fixtures = [("./certs/cert_3.pem", "./certs/cert_2.pem", true), ("./certs/test_ca.pem", "./certs/test_ca.pem", false)];
+        for (cert, issuer, is_leaf) in fixtures {
+            let (n_big, sign_big, tbs) = load_fixture(cert, issuer);
+            for not_self_signed in [false, true] {
+                let params = CircuitParams { not_self_signed, ..Default::default() };
+                let circuit = CertificateVerificationCircuit::<Fr>::new(n_big.clone(), sign_big.clone(), tbs.clone(), params);
+                circuit.params.set_current();
+                let prover = MockProver::run(circuit.params.k, &circuit, circuit.public_inputs()).unwrap();
+                assert_eq!(prover.verify().is_ok(), is_leaf || !not_self_signed);
+            }
+        }
+    }
+
+    #[test]
+    fn test_even_modulus_rejected() {
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        // Even under `ExposeResult`, which accepts any signature, and without `check_small_factors`
+        let params = CircuitParams { validity: ValidityMode::ExposeResult, ..Default::default() };
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big + 1u32, sign_big, tbs, params);
+        circuit.params.set_current();
+        let prover = MockProver::run(circuit.params.k, &circuit, circuit.public_inputs()).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_flatten_public_inputs_round_trip() {
+        let (n_big, sign_big, tbs) = load_fixture("./certs/san_leaf.pem", "./certs/test_ca.pem");
+        let layouts = [
+            CircuitParams::default(),
+            CircuitParams { instance_layout: InstanceLayout::DigestThenModulus, ..Default::default() },
+            CircuitParams { san_max_len: Some(64), validity: ValidityMode::ExposeResult, ..Default::default() },
+        ];
+        for params in layouts {
+            let circuit = CertificateVerificationCircuit::<Fr>::new(n_big.clone(), sign_big.clone(), tbs.clone(), params);
+            let flat = circuit.flatten_public_inputs();
+            assert_eq!(flat.len(), circuit.params.instance_columns().iter().map(|(_, len)| len).sum::<usize>());
+            assert_eq!(circuit.params.reshape_public_inputs(&flat).unwrap(), circuit.public_inputs());
+            assert!(circuit.params.reshape_public_inputs(&flat[1..]).is_err());
+        }
+    }
+
+    #[test]
+    fn test_params_diff() {
+        let params = CircuitParams::default();
+        assert!(params.diff(&params.clone()).is_empty());
+        let other = CircuitParams { lookup_bits: 13, hide_digest: true, ..Default::default() };
+        assert_eq!(params.diff(&other), vec!["lookup_bits: 12 vs 13", "hide_digest: false vs true"]);
+    }
+
+    #[test]
+    fn test_public_input_columns() {
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big.clone(), sign_big.clone(), tbs.clone(), CircuitParams::default());
+        // The n_instance and hash_instance columns
+        assert_eq!(
+            circuit.public_input_columns(),
+            vec![
+                InstanceColumnSpec { column: InstanceColumn::Modulus, name: "modulus", len: 32 },
+                InstanceColumnSpec { column: InstanceColumn::Digest, name: "digest", len: 32 },
+            ]
+        );
+        let lens = circuit.public_inputs().iter().map(Vec::len).collect::<Vec<_>>();
+        assert_eq!(lens, circuit.public_input_columns().iter().map(|spec| spec.len).collect::<Vec<_>>());
+
+        let params = CircuitParams { validity: ValidityMode::ExposeResult, ..Default::default() };
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big, sign_big, tbs, params);
+        let names = circuit.public_input_columns().iter().map(|spec| spec.name).collect::<Vec<_>>();
+        assert_eq!(names, ["modulus", "digest", "valid"]);
+    }
+
+    #[test]
+    fn test_solidity_public_inputs() {
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let params = CircuitParams { digest_encoding: DigestEncoding::EvmPacked, ..Default::default() };
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big.clone(), sign_big, tbs.clone(), params);
+        let words = circuit.solidity_public_inputs();
+        assert_eq!(words.len(), circuit.params.num_limbs() + 2);
+
+        // The least significant 64 bits of the modulus, right-aligned
+        let n_bytes = n_big.to_bytes_be();
+        assert_eq!(words[0][..24], [0; 24]);
+        assert_eq!(words[0][24..], n_bytes[n_bytes.len() - 8..]);
+
+        let digest = Sha256::digest(&tbs);
+        let num_limbs = circuit.params.num_limbs();
+        assert_eq!(words[num_limbs][16..], digest[..16]);
+        assert_eq!(words[num_limbs + 1][16..], digest[16..]);
+    }
+
+    #[test]
+    fn test_two_segment_byte_range() {
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        // A signature placeholder splits the signed bytes, as in a PDF
+        let mut document = tbs[..500].to_vec();
+        document.extend([b'0'; 64]);
+        document.extend(&tbs[500..]);
+        let ranges = [ByteRange::new(0, 500), ByteRange::new(564, tbs.len() - 500)];
+
+        let params = CircuitParams { byte_range_segments: 2, ..Default::default() };
+        let circuit = CertificateVerificationCircuit::<Fr>::from_byte_ranges(n_big, sign_big, &document, &ranges, params).unwrap();
+        circuit.params.set_current();
+        let public_inputs = circuit.public_inputs();
+        let digest = Sha256::digest([&document[..500], &document[564..]].concat());
+        assert_eq!(public_inputs[1], digest.iter().map(|byte| Fr::from(*byte as u64)).collect::<Vec<_>>());
+        assert_eq!(public_inputs[2], vec![Fr::from(500u64), Fr::from(tbs.len() as u64 - 500)]);
+        let prover = MockProver::run(circuit.params.k, &circuit, public_inputs.clone()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // Segment lengths must add up to the hashed length
+        let mut wrong_lens = public_inputs;
+        wrong_lens[2][1] -= Fr::from(1u64);
+        let prover = MockProver::run(circuit.params.k, &circuit, wrong_lens).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_config_describe() {
+        let params = CircuitParams { san_max_len: Some(64), ..CircuitParams::rsa_4096_sha256() };
+        params.set_current();
+        let mut meta = ConstraintSystem::<Fr>::default();
+        let config = CertificateVerificationCircuit::<Fr>::configure(&mut meta);
+        let description = config.describe();
+        for expected in ["k=17", "bits_len=4096", "limbs=64x64-bit", "msg_len=1920", "up to 1911 bytes", "digest=Sha256", "advice=40", "instance_columns=3"] {
+            assert!(description.contains(expected), "{expected} missing from {description}");
+        }
+    }
+
+    #[test]
+    fn test_msg_len_slot_selection() {
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let options = [1280, 1920];
+        let small = CircuitParams::default().with_msg_len_for(&options, tbs.len()).unwrap();
+        assert_eq!(small.msg_len, 1280);
+        let large = CircuitParams { k: 17, ..Default::default() }.with_msg_len_for(&options[1..], tbs.len()).unwrap();
+        assert_eq!(large.msg_len, 1920);
+        assert_eq!(CircuitParams::default().with_msg_len_for(&options, 1300).unwrap().msg_len, 1920);
+        assert_eq!(
+            CircuitParams::default().with_msg_len_for(&options, 1912),
+            Err(CertError::MessageTooLong { len: 1912, max: 1911 })
+        );
+
+        let mut outputs = vec![];
+        for params in [small, large] {
+            let circuit = CertificateVerificationCircuit::<Fr>::new(n_big.clone(), sign_big.clone(), tbs.clone(), params);
+            circuit.params.set_current();
+            let public_inputs = circuit.public_inputs();
+            let prover = MockProver::run(circuit.params.k, &circuit, public_inputs.clone()).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+            outputs.push(public_inputs);
+        }
+        assert_eq!(outputs[0], outputs[1]);
+    }
+
+    #[test]
+    fn test_single_lookup_column() {
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        assert_eq!(CircuitParams::default().num_lookup_arguments(), 12);
+        assert_eq!(CircuitParams::default().check_lookup_limit(4), Err(CertError::TooManyLookups { count: 12, max: 4 }));
+
+        // The lookup cells of the default circuit take 8 times the rows in a single SHA256 column
+        let params = CircuitParams { k: 19, num_lookup_advice: 1, sha256_lookup_advice: 1, ..Default::default() };
+        assert_eq!(params.check_lookup_limit(2), Ok(()));
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big, sign_big, tbs, params);
+        circuit.params.set_current();
+        let prover = MockProver::run(circuit.params.k, &circuit, circuit.public_inputs()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_max_supported_msg_len() {
+        assert_eq!(CircuitParams::default().max_supported_msg_len(), 1271);
+        assert_eq!(CircuitParams::rsa_4096_sha256().max_supported_msg_len(), 1911);
+
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        assert!(CertificateVerificationCircuit::<Fr>::try_new(n_big.clone(), sign_big.clone(), tbs, CircuitParams::default()).is_ok());
+        let oversized = CertificateVerificationCircuit::<Fr>::try_new(n_big, sign_big, vec![0x30; 1500], CircuitParams::default());
+        let err = oversized.err().unwrap();
+        assert_eq!(err, CertError::MessageTooLong { len: 1500, max: 1271 });
+        assert_eq!(err.to_string(), "message is 1500 bytes, longer than the 1271-byte maximum");
+    }
+
+    #[test]
+    fn test_validate_public_inputs() {
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big, sign_big, tbs, CircuitParams::default());
+        let public_inputs = circuit.public_inputs();
+        assert!(circuit.validate_public_inputs(&public_inputs).is_ok());
+
+        let too_few = vec![public_inputs[0].clone()];
+        assert_eq!(circuit.validate_public_inputs(&too_few), Err(CertError::InstanceCount { expected: 2, actual: 1 }));
+
+        let mut too_many = public_inputs.clone();
+        too_many.push(vec![]);
+        assert_eq!(circuit.validate_public_inputs(&too_many), Err(CertError::InstanceCount { expected: 2, actual: 3 }));
+
+        let mut wrong_length = public_inputs;
+        wrong_length[0].pop();
+        assert_eq!(
+            circuit.validate_public_inputs(&wrong_length),
+            Err(CertError::InstanceLength { column: "modulus", expected: 32, actual: 31 })
+        );
+    }
+}
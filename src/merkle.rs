@@ -0,0 +1,273 @@
+//! Verification under an issuer key from a registry of trusted keys, published as a Merkle root.
+//!
+//! Leaves are the commitment to each modulus's `bits_len / 8` big-endian bytes, the
+//! `issuer_key_hash` preimage, and a node is the commitment to its two children. Both sides hash
+//! only through the `commitment` module, which is SHA-256 until halo2-base has a Poseidon chip at
+//! the pinned revision.
+use halo2_base::{
+    gates::{GateInstructions, RangeInstructions},
+    utils::PrimeField,
+    AssignedValue,
+    Context,
+    QuantumCell::Existing,
+    halo2_proofs::{
+        plonk::{Circuit, ConstraintSystem, Error, Column, Instance},
+        circuit::{SimpleFloorPlanner, Layouter, Value, Cell},
+    },
+    SKIP_FIRST_PASS
+};
+use halo2_dynamic_sha256::Sha256DynamicConfig;
+use halo2_rsa::{RSAConfig, RSAInstructions, RSAPubE, RSAPublicKey, RSASignature};
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
+
+use crate::commitment::{assign_commitment_digest, commitment_digest};
+use crate::gadgets::unpack_le_bytes;
+use crate::{configure_gadgets, modulus_bytes, verify_pkcs1v15_signature, CertificateVerificationCircuit, CircuitParams, CircuitStats};
+
+/// Leaf of the modulus `n_big` in a trusted key tree.
+pub fn trusted_key_leaf(n_big: &BigUint, params: &CircuitParams) -> [u8; 32] {
+    commitment_digest(&modulus_bytes(n_big, params.bits_len))
+}
+
+/// Parent of the nodes `left` and `right`.
+pub fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    commitment_digest(&[left.as_slice(), right.as_slice()].concat())
+}
+
+/// Root of the tree over `leaves`, whose number must be a power of two.
+pub fn merkle_root(leaves: &[[u8; 32]]) -> [u8; 32] {
+    assert!(leaves.len().is_power_of_two(), "number of leaves must be a power of two");
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = level.chunks(2).map(|pair| hash_pair(&pair[0], &pair[1])).collect();
+    }
+    level[0]
+}
+
+/// Siblings of `leaves[index]` from the leaf up, the path `MerkleTrustedKeyCircuit` takes.
+pub fn merkle_path(leaves: &[[u8; 32]], index: usize) -> Vec<[u8; 32]> {
+    assert!(leaves.len().is_power_of_two(), "number of leaves must be a power of two");
+    let mut path = vec![];
+    let (mut level, mut index) = (leaves.to_vec(), index);
+    while level.len() > 1 {
+        path.push(level[index ^ 1]);
+        level = level.chunks(2).map(|pair| hash_pair(&pair[0], &pair[1])).collect();
+        index /= 2;
+    }
+    path
+}
+
+/// Verifies a signature under a `params.bits_len`-bit key with e = 65537 that is leaf `index` of
+/// a `2^DEPTH`-leaf trusted key tree. Public inputs are `[root bytes, message digest bytes]`;
+/// the key and its position stay private.
+///
+/// Every node takes a SHA256 slot of `msg_len` bytes like the message, so deep trees need a
+/// larger `k`.
+pub struct MerkleTrustedKeyCircuit<F: PrimeField, const DEPTH: usize> {
+    n_big: BigUint,
+    sign_big: BigUint,
+    msg: Vec<u8>,
+    index: usize,
+    path: Vec<[u8; 32]>,
+    params: CircuitParams,
+    _f: std::marker::PhantomData<F>,
+}
+
+impl<F: PrimeField, const DEPTH: usize> MerkleTrustedKeyCircuit<F, DEPTH> {
+    /// `path` is `merkle_path` of the key's leaf at `index`.
+    pub fn new(n_big: BigUint, sign_big: BigUint, msg: Vec<u8>, index: usize, path: Vec<[u8; 32]>, params: CircuitParams) -> Self {
+        assert_eq!(path.len(), DEPTH, "expected a path of {DEPTH} siblings");
+        assert!(index < 1 << DEPTH, "index past the {} leaves", 1 << DEPTH);
+        Self { n_big, sign_big, msg, index, path, params, _f: std::marker::PhantomData }
+    }
+
+    /// The root the key's path leads to.
+    pub fn root(&self) -> [u8; 32] {
+        let leaf = trusted_key_leaf(&self.n_big, &self.params);
+        self.path.iter().enumerate().fold(leaf, |node, (level, sibling)| match self.index >> level & 1 {
+            0 => hash_pair(&node, sibling),
+            _ => hash_pair(sibling, &node),
+        })
+    }
+
+    pub fn public_inputs(&self) -> Vec<Vec<F>> {
+        let to_fes = |bytes: &[u8]| bytes.iter().map(|byte| F::from(*byte as u64)).collect::<Vec<F>>();
+        vec![to_fes(&self.root()), to_fes(&Sha256::digest(&self.msg))]
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MerkleTrustedKeyConfig<F: PrimeField> {
+    rsa_config: RSAConfig<F>,
+    sha256_config: Sha256DynamicConfig<F>,
+    root_instance: Column<Instance>,
+    hash_instance: Column<Instance>,
+}
+
+impl<F: PrimeField> MerkleTrustedKeyConfig<F> {
+    /// Hashes `node` with its sibling, on the side the witnessed `is_right` bit puts it, and
+    /// returns the parent. The sibling is free: only `node`'s half of the preimage is constrained.
+    fn assign_parent<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        sha256_config: &mut Sha256DynamicConfig<F>,
+        node: &[AssignedValue<'v, F>],
+        node_value: &[u8; 32],
+        sibling: &[u8; 32],
+        is_right: bool
+    ) -> Result<Vec<AssignedValue<'v, F>>, Error> {
+        let range = self.rsa_config.biguint_config().range();
+        let gate = range.gate();
+        let preimage = match is_right {
+            false => [node_value.as_slice(), sibling.as_slice()].concat(),
+            true => [sibling.as_slice(), node_value.as_slice()].concat(),
+        };
+        let (preimage_cells, parent) = assign_commitment_digest(gate, ctx, sha256_config, &preimage)?;
+        let is_right = gate.load_witness(ctx, Value::known(F::from(is_right as u64)));
+        range.range_check(ctx, &is_right, 1);
+        let (left, right) = preimage_cells.split_at(32);
+        for ((left, right), byte) in left.iter().zip(right).zip(node) {
+            let node_half = gate.select(ctx, Existing(right), Existing(left), Existing(&is_right));
+            gate.assert_equal(ctx, Existing(&node_half), Existing(byte));
+        }
+        Ok(parent)
+    }
+}
+
+impl<F: PrimeField, const DEPTH: usize> Circuit<F> for MerkleTrustedKeyCircuit<F, DEPTH> {
+    type Config = MerkleTrustedKeyConfig<F>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        unimplemented!();
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let params = CircuitParams::current();
+        // The message, the leaf and one node per level
+        let (rsa_config, sha256_config) = configure_gadgets(meta, &params, 2 + DEPTH);
+        let root_instance = meta.instance_column();
+        meta.enable_equality(root_instance);
+        let hash_instance = meta.instance_column();
+        meta.enable_equality(hash_instance);
+
+        Self::Config {
+            rsa_config,
+            sha256_config,
+            root_instance,
+            hash_instance
+        }
+    }
+
+    fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+        let biguint_config = config.rsa_config.biguint_config();
+        config.sha256_config.load(&mut layouter)?;
+        biguint_config.range().load_lookup_table(&mut layouter)?;
+        let mut first_pass = SKIP_FIRST_PASS;
+        let (root_cells, hash_cells) = layouter.assign_region(
+            || "trusted key verifier",
+            |region| {
+                if first_pass {
+                    first_pass = false;
+                    return Ok((vec![], vec![]));
+                }
+
+                let mut aux = biguint_config.new_context(region);
+                let ctx = &mut aux;
+                let range = biguint_config.range();
+                let gate = range.gate();
+                let mut sha256_config = config.sha256_config.clone();
+
+                let e_fix = RSAPubE::Fix(BigUint::from(CertificateVerificationCircuit::<F>::DEFAULT_E));
+                let public_key = RSAPublicKey::new(Value::known(self.n_big.clone()), e_fix);
+                let public_key = config.rsa_config.assign_public_key(ctx, public_key)?;
+                let signature = RSASignature::new(Value::known(self.sign_big.clone()));
+                let signature = config.rsa_config.assign_signature(ctx, signature)?;
+                let hash_result = sha256_config.digest(ctx, &self.msg, None)?;
+                let is_valid = verify_pkcs1v15_signature(&config.rsa_config, ctx, &public_key, &hash_result.output_bytes, &signature, self.params.montgomery_exp)?;
+                gate.assert_is_const(ctx, &is_valid, F::one());
+
+                // The leaf is the commitment to the key's bytes
+                let mut n_bytes = unpack_le_bytes(range, ctx, public_key.n.limbs(), self.params.limb_bits);
+                n_bytes.reverse();
+                let preimage = modulus_bytes(&self.n_big, self.params.bits_len);
+                let (preimage_cells, leaf) = assign_commitment_digest(gate, ctx, &mut sha256_config, &preimage)?;
+                for (byte, cell) in preimage_cells.iter().zip(&n_bytes) {
+                    gate.assert_equal(ctx, Existing(byte), Existing(cell));
+                }
+
+                let mut node = leaf;
+                let mut node_value = trusted_key_leaf(&self.n_big, &self.params);
+                for (level, sibling) in self.path.iter().enumerate() {
+                    let is_right = self.index >> level & 1 == 1;
+                    node = config.assign_parent(ctx, &mut sha256_config, &node, &node_value, sibling, is_right)?;
+                    node_value = match is_right {
+                        false => hash_pair(&node_value, sibling),
+                        true => hash_pair(sibling, &node_value),
+                    };
+                }
+
+                range.finalize(ctx);
+                CircuitStats {
+                    total_advice: ctx.total_advice,
+                    total_fixed: ctx.total_fixed,
+                    lookup_cells: ctx.cells_to_lookup.len(),
+                }.record();
+                let root_cells = node.iter().map(|v| v.cell()).collect::<Vec<Cell>>();
+                let hash_cells = hash_result.output_bytes.iter().map(|v| v.cell()).collect::<Vec<Cell>>();
+                Ok((root_cells, hash_cells))
+            },
+        )?;
+        for (i, cell) in root_cells.into_iter().enumerate() {
+            layouter.constrain_instance(cell, config.root_instance, i)?;
+        }
+        for (i, cell) in hash_cells.into_iter().enumerate() {
+            layouter.constrain_instance(cell, config.hash_instance, i)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::load_fixture;
+    use halo2_base::halo2_proofs::{dev::MockProver, halo2curves::bn256::Fr};
+
+    fn merkle_params() -> CircuitParams {
+        CircuitParams { k: 18, ..Default::default() }
+    }
+
+    #[test]
+    fn test_trusted_key_tree() {
+        let params = merkle_params();
+        // cert_2's key signs cert_3; the other leaves are keys of other fixtures
+        let (issuer_n, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let (cert_1_n, _, _) = load_fixture("./certs/cert_2.pem", "./certs/cert_1.pem");
+        let (test_ca_n, _, _) = load_fixture("./certs/san_leaf.pem", "./certs/test_ca.pem");
+        let (san_leaf_n, _, _) = load_fixture("./certs/san_leaf.pem", "./certs/san_leaf.pem");
+        let keys = [cert_1_n, test_ca_n, issuer_n.clone(), san_leaf_n];
+        let leaves = keys.iter().map(|n| trusted_key_leaf(n, &params)).collect::<Vec<_>>();
+        let root = merkle_root(&leaves);
+        assert_eq!(merkle_path(&leaves, 2), vec![leaves[3], hash_pair(&leaves[0], &leaves[1])]);
+
+        let circuit = MerkleTrustedKeyCircuit::<Fr, 2>::new(issuer_n.clone(), sign_big.clone(), tbs.clone(), 2, merkle_path(&leaves, 2), params.clone());
+        assert_eq!(circuit.root(), root);
+        circuit.params.set_current();
+        let public_inputs = circuit.public_inputs();
+        let prover = MockProver::run(params.k, &circuit, public_inputs.clone()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // Another root
+        let mut other_root = public_inputs.clone();
+        other_root[0][0] += Fr::one();
+        let prover = MockProver::run(params.k, &circuit, other_root).unwrap();
+        assert!(prover.verify().is_err());
+
+        // The key in place of leaf 1 leads elsewhere
+        let circuit = MerkleTrustedKeyCircuit::<Fr, 2>::new(issuer_n, sign_big, tbs, 1, merkle_path(&leaves, 1), params.clone());
+        let prover = MockProver::run(params.k, &circuit, public_inputs).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
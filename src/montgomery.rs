@@ -0,0 +1,218 @@
+//! Modular exponentiation in Montgomery form, the `CircuitParams::montgomery_exp` alternative to
+//! halo2-rsa's exponentiation by `mul_mod`.
+//!
+//! With `R = 2^(limb_bits * n.len())`, each multiplication witnesses `u = a * b * R^-1 mod n` and
+//! checks `a * b + R * n == u * R + m * n` for a witnessed `m`, limb by limb with signed carries.
+//! The base goes in and the result comes out of Montgomery form through the same check against
+//! a product of one factor. See the `montgomery_exp` bench for the cells either path takes.
+use halo2_base::{
+    gates::{range::RangeConfig, GateInstructions, RangeInstructions},
+    utils::{fe_to_biguint, PrimeField},
+    AssignedValue,
+    Context,
+    QuantumCell::{Constant, Existing},
+};
+use halo2_base::halo2_proofs::circuit::Value;
+use halo2_rsa::big_uint::decompose_biguint;
+use num_bigint::BigUint;
+
+use crate::gadgets::is_lex_less_bits;
+
+/// `base^e mod n` for little-endian `limb_bits`-bit limbs of `base` and the odd modulus `n`, the
+/// result in as many limbs as `n`. `base` must be less than `n`: a larger one has no witness, so
+/// such a signature can't be proven valid or invalid on this path.
+pub fn modpow_fixed_exp<'v, F: PrimeField>(
+    range: &RangeConfig<F>,
+    ctx: &mut Context<'v, F>,
+    base: &[AssignedValue<'v, F>],
+    e: &BigUint,
+    n: &[AssignedValue<'v, F>],
+    limb_bits: usize,
+) -> Vec<AssignedValue<'v, F>> {
+    let r_bits = limb_bits * n.len();
+    let n_value = limbs_value(n, limb_bits);
+
+    // Into Montgomery form: `base_mont = base * R mod n`, so `redc(base_mont) == base`
+    let base_mont_value = limbs_value(base, limb_bits).zip(n_value.clone()).map(|(base, n)| (base << r_bits) % n);
+    let base_mont = load_reduced(range, ctx, base_mont_value, n, limb_bits);
+    assert_redc(range, ctx, &base_mont, n, base, limb_bits);
+
+    let mut acc = base_mont.clone();
+    for i in (0..e.bits() - 1).rev() {
+        acc = mont_mul(range, ctx, &acc, &acc, n, limb_bits);
+        if e.bit(i) {
+            acc = mont_mul(range, ctx, &acc, &base_mont, n, limb_bits);
+        }
+    }
+
+    let result_value = limbs_value(&acc, limb_bits).zip(n_value).map(|(acc, n)| redc(&acc, &n, r_bits));
+    let result = load_reduced(range, ctx, result_value, n, limb_bits);
+    assert_redc(range, ctx, &acc, n, &result, limb_bits);
+    result
+}
+
+/// `a * b * R^-1 mod n` for `a` and `b` less than `n`.
+fn mont_mul<'v, F: PrimeField>(
+    range: &RangeConfig<F>,
+    ctx: &mut Context<'v, F>,
+    a: &[AssignedValue<'v, F>],
+    b: &[AssignedValue<'v, F>],
+    n: &[AssignedValue<'v, F>],
+    limb_bits: usize,
+) -> Vec<AssignedValue<'v, F>> {
+    let gate = range.gate();
+    let num_limbs = n.len();
+    let product = (0..2 * num_limbs - 1)
+        .map(|k| {
+            let pairs = k.saturating_sub(num_limbs - 1)..=k.min(num_limbs - 1);
+            gate.inner_product(ctx, pairs.clone().map(|i| Existing(&a[i])), pairs.map(|i| Existing(&b[k - i])))
+        })
+        .collect::<Vec<_>>();
+    let u_value = limbs_value(&product, limb_bits)
+        .zip(limbs_value(n, limb_bits))
+        .map(|(product, n)| redc(&product, &n, limb_bits * num_limbs));
+    let u = load_reduced(range, ctx, u_value, n, limb_bits);
+    assert_redc(range, ctx, &product, n, &u, limb_bits);
+    u
+}
+
+/// Constrains `product + R * n == u * R + m * n` over the integers for a witnessed `m` of one
+/// limb more than `n`, so `u == product * R^-1 mod n`. Limb `k` of `product` is any sum of
+/// products of range-checked limbs whose indices add up to `k`, such as a single limb.
+fn assert_redc<'v, F: PrimeField>(
+    range: &RangeConfig<F>,
+    ctx: &mut Context<'v, F>,
+    product: &[AssignedValue<'v, F>],
+    n: &[AssignedValue<'v, F>],
+    u: &[AssignedValue<'v, F>],
+    limb_bits: usize,
+) {
+    let gate = range.gate();
+    let num_limbs = n.len();
+    let r_bits = limb_bits * num_limbs;
+    // `m` is below `2R` for `product` less than `n^2` and `u` less than `n`; outside those bounds
+    // there is no witness and the proof fails
+    let m_value = limbs_value(product, limb_bits)
+        .zip(limbs_value(n, limb_bits))
+        .zip(limbs_value(u, limb_bits))
+        .map(|((product, n), u)| {
+            let (sum, u_shifted) = (product + (&n << r_bits), u << r_bits);
+            match sum >= u_shifted {
+                true => (sum - u_shifted) / n,
+                false => BigUint::from(0u8),
+            }
+        });
+    let m = load_limbs(range, ctx, m_value, num_limbs + 1, limb_bits);
+
+    // Each carry is below `(num_limbs + 2) * 2^limb_bits` either way, offset to be range-checked
+    let carry_bits = limb_bits + (usize::BITS - (num_limbs + 2).leading_zeros()) as usize + 1;
+    let base = F::from_u128(1u128 << limb_bits);
+    let base_inv = base.invert().unwrap();
+    let carry_offset = F::from_u128(1u128 << (carry_bits - 1));
+    let mut carry: Option<AssignedValue<'v, F>> = None;
+    for k in 0..2 * num_limbs {
+        let pairs = k.saturating_sub(num_limbs - 1)..=k.min(num_limbs);
+        let mn = gate.inner_product(ctx, pairs.clone().map(|i| Existing(&m[i])), pairs.map(|i| Existing(&n[k - i])));
+        let shifted = k.checked_sub(num_limbs);
+        let positive = product
+            .get(k)
+            .into_iter()
+            .chain(shifted.map(|i| &n[i]))
+            .chain(carry.as_ref())
+            .map(Existing);
+        let positive = gate.sum(ctx, positive);
+        let negative = match shifted {
+            Some(i) => gate.add(ctx, Existing(&mn), Existing(&u[i])),
+            None => mn,
+        };
+        let diff = gate.sub(ctx, Existing(&positive), Existing(&negative));
+        if k == 2 * num_limbs - 1 {
+            gate.assert_is_const(ctx, &diff, F::zero());
+            break;
+        }
+        let next = gate.load_witness(ctx, diff.value().map(|diff| *diff * base_inv));
+        let next_shifted = gate.mul(ctx, Existing(&next), Constant(base));
+        gate.assert_equal(ctx, Existing(&next_shifted), Existing(&diff));
+        let offset = gate.add(ctx, Existing(&next), Constant(carry_offset));
+        range.range_check(ctx, &offset, carry_bits);
+        carry = Some(next);
+    }
+}
+
+/// Witnesses `value` as `n.len()` range-checked limbs, constrained to be less than `n`.
+fn load_reduced<'v, F: PrimeField>(
+    range: &RangeConfig<F>,
+    ctx: &mut Context<'v, F>,
+    value: Value<BigUint>,
+    n: &[AssignedValue<'v, F>],
+    limb_bits: usize,
+) -> Vec<AssignedValue<'v, F>> {
+    let limbs = load_limbs(range, ctx, value, n.len(), limb_bits);
+    let limbs_be = limbs.iter().rev().cloned().collect::<Vec<_>>();
+    let n_be = n.iter().rev().cloned().collect::<Vec<_>>();
+    let is_less = is_lex_less_bits(range, ctx, &limbs_be, &n_be, limb_bits);
+    range.gate().assert_is_const(ctx, &is_less, F::one());
+    limbs
+}
+
+fn load_limbs<'v, F: PrimeField>(
+    range: &RangeConfig<F>,
+    ctx: &mut Context<'v, F>,
+    value: Value<BigUint>,
+    num_limbs: usize,
+    limb_bits: usize,
+) -> Vec<AssignedValue<'v, F>> {
+    let limbs = value.map(|value| decompose_biguint::<F>(&value, num_limbs, limb_bits));
+    (0..num_limbs)
+        .map(|i| {
+            let limb = range.gate().load_witness(ctx, limbs.as_ref().map(|limbs| limbs[i]));
+            range.range_check(ctx, &limb, limb_bits);
+            limb
+        })
+        .collect()
+}
+
+/// The integer with little-endian `limb_bits`-bit limbs `limbs`, which may exceed `limb_bits`.
+fn limbs_value<F: PrimeField>(limbs: &[AssignedValue<'_, F>], limb_bits: usize) -> Value<BigUint> {
+    limbs.iter().enumerate().fold(Value::known(BigUint::from(0u8)), |acc, (i, limb)| {
+        acc.zip(limb.value()).map(|(acc, limb)| acc + (fe_to_biguint(limb) << (limb_bits * i)))
+    })
+}
+
+/// `x * R^-1 mod n` for `R = 2^r_bits`, an odd `n` less than `R` and `x` less than `n * R`.
+pub fn redc(x: &BigUint, n: &BigUint, r_bits: usize) -> BigUint {
+    let r = BigUint::from(1u8) << r_bits;
+    let mask = &r - 1u8;
+    // `n^-1 mod R` by Newton's iteration, which doubles the number of correct low bits each step
+    let mut n_inv = BigUint::from(1u8);
+    let mut correct_bits = 1;
+    while correct_bits < r_bits {
+        let n_n_inv = (n * &n_inv) & &mask;
+        n_inv = (&n_inv * ((&r + 2u8 - n_n_inv) & &mask)) & &mask;
+        correct_bits *= 2;
+    }
+    let m = (x * (&r - n_inv)) & &mask;
+    let u = (x + m * n) >> r_bits;
+    if &u >= n {
+        u - n
+    } else {
+        u
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_redc() {
+        let n = BigUint::from(0xffff_fffbu64) * BigUint::from(0xffff_ffbfu64);
+        let r_bits = 64;
+        for x in [0u64, 1, 2, 0x1234_5678_9abc_def0] {
+            let x = BigUint::from(x);
+            let u = redc(&x, &n, r_bits);
+            assert!(u < n);
+            assert_eq!((u << r_bits) % &n, &x % &n, "x = {x}");
+        }
+    }
+}
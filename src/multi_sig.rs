@@ -0,0 +1,177 @@
+//! Several signatures over one message, as when a number of authorities attest to one document.
+use halo2_base::{
+    gates::RangeInstructions,
+    utils::PrimeField,
+    halo2_proofs::{
+        plonk::{Circuit, ConstraintSystem, Error, Column, Instance},
+        circuit::{SimpleFloorPlanner, Layouter, Value, Cell},
+    },
+    SKIP_FIRST_PASS
+};
+use halo2_dynamic_sha256::Sha256DynamicConfig;
+use halo2_rsa::{RSAConfig, RSAInstructions, RSAPubE, RSAPublicKey, RSASignature};
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
+
+use crate::{configure_gadgets, modulus_instance, verify_pkcs1v15_signature, CertificateVerificationCircuit, CircuitParams, CircuitStats};
+
+/// Checks each `(n, signature)` of `signers` over `msg`, under `params.bits_len`-bit keys with
+/// e = 65537. The message is hashed once, in a single SHA256 slot, and every signature is checked
+/// against that digest. Public inputs are `[modulus limbs of each signer, digest bytes, validity
+/// bits]`: one 0/1 bit per signer, in order, so a verifier can count the valid ones for an M-of-N
+/// policy. Unlike `ValidityMode::RequireValid`, invalid signatures don't stop the proof.
+pub struct MultiSignatureCircuit<F: PrimeField> {
+    signers: Vec<(BigUint, BigUint)>,
+    msg: Vec<u8>,
+    params: CircuitParams,
+    _f: std::marker::PhantomData<F>,
+}
+
+impl<F: PrimeField> MultiSignatureCircuit<F> {
+    pub fn new(signers: Vec<(BigUint, BigUint)>, msg: Vec<u8>, params: CircuitParams) -> Self {
+        Self { signers, msg, params, _f: std::marker::PhantomData }
+    }
+
+    /// Whether each signature verifies, computed natively.
+    pub fn validity(&self) -> Vec<bool> {
+        self.signers
+            .iter()
+            .map(|(n_big, sign_big)| CertificateVerificationCircuit::<F>::new(n_big.clone(), sign_big.clone(), self.msg.clone(), self.params.clone()).signature_is_valid())
+            .collect()
+    }
+
+    pub fn public_inputs(&self) -> Vec<Vec<F>> {
+        let limbs = self.signers.iter().flat_map(|(n_big, _)| modulus_instance(n_big, &self.params)).collect();
+        let digest = Sha256::digest(&self.msg).iter().map(|byte| F::from(*byte as u64)).collect();
+        let validity = self.validity().iter().map(|valid| F::from(*valid as u64)).collect();
+        vec![limbs, digest, validity]
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MultiSignatureConfig<F: PrimeField> {
+    rsa_config: RSAConfig<F>,
+    sha256_config: Sha256DynamicConfig<F>,
+    n_instance: Column<Instance>,
+    hash_instance: Column<Instance>,
+    valid_instance: Column<Instance>,
+}
+
+impl<F: PrimeField> Circuit<F> for MultiSignatureCircuit<F> {
+    type Config = MultiSignatureConfig<F>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        unimplemented!();
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let params = CircuitParams::current();
+        let (rsa_config, sha256_config) = configure_gadgets(meta, &params, 1);
+        let n_instance = meta.instance_column();
+        meta.enable_equality(n_instance);
+        let hash_instance = meta.instance_column();
+        meta.enable_equality(hash_instance);
+        let valid_instance = meta.instance_column();
+        meta.enable_equality(valid_instance);
+
+        Self::Config {
+            rsa_config,
+            sha256_config,
+            n_instance,
+            hash_instance,
+            valid_instance
+        }
+    }
+
+    fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+        let biguint_config = config.rsa_config.biguint_config();
+        config.sha256_config.load(&mut layouter)?;
+        biguint_config.range().load_lookup_table(&mut layouter)?;
+        let mut first_pass = SKIP_FIRST_PASS;
+        let (n_cells, hash_cells, valid_cells) = layouter.assign_region(
+            || "multi-signature verifier",
+            |region| {
+                if first_pass {
+                    first_pass = false;
+                    return Ok((vec![], vec![], vec![]));
+                }
+
+                let mut aux = biguint_config.new_context(region);
+                let ctx = &mut aux;
+                let mut sha256_config = config.sha256_config.clone();
+                let hash_result = sha256_config.digest(ctx, &self.msg, None)?;
+
+                let mut n_cells = vec![];
+                let mut valid_cells = vec![];
+                for (n_big, sign_big) in &self.signers {
+                    let e_fix = RSAPubE::Fix(BigUint::from(CertificateVerificationCircuit::<F>::DEFAULT_E));
+                    let public_key = RSAPublicKey::new(Value::known(n_big.clone()), e_fix);
+                    let public_key = config.rsa_config.assign_public_key(ctx, public_key)?;
+                    let signature = RSASignature::new(Value::known(sign_big.clone()));
+                    let signature = config.rsa_config.assign_signature(ctx, signature)?;
+                    let is_valid = verify_pkcs1v15_signature(&config.rsa_config, ctx, &public_key, &hash_result.output_bytes, &signature, self.params.montgomery_exp)?;
+                    n_cells.extend(public_key.n.limbs().iter().map(|v| v.cell()));
+                    valid_cells.push(is_valid.cell());
+                }
+
+                biguint_config.range().finalize(ctx);
+                CircuitStats {
+                    total_advice: ctx.total_advice,
+                    total_fixed: ctx.total_fixed,
+                    lookup_cells: ctx.cells_to_lookup.len(),
+                }.record();
+                let hash_cells = hash_result.output_bytes.iter().map(|v| v.cell()).collect::<Vec<Cell>>();
+                Ok((n_cells, hash_cells, valid_cells))
+            },
+        )?;
+        for (i, cell) in n_cells.into_iter().enumerate() {
+            layouter.constrain_instance(cell, config.n_instance, i)?;
+        }
+        for (i, cell) in hash_cells.into_iter().enumerate() {
+            layouter.constrain_instance(cell, config.hash_instance, i)?;
+        }
+        for (i, cell) in valid_cells.into_iter().enumerate() {
+            layouter.constrain_instance(cell, config.valid_instance, i)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use halo2_base::halo2_proofs::{dev::MockProver, halo2curves::bn256::Fr};
+    use rsa::{Hash, PaddingScheme, PublicKeyParts, RsaPrivateKey};
+
+    /// A fresh 2048-bit key and its signature over `msg`, as `(modulus, signature)`.
+    fn sign(msg: &[u8]) -> (BigUint, BigUint) {
+        let key = RsaPrivateKey::new(&mut rand::thread_rng(), 2048).unwrap();
+        let signature = key.sign(PaddingScheme::new_pkcs1v15_sign(Some(Hash::SHA2_256)), &Sha256::digest(msg)).unwrap();
+        (BigUint::from_bytes_be(&key.n().to_bytes_be()), BigUint::from_bytes_be(&signature))
+    }
+
+    #[test]
+    fn test_two_signers() {
+        let msg = b"a document two authorities sign".to_vec();
+        let (first, second) = (sign(&msg), sign(&msg));
+        let params = CircuitParams { k: 18, ..Default::default() };
+        let circuit = MultiSignatureCircuit::<Fr>::new(vec![first.clone(), second.clone()], msg.clone(), params.clone());
+        assert_eq!(circuit.validity(), vec![true, true]);
+        circuit.params.set_current();
+        let public_inputs = circuit.public_inputs();
+        let prover = MockProver::run(params.k, &circuit, public_inputs.clone()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let mut both_invalid = public_inputs;
+        both_invalid[2] = vec![Fr::zero(), Fr::zero()];
+        let prover = MockProver::run(params.k, &circuit, both_invalid).unwrap();
+        assert!(prover.verify().is_err());
+
+        // A corrupted second signature only clears its own bit
+        let circuit = MultiSignatureCircuit::<Fr>::new(vec![first, (second.0, second.1 + 1u32)], msg, params.clone());
+        assert_eq!(circuit.validity(), vec![true, false]);
+        let prover = MockProver::run(params.k, &circuit, circuit.public_inputs()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+}
@@ -0,0 +1,506 @@
+use std::cell::RefCell;
+
+use num_bigint::BigUint;
+
+use crate::CertError;
+
+/// Order of the instance columns, i.e. of the vectors in the circuit's public inputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InstanceLayout {
+    /// `[modulus limbs, digest bytes]`
+    #[default]
+    ModulusThenDigest,
+    /// `[digest bytes, modulus limbs]`
+    DigestThenModulus,
+}
+
+impl InstanceLayout {
+    /// Index of the digest column in the public inputs.
+    pub fn digest_index(&self) -> usize {
+        match self {
+            InstanceLayout::ModulusThenDigest => 1,
+            InstanceLayout::DigestThenModulus => 0,
+        }
+    }
+}
+
+/// An instance column the circuit can expose, see `CircuitParams::instance_columns`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InstanceColumn {
+    Modulus,
+    IssuerKeyHash,
+    Digest,
+    San,
+    DnsName,
+    DnsNames,
+    SigAlg,
+    SignedRange,
+    TbsDigest,
+    NotExpired,
+    ValidityDates,
+    Exponent,
+    Valid,
+    ByteRange,
+}
+
+impl InstanceColumn {
+    /// Name of the column in errors.
+    pub fn name(&self) -> &'static str {
+        match self {
+            InstanceColumn::Modulus => "modulus",
+            InstanceColumn::IssuerKeyHash => "issuer_key_hash",
+            InstanceColumn::Digest => "digest",
+            InstanceColumn::San => "san",
+            InstanceColumn::DnsName => "dns_name",
+            InstanceColumn::DnsNames => "dns_names",
+            InstanceColumn::SigAlg => "sig_alg",
+            InstanceColumn::SignedRange => "signed_range",
+            InstanceColumn::TbsDigest => "tbs_digest",
+            InstanceColumn::NotExpired => "not_expired",
+            InstanceColumn::ValidityDates => "validity_dates",
+            InstanceColumn::Exponent => "exponent",
+            InstanceColumn::Valid => "valid",
+            InstanceColumn::ByteRange => "byte_range",
+        }
+    }
+}
+
+/// One instance column of a circuit's public inputs, as reported by
+/// `CertificateVerificationCircuit::public_input_columns`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InstanceColumnSpec {
+    pub column: InstanceColumn,
+    /// `column.name()`.
+    pub name: &'static str,
+    /// Number of field elements in the column.
+    pub len: usize,
+}
+
+/// What the circuit does with the result of the signature check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ValidityMode {
+    /// Constrains the signature to be valid, so a proof exists only for valid certificates and
+    /// proves exactly that.
+    #[default]
+    RequireValid,
+    /// Leaves the result unconstrained and exposes it as a final 0/1 instance column, so a proof
+    /// exists for any signature and proves whether it is valid. Verifiers must check the column.
+    ExposeResult,
+}
+
+/// Digest the signature covers. Only SHA-256 has an in-circuit gadget; the others are here so
+/// parameters and instances meant for them can be told apart and rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DigestAlgorithm {
+    #[default]
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl DigestAlgorithm {
+    /// Digest length in bytes.
+    pub fn output_len(&self) -> usize {
+        match self {
+            DigestAlgorithm::Sha256 => 32,
+            DigestAlgorithm::Sha384 => 48,
+            DigestAlgorithm::Sha512 => 64,
+        }
+    }
+
+    /// OID of the algorithm, as named in a PKCS#1 DigestInfo.
+    pub fn oid(&self) -> &'static str {
+        match self {
+            DigestAlgorithm::Sha256 => "2.16.840.1.101.3.4.2.1",
+            DigestAlgorithm::Sha384 => "2.16.840.1.101.3.4.2.2",
+            DigestAlgorithm::Sha512 => "2.16.840.1.101.3.4.2.3",
+        }
+    }
+}
+
+/// How the digest instance column encodes the digest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DigestEncoding {
+    /// One field element per byte, in digest order.
+    #[default]
+    Bytes,
+    /// One field element per 16 bytes, read big-endian, in digest order: the two `uint256` halves
+    /// an EVM verifier gets by splitting the `sha256` precompile output. See `evm_digest_instance`.
+    EvmPacked,
+}
+
+/// Circuit dimensions and optional features. `Circuit::configure` reads these through
+/// `set_current`/`current`, so prover and verifier must agree on every field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CircuitParams {
+    /// The circuit has `2^k` rows.
+    pub k: u32,
+    pub bits_len: usize,
+    pub limb_bits: usize,
+    /// Bit length halo2-rsa allows for a variable (witnessed) exponent.
+    pub exp_limb_bits: usize,
+    /// The issuer key's public exponent, a constant of the circuit. Odd, at least 3 and shorter than
+    /// the modulus; each bit costs a modular squaring, so 65537 (17 bits) is the cheap common case.
+    /// With `variable_exponent`, the witness value instead.
+    pub public_exponent: BigUint,
+    /// Makes the exponent a witness of at most `exp_limb_bits` bits, constrained odd and at least 3,
+    /// so keys with different exponents share a verifying key. It is committed to as a single
+    /// field element in an instance column after any expiry column (see `exponent_instance`).
+    /// Costs `exp_limb_bits` squarings and multiplications, whatever the exponent.
+    pub variable_exponent: bool,
+    /// Computes the signature exponentiation in Montgomery form (see `montgomery`) instead of with
+    /// halo2-rsa's `mul_mod`. Same result, columns and instances; only the cells differ, as the
+    /// `montgomery_exp` bench measures. Needs a fixed `public_exponent`, and a signature that isn't
+    /// less than the modulus has no witness on this path, valid or not.
+    pub montgomery_exp: bool,
+    pub num_advice: usize,
+    pub num_fixed: usize,
+    /// Range-check lookup columns, one lookup argument each. Fewer columns hold the same lookup
+    /// cells in more rows, so `k` may have to grow (see `num_lookup_arguments`).
+    pub num_lookup_advice: usize,
+    pub lookup_bits: usize,
+    /// Padded SHA256 input capacity in bytes, a multiple of 64.
+    pub msg_len: usize,
+    pub sha256_lookup_bits: usize,
+    /// SHA256 spread-table lookup columns, one lookup argument each, with the same rows-for-columns
+    /// trade-off as `num_lookup_advice`.
+    pub sha256_lookup_advice: usize,
+    pub digest: DigestAlgorithm,
+    pub digest_encoding: DigestEncoding,
+    /// Bakes a single issuer modulus into the circuit as constants. The modulus instance column
+    /// is dropped and the circuit only verifies signatures under this key.
+    pub fixed_modulus: Option<BigUint>,
+    /// Replaces the modulus instance column with a single-element commitment to the modulus (see
+    /// `issuer_key_hash`), hashed in-circuit, so the proof binds to a known issuer in one element.
+    /// Costs a SHA256 slot per certificate, and `msg_len` must fit the modulus bytes.
+    pub issuer_key_hash: bool,
+    pub instance_layout: InstanceLayout,
+    /// When set, the subjectAltName `extnValue` (at most this many bytes, < 128) is exposed
+    /// as an extra instance column after the modulus and digest.
+    pub san_max_len: Option<usize>,
+    /// When set (together with `san_max_len`), the SAN stays private and only a target dNSName of
+    /// at most this many bytes is public, proven to be one of the SAN entries.
+    pub dns_name_max_len: Option<usize>,
+    /// When set (together with `san_max_len`) to `(max names, max name length)`, the SAN stays
+    /// private and a SHA-256 commitment to its sorted set of dNSNames is public instead (see
+    /// `dns_names_commitment`), so many names cost one 32-byte column. The SAN must hold only
+    /// distinct dNSNames.
+    pub dns_names_commitment: Option<(usize, usize)>,
+    /// When set, the OID of the TBS `signature` AlgorithmIdentifier (at most this many content
+    /// bytes, < 128) is exposed as `[len, oid bytes.., 0 padding]`, after any SAN/dNSName column.
+    pub sig_alg_max_len: Option<usize>,
+    /// When set, the message is a whole DER certificate (a 2-byte long-form length, TBS included)
+    /// rather than its TBS: the digest instance is the SHA-256 of the certificate, and
+    /// `[start, end)` of the TBS within it, the bytes the signature covers, is an instance column
+    /// after any signature algorithm column. Costs a second SHA256 slot per certificate, and
+    /// `msg_len` must fit the certificate.
+    pub signed_range: bool,
+    /// With `signed_range`, also exposes the SHA-256 of the TBS, as 32 bytes whatever
+    /// `digest_encoding`, in an instance column after the signed range: the digest instance
+    /// commits to the whole certificate, signature included, and this one to the signed bytes.
+    pub tbs_digest: bool,
+    /// When set, `[current time, not expired]` is an instance column after any signed range: a
+    /// public time (see `time_instance`) and whether it is at most the certificate's notAfter,
+    /// which stays private. notAfter must be a UTCTime, and the TBS must have the short-form
+    /// serialNumber and AlgorithmIdentifier and the issuer header (at most 255 bytes) the circuit
+    /// locates it by.
+    pub expiry_check: bool,
+    /// When set, `[notBefore, notAfter]` as unix timestamps is an instance column after any expiry
+    /// column, for verifiers that apply their own time policy. The TBS layout requirements are
+    /// those of `expiry_check`, and both times must fall in 1970 to 2049.
+    pub validity_dates: bool,
+    pub validity: ValidityMode,
+    /// Drops the digest instance column, for pure validity proofs that shouldn't reveal which
+    /// certificate was checked. Leaves just the modulus, or nothing with `fixed_modulus`.
+    pub hide_digest: bool,
+    /// Constrains the modulus to be free of the factors in `SMALL_PRIMES` (it is always constrained
+    /// to be odd). A cheap sanity check against garbage keys, not a primality test of the factors.
+    pub check_small_factors: bool,
+    /// Constrains the issuer modulus to differ from the certificate's own SPKI modulus, rejecting
+    /// self-signed certificates where a leaf is required. The subject key must then be a
+    /// `bits_len`-bit RSA key with e = 65537, located like a chain issuer's.
+    pub not_self_signed: bool,
+    /// When nonzero, the message is this many `ByteRange` segments of a larger document, and the
+    /// segment lengths (zero-padded) are a final instance column constrained to sum to the hashed
+    /// length.
+    pub byte_range_segments: usize,
+    /// For experimental DSA-style schemes: the signature covers only the leading this many digest
+    /// bytes, in a DigestInfo whose lengths are adjusted to match. The digest instance stays whole.
+    /// `None`, as PKCS#1 v1.5 requires, signs the full digest.
+    pub digest_truncation: Option<usize>,
+}
+
+/// Number of `limb_bits`-bit limbs of a `bits_len`-bit integer, rounded up. halo2-rsa takes
+/// `bits_len` to be a whole number of limbs, which debug builds assert.
+pub fn num_limbs(bits_len: usize, limb_bits: usize) -> usize {
+    debug_assert_eq!(bits_len % limb_bits, 0, "bits_len must be a multiple of limb_bits");
+    (bits_len + limb_bits - 1) / limb_bits
+}
+
+/// Primes `check_small_factors` rules out as factors of the modulus.
+pub const SMALL_PRIMES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+
+fn max_msg_len_in(msg_len: usize) -> usize {
+    msg_len.saturating_sub(9)
+}
+
+thread_local! {
+    static CIRCUIT_PARAMS: RefCell<CircuitParams> = RefCell::new(CircuitParams::default());
+}
+
+impl Default for CircuitParams {
+    fn default() -> Self {
+        Self::rsa_2048_sha256()
+    }
+}
+
+impl CircuitParams {
+    /// sha256WithRSAEncryption under a 2048-bit issuer key, TBS up to 1271 bytes.
+    pub fn rsa_2048_sha256() -> Self {
+        Self {
+            k: 16,
+            bits_len: 2048,
+            limb_bits: 64,
+            exp_limb_bits: 5,
+            public_exponent: BigUint::from(65537u32),
+            variable_exponent: false,
+            montgomery_exp: false,
+            num_advice: 40,
+            num_fixed: 1,
+            num_lookup_advice: 4,
+            lookup_bits: 12,
+            msg_len: 1280,
+            sha256_lookup_bits: 8,      // is this enough?
+            sha256_lookup_advice: 8,    // might need to increase this
+            digest: DigestAlgorithm::Sha256,
+            digest_encoding: DigestEncoding::default(),
+            fixed_modulus: None,
+            issuer_key_hash: false,
+            instance_layout: InstanceLayout::default(),
+            san_max_len: None,
+            dns_name_max_len: None,
+            dns_names_commitment: None,
+            sig_alg_max_len: None,
+            signed_range: false,
+            tbs_digest: false,
+            expiry_check: false,
+            validity_dates: false,
+            validity: ValidityMode::default(),
+            hide_digest: false,
+            check_small_factors: false,
+            not_self_signed: false,
+            byte_range_segments: 0,
+            digest_truncation: None,
+        }
+    }
+
+    /// sha256WithRSAEncryption under a 4096-bit issuer key (typical of roots), TBS up to 1911 bytes.
+    /// There is no SHA-384 gadget yet, so sha384WithRSAEncryption profiles aren't covered.
+    pub fn rsa_4096_sha256() -> Self {
+        Self {
+            k: 17,
+            bits_len: 4096,
+            msg_len: 1920,
+            ..Self::rsa_2048_sha256()
+        }
+    }
+
+    /// Longest message, in bytes, that fits the `msg_len`-byte SHA256 slot: SHA-256 padding takes
+    /// at least 9 bytes of it.
+    pub fn max_supported_msg_len(&self) -> usize {
+        max_msg_len_in(self.msg_len)
+    }
+
+    /// Sets `msg_len` to the smallest of `msg_len_options` that fits a `message_len`-byte message
+    /// (see `max_supported_msg_len`). Any slot that fits gives the same digest and validity, the
+    /// smaller ones just cost fewer rows.
+    pub fn with_msg_len_for(self, msg_len_options: &[usize], message_len: usize) -> Result<Self, CertError> {
+        let fits = |msg_len: &usize| message_len <= max_msg_len_in(*msg_len);
+        match msg_len_options.iter().copied().filter(fits).min() {
+            Some(msg_len) => Ok(Self { msg_len, ..self }),
+            None => {
+                let max = msg_len_options.iter().max().map_or(0, |msg_len| max_msg_len_in(*msg_len));
+                Err(CertError::MessageTooLong { len: message_len, max })
+            }
+        }
+    }
+
+    /// Lookup arguments the circuits configure: one per lookup column of the range and SHA256
+    /// gadgets. Backends that charge per argument can trade them for rows, at a larger `k`.
+    pub fn num_lookup_arguments(&self) -> usize {
+        self.num_lookup_advice + self.sha256_lookup_advice
+    }
+
+    /// Checks `num_lookup_arguments` against a backend's `max`.
+    pub fn check_lookup_limit(&self, max: usize) -> Result<(), CertError> {
+        match self.num_lookup_arguments() {
+            count if count > max => Err(CertError::TooManyLookups { count, max }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Bit length of `public_exponent`: the least `exp_limb_bits` that fits it with `variable_exponent`.
+    pub fn required_exp_limb_bits(&self) -> usize {
+        self.public_exponent.bits() as usize
+    }
+
+    /// Checks that `public_exponent` fits `exp_limb_bits` with `variable_exponent`, which
+    /// `Circuit::configure` asserts.
+    pub fn check_exponent(&self) -> Result<(), CertError> {
+        match self.required_exp_limb_bits() {
+            bits if self.variable_exponent && bits > self.exp_limb_bits => {
+                Err(CertError::ExponentTooLarge { bits, exp_limb_bits: self.exp_limb_bits })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Sets `public_exponent` to `e`, with `exp_limb_bits` sized to fit it exactly. Keys with
+    /// different exponents share a `variable_exponent` verifying key only if it fits the longest.
+    pub fn with_exponent(self, e: BigUint) -> Self {
+        let exp_limb_bits = e.bits() as usize;
+        Self { public_exponent: e, exp_limb_bits, ..self }
+    }
+
+    /// Rows of the circuit, `2^k`.
+    pub fn rows(&self) -> usize {
+        1 << self.k
+    }
+
+    /// Limbs of the modulus, and of the signature, in the assigned integers.
+    pub fn num_limbs(&self) -> usize {
+        num_limbs(self.bits_len, self.limb_bits)
+    }
+
+    /// Number of leading digest bytes the signature covers.
+    pub fn signed_digest_len(&self) -> usize {
+        self.digest_truncation.unwrap_or(self.digest.output_len())
+    }
+
+    /// Length of the digest instance column under `digest_encoding`.
+    pub fn digest_instance_len(&self) -> usize {
+        match self.digest_encoding {
+            DigestEncoding::Bytes => self.digest.output_len(),
+            DigestEncoding::EvmPacked => self.digest.output_len() / 16,
+        }
+    }
+
+    /// Index of the digest column in the public inputs, `None` with `hide_digest`.
+    pub fn digest_index(&self) -> Option<usize> {
+        self.instance_columns().iter().position(|(column, _)| *column == InstanceColumn::Digest)
+    }
+
+    /// `(column, length)` of each instance column, in public-input order.
+    pub(crate) fn instance_columns(&self) -> Vec<(InstanceColumn, usize)> {
+        let modulus = match self.issuer_key_hash {
+            true => (InstanceColumn::IssuerKeyHash, 1),
+            false => (InstanceColumn::Modulus, self.num_limbs()),
+        };
+        let digest = (InstanceColumn::Digest, self.digest_instance_len());
+        let mut columns = match (&self.fixed_modulus, self.hide_digest, self.instance_layout) {
+            (Some(_), true, _) => vec![],
+            (Some(_), false, _) => vec![digest],
+            (None, true, _) => vec![modulus],
+            (None, false, InstanceLayout::ModulusThenDigest) => vec![modulus, digest],
+            (None, false, InstanceLayout::DigestThenModulus) => vec![digest, modulus],
+        };
+        if let Some(max_len) = self.san_max_len {
+            // The SAN itself is only public when nothing is derived from it
+            if self.dns_name_max_len.is_none() && self.dns_names_commitment.is_none() {
+                columns.push((InstanceColumn::San, max_len + 1));
+            }
+            if let Some(max_len) = self.dns_name_max_len {
+                columns.push((InstanceColumn::DnsName, max_len + 1));
+            }
+            if self.dns_names_commitment.is_some() {
+                columns.push((InstanceColumn::DnsNames, 32));
+            }
+        }
+        if let Some(max_len) = self.sig_alg_max_len {
+            columns.push((InstanceColumn::SigAlg, max_len + 1));
+        }
+        if self.signed_range {
+            columns.push((InstanceColumn::SignedRange, 2));
+            if self.tbs_digest {
+                columns.push((InstanceColumn::TbsDigest, 32));
+            }
+        }
+        if self.expiry_check {
+            columns.push((InstanceColumn::NotExpired, 2));
+        }
+        if self.validity_dates {
+            columns.push((InstanceColumn::ValidityDates, 2));
+        }
+        if self.variable_exponent {
+            columns.push((InstanceColumn::Exponent, 1));
+        }
+        if self.validity == ValidityMode::ExposeResult {
+            columns.push((InstanceColumn::Valid, 1));
+        }
+        if self.byte_range_segments > 0 {
+            columns.push((InstanceColumn::ByteRange, self.byte_range_segments));
+        }
+        columns
+    }
+
+    /// Splits flat public inputs (see `CertificateVerificationCircuit::flatten_public_inputs`) back
+    /// into one vector per instance column.
+    pub fn reshape_public_inputs<F: Copy>(&self, flat: &[F]) -> Result<Vec<Vec<F>>, CertError> {
+        let columns = self.instance_columns();
+        let expected = columns.iter().map(|(_, len)| len).sum::<usize>();
+        if flat.len() != expected {
+            return Err(CertError::InstanceLength { column: "flattened", expected, actual: flat.len() });
+        }
+        let mut rest = flat;
+        Ok(columns
+            .into_iter()
+            .map(|(_, len)| {
+                let (column, tail) = rest.split_at(len);
+                rest = tail;
+                column.to_vec()
+            })
+            .collect())
+    }
+
+    /// The fields in which `self` and `other` differ, as `field: self value vs other value`. Any
+    /// difference changes the circuit or its instances, so a proof made with one params fails to
+    /// verify with the other: this is where to look when a proof unexpectedly doesn't verify.
+    pub fn diff(&self, other: &Self) -> Vec<String> {
+        // Exhaustive, so a new field can't be left out of the comparison
+        let Self {
+            k, bits_len, limb_bits, exp_limb_bits, public_exponent, variable_exponent, montgomery_exp,
+            num_advice, num_fixed, num_lookup_advice, lookup_bits, msg_len, sha256_lookup_bits,
+            sha256_lookup_advice, digest, digest_encoding, fixed_modulus, issuer_key_hash,
+            instance_layout, san_max_len, dns_name_max_len, dns_names_commitment, sig_alg_max_len,
+            signed_range, tbs_digest, expiry_check, validity_dates, validity, hide_digest,
+            check_small_factors, not_self_signed, byte_range_segments, digest_truncation,
+        } = self;
+        let mut diffs = vec![];
+        macro_rules! compare {
+            ($($field:ident),*) => {
+                $(if *$field != other.$field {
+                    diffs.push(format!("{}: {:?} vs {:?}", stringify!($field), $field, other.$field));
+                })*
+            };
+        }
+        compare!(
+            k, bits_len, limb_bits, exp_limb_bits, public_exponent, variable_exponent, montgomery_exp,
+            num_advice, num_fixed, num_lookup_advice, lookup_bits, msg_len, sha256_lookup_bits,
+            sha256_lookup_advice, digest, digest_encoding, fixed_modulus, issuer_key_hash,
+            instance_layout, san_max_len, dns_name_max_len, dns_names_commitment, sig_alg_max_len,
+            signed_range, tbs_digest, expiry_check, validity_dates, validity, hide_digest,
+            check_small_factors, not_self_signed, byte_range_segments, digest_truncation
+        );
+        diffs
+    }
+
+    /// `Circuit::configure` can't see the circuit, so it reads the params set here.
+    /// Must be called on the proving/keygen thread before `MockProver::run`/`keygen_vk`.
+    pub fn set_current(&self) {
+        CIRCUIT_PARAMS.with(|params| *params.borrow_mut() = self.clone());
+    }
+
+    pub fn current() -> Self {
+        CIRCUIT_PARAMS.with(|params| params.borrow().clone())
+    }
+}
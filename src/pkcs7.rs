@@ -0,0 +1,417 @@
+//! CMS/PKCS#7 `SignedData` signatures, as embedded in signed PDFs.
+//!
+//! A signer with signed attributes signs the DER of the attributes as a `SET OF`, not the
+//! content itself. The attributes then carry the content hash in `messageDigest`.
+use halo2_base::{
+    gates::GateInstructions,
+    utils::PrimeField,
+    QuantumCell::Existing,
+    halo2_proofs::{
+        plonk::{Circuit, ConstraintSystem, Error, Column, Instance},
+        circuit::{SimpleFloorPlanner, Layouter, Value, Cell},
+    },
+    SKIP_FIRST_PASS
+};
+use halo2_dynamic_sha256::Sha256DynamicConfig;
+use halo2_rsa::{RSAConfig, RSAInstructions, RSAPubE, RSAPublicKey, RSASignature};
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
+
+use crate::der::{children, read_tlv, TAG_OCTET_STRING, TAG_OID, TAG_SEQUENCE, TAG_SET};
+use crate::gadgets::select_window;
+use crate::{configure_gadgets, modulus_instance, verify_pkcs1v15_signature};
+use crate::{CertError, CertificateVerificationCircuit, CircuitParams, CircuitStats, Field};
+
+/// DER contents of the id-signedData OID (1.2.840.113549.1.7.2).
+pub const OID_SIGNED_DATA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x02];
+/// A messageDigest (1.2.840.113549.1.9.4) attribute holding a SHA-256 digest, up to the digest:
+/// `SEQUENCE { OID, SET { OCTET STRING (32 bytes) } }`.
+pub const MESSAGE_DIGEST_PREFIX: [u8; 17] = [
+    0x30, 0x2f, 0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x04, 0x31, 0x22, 0x04, 0x20,
+];
+/// `[0]`, both the explicit SignedData wrapper and the implicit `signedAttrs` tag.
+const TAG_CONTEXT_0: u8 = 0xa0;
+
+/// What the first `SignerInfo` of a `SignedData` signs, and its signature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignerInfoParts {
+    /// The `signedAttrs`, re-tagged as the `SET OF` that is actually signed.
+    pub signed_attrs: Vec<u8>,
+    pub signature: BigUint,
+}
+
+/// Extracts the signed attributes and signature of the first signer of a DER `ContentInfo`
+/// holding `SignedData`. Signers without signed attributes (signing the content directly) are
+/// rejected.
+pub fn parse_signed_data(der: &[u8]) -> Result<SignerInfoParts, CertError> {
+    let malformed = CertError::MalformedStructure;
+    let outer = read_tlv(der, 0)
+        .filter(|tlv| tlv.tag == TAG_SEQUENCE && tlv.end() == der.len())
+        .ok_or(malformed("ContentInfo is not a DER SEQUENCE"))?;
+    let content = match children(der, &outer).as_deref() {
+        Some([oid, content]) if oid.tag == TAG_OID && &der[oid.content()] == OID_SIGNED_DATA && content.tag == TAG_CONTEXT_0 => *content,
+        _ => return Err(malformed("not a SignedData ContentInfo")),
+    };
+    let signed_data = read_tlv(der, content.content().start)
+        .filter(|tlv| tlv.tag == TAG_SEQUENCE && tlv.end() == content.end())
+        .ok_or(malformed("missing SignedData"))?;
+    // signerInfos is the last field; digestAlgorithms is also a SET, so search from the end
+    let signer_infos = children(der, &signed_data)
+        .and_then(|fields| fields.last().copied())
+        .filter(|tlv| tlv.tag == TAG_SET)
+        .ok_or(malformed("missing signerInfos"))?;
+    let signer_info = read_tlv(der, signer_infos.content().start)
+        .filter(|tlv| tlv.tag == TAG_SEQUENCE && signer_infos.len > 0)
+        .ok_or(malformed("empty signerInfos"))?;
+    let fields = children(der, &signer_info).ok_or(malformed("SignerInfo fields don't parse"))?;
+
+    let attrs_index = fields
+        .iter()
+        .position(|tlv| tlv.tag == TAG_CONTEXT_0)
+        .ok_or(malformed("SignerInfo has no signed attributes"))?;
+    let attrs = fields[attrs_index];
+    // signatureAlgorithm follows the attributes, then the signature
+    let signature = fields
+        .get(attrs_index + 2)
+        .filter(|tlv| tlv.tag == TAG_OCTET_STRING)
+        .ok_or(malformed("missing SignerInfo signature"))?;
+
+    let mut signed_attrs = der[attrs.start..attrs.end()].to_vec();
+    signed_attrs[0] = TAG_SET;
+    Ok(SignerInfoParts { signed_attrs, signature: BigUint::from_bytes_be(&der[signature.content()]) })
+}
+
+/// Offset in `signed_attrs` (a `SET OF` Attribute) of the SHA-256 messageDigest attribute.
+pub fn find_message_digest(signed_attrs: &[u8]) -> Option<usize> {
+    let set = read_tlv(signed_attrs, 0).filter(|tlv| tlv.tag == TAG_SET && tlv.end() == signed_attrs.len())?;
+    children(signed_attrs, &set)?
+        .into_iter()
+        .find(|attr| signed_attrs[attr.start..].starts_with(&MESSAGE_DIGEST_PREFIX) && attr.end() == attr.start + 49)
+        .map(|attr| attr.start)
+}
+
+/// Checks natively that the messageDigest of `signed_attrs` (the `SET OF` of `SignerInfoParts`) is
+/// `content_digest` and that `sign_big` is a PKCS#1 v1.5 SHA-256 signature over `signed_attrs`
+/// under `n_big`, with e = 65537.
+///
+/// This is how CMS signs with attributes, and how Authenticode signs the `SpcIndirectDataContent`
+/// holding a file hash: the content only enters the signature through the attribute, so its digest
+/// can come from wherever the caller hashed it.
+pub fn verify_signed_attributes(
+    signed_attrs: &[u8],
+    content_digest: &[u8; 32],
+    n_big: &BigUint,
+    sign_big: &BigUint,
+) -> Result<(), CertError> {
+    let offset = find_message_digest(signed_attrs)
+        .ok_or(CertError::MalformedStructure("signed attributes have no SHA-256 messageDigest"))?;
+    if &signed_attrs[offset + MESSAGE_DIGEST_PREFIX.len()..offset + 49] != content_digest {
+        return Err(CertError::ContentDigestMismatch);
+    }
+    let params = CircuitParams { bits_len: (n_big.bits() as usize + 7) / 8 * 8, ..Default::default() };
+    let circuit = CertificateVerificationCircuit::<Field>::new(n_big.clone(), sign_big.clone(), signed_attrs, params);
+    match circuit.signature_is_valid() {
+        true => Ok(()),
+        false => Err(CertError::InvalidSignature),
+    }
+}
+
+/// Verifies a CMS signer's PKCS#1 v1.5 SHA-256 signature over its signed attributes, and that the
+/// attributes' messageDigest is the SHA-256 of `content`. Public inputs are
+/// `[signer modulus limbs, signed attributes digest, content digest]`.
+///
+/// Only the dimension fields of `params` are used; `msg_len` bounds both the attributes and the
+/// content.
+pub struct Pkcs7SignatureCircuit<F: PrimeField> {
+    n_big: BigUint,
+    sign_big: BigUint,
+    signed_attrs: Vec<u8>,
+    /// `None` when only the digest is known, see `from_content_digest`.
+    content: Option<Vec<u8>>,
+    content_digest: [u8; 32],
+    params: CircuitParams,
+    _f: std::marker::PhantomData<F>,
+}
+
+impl<F: PrimeField> Pkcs7SignatureCircuit<F> {
+    pub fn new(n_big: BigUint, signer_info: SignerInfoParts, content: impl Into<Vec<u8>>, params: CircuitParams) -> Self {
+        let content = content.into();
+        let content_digest = Sha256::digest(&content).into();
+        Self::with_content(n_big, signer_info, Some(content), content_digest, params)
+    }
+
+    /// Like `new`, for signed attributes over content the circuit doesn't hash, such as an
+    /// Authenticode `SpcIndirectDataContent` or a PDF byte range hashed elsewhere. The public
+    /// content digest is then only tied to the signature through the messageDigest attribute, and
+    /// the circuit doesn't show it is the hash of anything in particular.
+    pub fn from_content_digest(n_big: BigUint, signer_info: SignerInfoParts, content_digest: [u8; 32], params: CircuitParams) -> Self {
+        Self::with_content(n_big, signer_info, None, content_digest, params)
+    }
+
+    fn with_content(
+        n_big: BigUint,
+        signer_info: SignerInfoParts,
+        content: Option<Vec<u8>>,
+        content_digest: [u8; 32],
+        params: CircuitParams,
+    ) -> Self {
+        let SignerInfoParts { signed_attrs, signature } = signer_info;
+        Self { n_big, sign_big: signature, signed_attrs, content, content_digest, params, _f: std::marker::PhantomData }
+    }
+
+    pub fn public_inputs(&self) -> Vec<Vec<F>> {
+        let bytes_fes = |bytes: &[u8]| bytes.iter().map(|byte| F::from(*byte as u64)).collect::<Vec<F>>();
+        vec![
+            modulus_instance(&self.n_big, &self.params),
+            bytes_fes(&Sha256::digest(&self.signed_attrs)),
+            bytes_fes(&self.content_digest),
+        ]
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Pkcs7SignatureConfig<F: PrimeField> {
+    rsa_config: RSAConfig<F>,
+    sha256_config: Sha256DynamicConfig<F>,
+    n_instance: Column<Instance>,
+    hash_instance: Column<Instance>,
+    content_hash_instance: Column<Instance>,
+}
+
+impl<F: PrimeField> Circuit<F> for Pkcs7SignatureCircuit<F> {
+    type Config = Pkcs7SignatureConfig<F>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        unimplemented!();
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let params = CircuitParams::current();
+        // One SHA256 slot for the signed attributes, one for the content
+        let (rsa_config, sha256_config) = configure_gadgets(meta, &params, 2);
+        let n_instance = meta.instance_column();
+        meta.enable_equality(n_instance);
+        let hash_instance = meta.instance_column();
+        meta.enable_equality(hash_instance);
+        let content_hash_instance = meta.instance_column();
+        meta.enable_equality(content_hash_instance);
+
+        Self::Config {
+            rsa_config,
+            sha256_config,
+            n_instance,
+            hash_instance,
+            content_hash_instance
+        }
+    }
+
+    fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+        let biguint_config = config.rsa_config.biguint_config();
+        config.sha256_config.load(&mut layouter)?;
+        biguint_config.range().load_lookup_table(&mut layouter)?;
+        let mut first_pass = SKIP_FIRST_PASS;
+        let (public_key_cells, hashed_msg_cells, content_hash_cells) = layouter.assign_region(
+            || "cms signature verifier",
+            |region| {
+                if first_pass {
+                    first_pass = false;
+                    return Ok((vec![], vec![], vec![]));
+                }
+
+                let mut aux = biguint_config.new_context(region);
+                let ctx = &mut aux;
+                let e_fix = RSAPubE::Fix(BigUint::from(CertificateVerificationCircuit::<F>::DEFAULT_E));
+                let public_key = RSAPublicKey::new(Value::known(self.n_big.clone()), e_fix);
+                let public_key = config.rsa_config.assign_public_key(ctx, public_key)?;
+                let signature = RSASignature::new(Value::known(self.sign_big.clone()));
+                let signature = config.rsa_config.assign_signature(ctx, signature)?;
+
+                let mut sha256_config = config.sha256_config.clone();
+                let hash_result = sha256_config.digest(ctx, &self.signed_attrs, None)?;
+                let hashed_msg = hash_result.output_bytes;
+                let is_valid = verify_pkcs1v15_signature(&config.rsa_config, ctx, &public_key, &hashed_msg, &signature, self.params.montgomery_exp)?;
+                let gate = biguint_config.gate();
+                gate.assert_is_const(ctx, &is_valid, F::one());
+
+                // The messageDigest attribute (at a witnessed offset, anchored by its DER prefix) is
+                // the content hash. Only the signer could have put a second such pattern in the
+                // attributes, so the anchor is enough.
+                let content_hash = match &self.content {
+                    Some(content) => sha256_config.digest(ctx, content, None)?.output_bytes,
+                    None => self
+                        .content_digest
+                        .iter()
+                        .map(|byte| gate.load_witness(ctx, Value::known(F::from(*byte as u64))))
+                        .collect(),
+                };
+                let offset = find_message_digest(&self.signed_attrs).ok_or(Error::Synthesis)?;
+                let start = gate.load_witness(ctx, Value::known(F::from(offset as u64)));
+                let window = select_window(gate, ctx, &hash_result.input_bytes, &start, MESSAGE_DIGEST_PREFIX.len() + 32);
+                let (prefix, attr_digest) = window.split_at(MESSAGE_DIGEST_PREFIX.len());
+                for (byte, expected) in prefix.iter().zip(MESSAGE_DIGEST_PREFIX) {
+                    gate.assert_is_const(ctx, byte, F::from(expected as u64));
+                }
+                for (attr_byte, content_byte) in attr_digest.iter().zip(&content_hash) {
+                    gate.assert_equal(ctx, Existing(attr_byte), Existing(content_byte));
+                }
+
+                biguint_config.range().finalize(ctx);
+                CircuitStats {
+                    total_advice: ctx.total_advice,
+                    total_fixed: ctx.total_fixed,
+                    lookup_cells: ctx.cells_to_lookup.len(),
+                }.record();
+                let public_key_cells = public_key
+                    .n
+                    .limbs()
+                    .iter()
+                    .map(|v| v.cell())
+                    .collect::<Vec<Cell>>();
+                let hashed_msg_cells = hashed_msg
+                    .into_iter()
+                    .map(|v| v.cell())
+                    .collect::<Vec<Cell>>();
+                let content_hash_cells = content_hash
+                    .into_iter()
+                    .map(|v| v.cell())
+                    .collect::<Vec<Cell>>();
+                Ok((public_key_cells, hashed_msg_cells, content_hash_cells))
+            },
+        )?;
+        for (i, cell) in public_key_cells.into_iter().enumerate() {
+            layouter.constrain_instance(cell, config.n_instance, i)?;
+        }
+        for (i, cell) in hashed_msg_cells.into_iter().enumerate() {
+            layouter.constrain_instance(cell, config.hash_instance, i)?;
+        }
+        for (i, cell) in content_hash_cells.into_iter().enumerate() {
+            layouter.constrain_instance(cell, config.content_hash_instance, i)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::der::encode_tlv;
+    use crate::test::load_fixture;
+    use crate::test_utils::fixture_path;
+    use halo2_base::halo2_proofs::{dev::MockProver, halo2curves::bn256::Fr};
+
+    /// Detached `openssl cms -sign -md sha256` of `signed_content.txt` by the san_leaf key.
+    fn signed_data() -> Vec<u8> {
+        std::fs::read(fixture_path("./certs/signed_content.p7s")).expect("Failed to read SignedData")
+    }
+
+    fn content() -> Vec<u8> {
+        std::fs::read(fixture_path("./certs/signed_content.txt")).expect("Failed to read signed content")
+    }
+
+    #[test]
+    fn test_parse_signed_data() {
+        let parts = parse_signed_data(&signed_data()).unwrap();
+        assert_eq!(parts.signed_attrs.len(), 231);
+        assert_eq!(parts.signed_attrs[0], TAG_SET);
+
+        // The digest recovered from the signature is that of the re-tagged attributes
+        let (signer_n, _, _) = load_fixture("./certs/san_leaf.pem", "./certs/san_leaf.pem");
+        let em = parts.signature.modpow(&BigUint::from(65537u32), &signer_n).to_bytes_be();
+        assert_eq!(&em[em.len() - 32..], Sha256::digest(&parts.signed_attrs).as_slice());
+
+        assert!(parse_signed_data(&signed_data()[..100]).is_err());
+        let cert = std::fs::read_to_string(fixture_path("./certs/cert_3.pem")).unwrap();
+        assert!(parse_signed_data(cert.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_pkcs7_signature_verification() {
+        // The second path's key is the one returned, so this is the signer's own modulus
+        let (signer_n, _, _) = load_fixture("./certs/san_leaf.pem", "./certs/san_leaf.pem");
+        let parts = parse_signed_data(&signed_data()).unwrap();
+        let circuit = Pkcs7SignatureCircuit::<Fr>::new(signer_n.clone(), parts.clone(), content(), CircuitParams::default());
+        circuit.params.set_current();
+        let prover = MockProver::run(circuit.params.k, &circuit, circuit.public_inputs()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // Another key doesn't verify the same attributes
+        let (other_n, _, _) = load_fixture("./certs/san_leaf.pem", "./certs/test_ca.pem");
+        let circuit = Pkcs7SignatureCircuit::<Fr>::new(other_n, parts, content(), CircuitParams::default());
+        let prover = MockProver::run(circuit.params.k, &circuit, circuit.public_inputs()).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    /// DER contents of the contentType (1.2.840.113549.1.9.3) attribute OID.
+    const OID_CONTENT_TYPE: [u8; 9] = [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x03];
+    /// DER contents of the Authenticode SpcIndirectDataContent (1.3.6.1.4.1.311.2.1.4) OID.
+    const OID_SPC_INDIRECT_DATA: [u8; 10] = [0x2b, 0x06, 0x01, 0x04, 0x01, 0x82, 0x37, 0x02, 0x01, 0x04];
+
+    /// Authenticode-style signed attributes, contentType SpcIndirectDataContent and a messageDigest
+    /// of `content_digest`, signed with test_ca's key. Returns the signer modulus with them.
+    fn indirect_signer_info(content_digest: &[u8; 32]) -> (BigUint, SignerInfoParts) {
+        let content_type_value = encode_tlv(TAG_SET, &encode_tlv(TAG_OID, &OID_SPC_INDIRECT_DATA));
+        let content_type = encode_tlv(TAG_SEQUENCE, &[encode_tlv(TAG_OID, &OID_CONTENT_TYPE), content_type_value].concat());
+        let message_digest = [MESSAGE_DIGEST_PREFIX.as_slice(), content_digest].concat();
+        // SET OF sorts by encoding, and `30 19` comes before `30 2f`
+        let signed_attrs = encode_tlv(TAG_SET, &[content_type, message_digest].concat());
+
+        let key = openssl::rsa::Rsa::private_key_from_pem(&std::fs::read(fixture_path("./certs/test_ca.key")).unwrap()).unwrap();
+        let digest_info = [crate::SHA256_DIGEST_INFO_PREFIX.as_slice(), &Sha256::digest(&signed_attrs)].concat();
+        let mut signature = vec![0; key.size() as usize];
+        let len = key.private_encrypt(&digest_info, &mut signature, openssl::rsa::Padding::PKCS1).unwrap();
+        signature.truncate(len);
+        let n_big = BigUint::from_bytes_be(&key.n().to_vec());
+        (n_big, SignerInfoParts { signed_attrs, signature: BigUint::from_bytes_be(&signature) })
+    }
+
+    #[test]
+    fn test_verify_signed_attributes() {
+        let content_digest: [u8; 32] = Sha256::digest(b"SpcIndirectDataContent of a signed file").into();
+        let (n_big, parts) = indirect_signer_info(&content_digest);
+        assert!(find_message_digest(&parts.signed_attrs).is_some());
+        assert_eq!(verify_signed_attributes(&parts.signed_attrs, &content_digest, &n_big, &parts.signature), Ok(()));
+
+        let mut other_digest = content_digest;
+        other_digest[0] ^= 0x01;
+        assert_eq!(
+            verify_signed_attributes(&parts.signed_attrs, &other_digest, &n_big, &parts.signature),
+            Err(CertError::ContentDigestMismatch)
+        );
+        let (other_n, _, _) = load_fixture("./certs/san_leaf.pem", "./certs/san_leaf.pem");
+        assert_eq!(
+            verify_signed_attributes(&parts.signed_attrs, &content_digest, &other_n, &parts.signature),
+            Err(CertError::InvalidSignature)
+        );
+
+        let circuit = Pkcs7SignatureCircuit::<Fr>::from_content_digest(n_big, parts, content_digest, CircuitParams::default());
+        circuit.params.set_current();
+        let public_inputs = circuit.public_inputs();
+        assert_eq!(public_inputs[2], content_digest.iter().map(|byte| Fr::from(*byte as u64)).collect::<Vec<_>>());
+        let prover = MockProver::run(circuit.params.k, &circuit, public_inputs.clone()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let mut wrong = public_inputs;
+        wrong[2][0] += Fr::from(1);
+        let prover = MockProver::run(circuit.params.k, &circuit, wrong).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_message_digest_link() {
+        let parts = parse_signed_data(&signed_data()).unwrap();
+        let offset = find_message_digest(&parts.signed_attrs).unwrap();
+        let digest = &parts.signed_attrs[offset + MESSAGE_DIGEST_PREFIX.len()..offset + 49];
+        assert_eq!(digest, Sha256::digest(content()).as_slice());
+
+        // The signature still verifies over the untouched attributes and the content digest instance
+        // follows the tampered content, so only the messageDigest link catches this
+        let (signer_n, _, _) = load_fixture("./certs/san_leaf.pem", "./certs/san_leaf.pem");
+        let mut tampered = content();
+        tampered[0] ^= 0x01;
+        let circuit = Pkcs7SignatureCircuit::<Fr>::new(signer_n, parts, tampered, CircuitParams::default());
+        circuit.params.set_current();
+        let prover = MockProver::run(circuit.params.k, &circuit, circuit.public_inputs()).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
@@ -0,0 +1,592 @@
+//! Real proving and verification with KZG (SHPLONK) over bn256.
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+
+use halo2_base::halo2_proofs::{
+    halo2curves::{
+        bn256::{Bn256, Fr, G1Affine},
+        group::ff::PrimeField,
+    },
+    plonk::{create_proof, keygen_pk, keygen_vk, verify_proof, Error, ProvingKey, VerifyingKey},
+    poly::{
+        commitment::{Params, ParamsProver},
+        kzg::{
+            commitment::{KZGCommitmentScheme, ParamsKZG},
+            multiopen::{ProverSHPLONK, VerifierSHPLONK},
+            strategy::SingleStrategy,
+        },
+    },
+    transcript::{Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer},
+};
+use num_bigint::BigUint;
+use rand::{rngs::OsRng, RngCore};
+
+use crate::{CertificateVerificationCircuit, CircuitParams};
+
+/// Loads KZG params for `2^k` rows from `path`, or generates them and writes them there.
+///
+/// Generated params come from a local, insecure setup; only use them for testing.
+pub fn load_or_generate_params(k: u32, path: impl AsRef<Path>) -> io::Result<ParamsKZG<Bn256>> {
+    let path = path.as_ref();
+    if path.exists() {
+        let params = ParamsKZG::<Bn256>::read(&mut BufReader::new(File::open(path)?))?;
+        if params.k() != k {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} holds params for k = {}, not {k}", path.display(), params.k()),
+            ));
+        }
+        return Ok(params);
+    }
+    let params = ParamsKZG::<Bn256>::setup(k, OsRng);
+    let mut writer = BufWriter::new(File::create(path)?);
+    params.write(&mut writer)?;
+    writer.flush()?;
+    Ok(params)
+}
+
+/// Proving key for circuits shaped like `circuit`. Fails with `Error::Synthesis` unless `params`
+/// are for the `circuit.params.rows()` rows the circuit is configured for: keygen itself only
+/// rejects params too small to hold the circuit.
+pub fn keygen(
+    params: &ParamsKZG<Bn256>,
+    circuit: &CertificateVerificationCircuit<Fr>,
+) -> Result<ProvingKey<G1Affine>, Error> {
+    if params.k() != circuit.params.k {
+        return Err(Error::Synthesis);
+    }
+    circuit.params.set_current();
+    let vk = keygen_vk(params, circuit)?;
+    keygen_pk(params, vk, circuit)
+}
+
+/// Proves `circuit` like `prove` and returns the proof with its public inputs and verifying key
+/// digest as JSON, see `ProofBundle::to_json`.
+pub fn prove_and_export_json(
+    params: &ParamsKZG<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    circuit: &CertificateVerificationCircuit<Fr>,
+) -> Result<String, Error> {
+    let proof = prove(params, pk, circuit)?;
+    Ok(ProofBundle::new(pk.get_vk(), circuit.public_inputs(), proof).to_json())
+}
+
+/// Proves `circuit` against its own `public_inputs()`.
+pub fn prove(
+    params: &ParamsKZG<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    circuit: &CertificateVerificationCircuit<Fr>,
+) -> Result<Vec<u8>, Error> {
+    create_proof_with_rng(params, pk, circuit, OsRng)
+}
+
+/// Like `prove`, with the proof's blinding randomness drawn from `rng` rather than `OsRng`. A
+/// seeded `rng` makes proofs byte-for-byte reproducible, for regression tests; such proofs aren't
+/// zero-knowledge, so this is only exposed to tests.
+#[cfg(any(test, feature = "test-utils"))]
+pub fn prove_with_rng(
+    params: &ParamsKZG<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    circuit: &CertificateVerificationCircuit<Fr>,
+    rng: impl RngCore,
+) -> Result<Vec<u8>, Error> {
+    create_proof_with_rng(params, pk, circuit, rng)
+}
+
+fn create_proof_with_rng(
+    params: &ParamsKZG<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    circuit: &CertificateVerificationCircuit<Fr>,
+    rng: impl RngCore,
+) -> Result<Vec<u8>, Error> {
+    circuit.params.set_current();
+    let public_inputs = circuit.public_inputs();
+    let instances = public_inputs.iter().map(|column| column.as_slice()).collect::<Vec<_>>();
+    let mut transcript = Blake2bWrite::<_, G1Affine, Challenge255<_>>::init(vec![]);
+    create_proof::<
+        KZGCommitmentScheme<Bn256>,
+        ProverSHPLONK<'_, Bn256>,
+        Challenge255<G1Affine>,
+        _,
+        Blake2bWrite<Vec<u8>, G1Affine, Challenge255<G1Affine>>,
+        _,
+    >(params, pk, std::slice::from_ref(circuit), &[&instances], rng, &mut transcript)?;
+    Ok(transcript.finalize())
+}
+
+/// Params and proving key kept warm across proofs, for long-running provers: keygen runs once,
+/// and each proof skips reloading the SHA256 and range lookup tables.
+///
+/// Skipping the tables is sound because they are fixed columns: `create_proof` takes fixed columns
+/// from the proving key and ignores fixed assignments made during synthesis. Keygen and
+/// `MockProver` do read them, so circuits handed to `prove` here must not be reused for either.
+/// The context is tied to the `CircuitParams` it was built with and rejects circuits with others.
+pub struct ProvingContext {
+    params: ParamsKZG<Bn256>,
+    pk: ProvingKey<G1Affine>,
+    circuit_params: CircuitParams,
+}
+
+impl ProvingContext {
+    /// Runs keygen for circuits shaped like `circuit`.
+    pub fn new(params: ParamsKZG<Bn256>, circuit: &CertificateVerificationCircuit<Fr>) -> Result<Self, Error> {
+        let pk = keygen(&params, circuit)?;
+        Ok(Self { params, pk, circuit_params: circuit.params.clone() })
+    }
+
+    pub fn params(&self) -> &ParamsKZG<Bn256> {
+        &self.params
+    }
+
+    pub fn vk(&self) -> &VerifyingKey<G1Affine> {
+        self.pk.get_vk()
+    }
+
+    /// Proves `circuit` against its own `public_inputs()`. Fails with `Error::Synthesis` if it was
+    /// built with other `CircuitParams` than the context.
+    pub fn prove(&self, mut circuit: CertificateVerificationCircuit<Fr>) -> Result<Vec<u8>, Error> {
+        if circuit.params != self.circuit_params {
+            return Err(Error::Synthesis);
+        }
+        circuit.load_tables = false;
+        prove(&self.params, &self.pk, &circuit)
+    }
+}
+
+pub fn verify(
+    params: &ParamsKZG<Bn256>,
+    vk: &VerifyingKey<G1Affine>,
+    public_inputs: &[Vec<Fr>],
+    proof: &[u8],
+) -> Result<(), Error> {
+    verify_from_reader(params, vk, proof, public_inputs)
+}
+
+/// Like `verify`, with public inputs in the flat canonical order of
+/// `CertificateVerificationCircuit::flatten_public_inputs` for `circuit_params`.
+pub fn verify_flat(
+    params: &ParamsKZG<Bn256>,
+    vk: &VerifyingKey<G1Affine>,
+    circuit_params: &CircuitParams,
+    flat_inputs: &[Fr],
+    proof: &[u8],
+) -> Result<(), Error> {
+    let public_inputs = circuit_params.reshape_public_inputs(flat_inputs).map_err(|_| Error::InvalidInstances)?;
+    verify(params, vk, &public_inputs, proof)
+}
+
+/// Like `verify`, but reads the proof through the transcript as it goes instead of
+/// needing it in memory up front.
+pub fn verify_from_reader(
+    params: &ParamsKZG<Bn256>,
+    vk: &VerifyingKey<G1Affine>,
+    reader: impl Read,
+    public_inputs: &[Vec<Fr>],
+) -> Result<(), Error> {
+    let instances = public_inputs.iter().map(|column| column.as_slice()).collect::<Vec<_>>();
+    let mut transcript = Blake2bRead::<_, G1Affine, Challenge255<_>>::init(reader);
+    verify_proof::<
+        KZGCommitmentScheme<Bn256>,
+        VerifierSHPLONK<'_, Bn256>,
+        Challenge255<G1Affine>,
+        Blake2bRead<_, G1Affine, Challenge255<G1Affine>>,
+        SingleStrategy<'_, Bn256>,
+    >(params.verifier_params(), vk, SingleStrategy::new(params), &[&instances], &mut transcript)
+}
+
+/// Verifies untrusted proofs with a time limit, for services. Verification runs on worker threads
+/// that share `params` and `vk`, at most `max_workers` at once. A worker can't be cancelled, so
+/// after a timeout it runs to completion in the background, holding its slot until then; while
+/// every slot is taken, new proofs are rejected rather than queued.
+pub struct TimeoutVerifier {
+    params: Arc<ParamsKZG<Bn256>>,
+    vk: Arc<VerifyingKey<G1Affine>>,
+    max_workers: usize,
+    busy: Arc<AtomicUsize>,
+}
+
+/// A taken worker slot of a `TimeoutVerifier`, given back when dropped, panics included.
+struct WorkerSlot(Arc<AtomicUsize>);
+
+impl Drop for WorkerSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+impl TimeoutVerifier {
+    pub fn new(params: Arc<ParamsKZG<Bn256>>, vk: Arc<VerifyingKey<G1Affine>>, max_workers: usize) -> Self {
+        Self { params, vk, max_workers, busy: Arc::new(AtomicUsize::new(0)) }
+    }
+
+    /// Like `verify`, but gives up after `timeout`. Fails with a transcript error of kind
+    /// `io::ErrorKind::TimedOut` on timeout, `WouldBlock` if every worker is busy, and `Other` if
+    /// verification panics.
+    pub fn verify(&self, public_inputs: &[Vec<Fr>], proof: &[u8], timeout: Duration) -> Result<(), Error> {
+        let transcript_error = |kind, msg: String| Error::Transcript(io::Error::new(kind, msg));
+        let taken = self.busy.fetch_update(Ordering::AcqRel, Ordering::Acquire, |busy| (busy < self.max_workers).then(|| busy + 1));
+        if taken.is_err() {
+            return Err(transcript_error(io::ErrorKind::WouldBlock, format!("all {} verification workers are busy", self.max_workers)));
+        }
+        let slot = WorkerSlot(self.busy.clone());
+        let (params, vk, public_inputs, proof) = (self.params.clone(), self.vk.clone(), public_inputs.to_vec(), proof.to_vec());
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let _slot = slot;
+            // The receiver is gone after a timeout, which is fine
+            let _ = sender.send(verify(&params, &vk, &public_inputs, &proof));
+        });
+        match receiver.recv_timeout(timeout) {
+            Ok(result) => result,
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                Err(transcript_error(io::ErrorKind::TimedOut, format!("verification took longer than {timeout:?}")))
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                Err(transcript_error(io::ErrorKind::Other, "verification panicked".to_string()))
+            }
+        }
+    }
+}
+
+const BUNDLE_MAGIC: [u8; 4] = *b"ZKCB";
+const BUNDLE_VERSION: u8 = 1;
+
+/// Identifies a verifying key: its transcript representation, the hash of the pinned key
+/// (domain, constraint system, fixed and permutation commitments) that seeds every transcript.
+pub fn vk_digest(vk: &VerifyingKey<G1Affine>) -> [u8; 32] {
+    vk.transcript_repr().to_repr()
+}
+
+/// A proof with its public inputs and the digest of the verifying key it was made for, in a
+/// single file so they can't be mixed with artifacts of other circuits.
+///
+/// Layout: the magic `ZKCB`, a version byte, then the digest, the public inputs and the proof, each
+/// prefixed with its length in bytes as a little-endian `u32`. Public inputs are a `u32` column
+/// count, then per column a `u32` length and 32-byte little-endian field elements.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProofBundle {
+    pub public_inputs: Vec<Vec<Fr>>,
+    pub proof: Vec<u8>,
+    pub vk_digest: [u8; 32],
+}
+
+impl ProofBundle {
+    pub fn new(vk: &VerifyingKey<G1Affine>, public_inputs: Vec<Vec<Fr>>, proof: Vec<u8>) -> Self {
+        Self { public_inputs, proof, vk_digest: vk_digest(vk) }
+    }
+
+    pub fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        let mut public_inputs = (self.public_inputs.len() as u32).to_le_bytes().to_vec();
+        for column in &self.public_inputs {
+            public_inputs.extend((column.len() as u32).to_le_bytes());
+            public_inputs.extend(column.iter().flat_map(|value| value.to_repr()));
+        }
+        writer.write_all(&BUNDLE_MAGIC)?;
+        writer.write_all(&[BUNDLE_VERSION])?;
+        for section in [&self.vk_digest[..], &public_inputs[..], &self.proof[..]] {
+            writer.write_all(&(section.len() as u32).to_le_bytes())?;
+            writer.write_all(section)?;
+        }
+        writer.flush()
+    }
+
+    /// The bundle as a JSON object, for tooling in other languages:
+    ///
+    /// ```json
+    /// {"version": 1, "vk_digest": "<hex>", "proof": "<hex>", "public_inputs": [["<decimal>", ..], ..]}
+    /// ```
+    ///
+    /// `vk_digest` and `proof` are lowercase hex without a `0x` prefix. `public_inputs` holds one
+    /// array per instance column, in `CircuitParams::instance_columns` order, of canonical field
+    /// elements as decimal strings.
+    pub fn to_json(&self) -> String {
+        let hex = |bytes: &[u8]| bytes.iter().map(|byte| format!("{byte:02x}")).collect::<String>();
+        let columns = self
+            .public_inputs
+            .iter()
+            .map(|column| {
+                let values = column.iter().map(|value| format!("\"{}\"", BigUint::from_bytes_le(value.to_repr().as_ref())));
+                format!("[{}]", values.collect::<Vec<_>>().join(","))
+            })
+            .collect::<Vec<_>>();
+        format!(
+            "{{\"version\":{BUNDLE_VERSION},\"vk_digest\":\"{}\",\"proof\":\"{}\",\"public_inputs\":[{}]}}",
+            hex(&self.vk_digest),
+            hex(&self.proof),
+            columns.join(",")
+        )
+    }
+
+    /// Reads a bundle written by `write`, failing with `InvalidData` if it is malformed or was made
+    /// for another verifying key than `vk`.
+    pub fn read(mut reader: impl Read, vk: &VerifyingKey<G1Affine>) -> io::Result<Self> {
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, format!("proof bundle: {msg}"));
+        let mut header = [0u8; 5];
+        reader.read_exact(&mut header)?;
+        if header[..4] != BUNDLE_MAGIC {
+            return Err(invalid("bad magic"));
+        }
+        if header[4] != BUNDLE_VERSION {
+            return Err(invalid(&format!("unsupported version {}", header[4])));
+        }
+
+        let digest_section = read_section(&mut reader)?;
+        let vk_digest: [u8; 32] = digest_section.try_into().map_err(|_| invalid("vk digest is not 32 bytes"))?;
+        if vk_digest != self::vk_digest(vk) {
+            return Err(invalid("made for another verifying key"));
+        }
+
+        let public_inputs_section = read_section(&mut reader)?;
+        let mut rest = public_inputs_section.as_slice();
+        let truncated = || invalid("truncated public inputs");
+        let num_columns = take_u32(&mut rest).ok_or_else(truncated)?;
+        let mut public_inputs = vec![];
+        for _ in 0..num_columns {
+            let len = take_u32(&mut rest).ok_or_else(truncated)?;
+            let column = take(&mut rest, len * 32)
+                .ok_or_else(truncated)?
+                .chunks(32)
+                .map(|repr| Option::from(Fr::from_repr(repr.try_into().unwrap())).ok_or_else(|| invalid("non-canonical field element")))
+                .collect::<io::Result<Vec<_>>>()?;
+            public_inputs.push(column);
+        }
+        if !rest.is_empty() {
+            return Err(invalid("trailing bytes after public inputs"));
+        }
+
+        let proof = read_section(&mut reader)?;
+        Ok(Self { public_inputs, proof, vk_digest })
+    }
+}
+
+/// Splits `len` bytes off the front of `rest`.
+fn take<'a>(rest: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+    if rest.len() < len {
+        return None;
+    }
+    let (head, tail) = rest.split_at(len);
+    *rest = tail;
+    Some(head)
+}
+
+fn take_u32(rest: &mut &[u8]) -> Option<usize> {
+    take(rest, 4).map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()) as usize)
+}
+
+/// Longest section `ProofBundle::read` accepts. Proofs of these circuits are a few KB and their
+/// public inputs less, so this only bounds what a crafted length prefix can make it allocate.
+const MAX_SECTION_LEN: usize = 1 << 24;
+
+fn read_section(reader: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut len = [0u8; 4];
+    reader.read_exact(&mut len)?;
+    let len = u32::from_le_bytes(len) as usize;
+    if len > MAX_SECTION_LEN {
+        let msg = format!("proof bundle: {len}-byte section, longer than {MAX_SECTION_LEN}");
+        return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+    }
+    // Grown as bytes arrive, so a length past the end of the input allocates no more than the input
+    let mut section = vec![];
+    reader.by_ref().take(len as u64).read_to_end(&mut section)?;
+    if section.len() < len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(section)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::load_fixture;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_load_or_generate_params() {
+        let path = std::env::temp_dir().join(format!("zkcert_params_k4_{}.bin", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let generated = load_or_generate_params(4, &path).unwrap();
+        assert!(path.exists());
+        // Setup is randomized, so equal params mean the second call read the cache
+        let loaded = load_or_generate_params(4, &path).unwrap();
+        let (mut generated_bytes, mut loaded_bytes) = (vec![], vec![]);
+        generated.write(&mut generated_bytes).unwrap();
+        loaded.write(&mut loaded_bytes).unwrap();
+        assert_eq!(generated_bytes, loaded_bytes);
+
+        assert_eq!(load_or_generate_params(5, &path).err().map(|e| e.kind()), Some(io::ErrorKind::InvalidData));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_proving_context() {
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let new_circuit = || CertificateVerificationCircuit::<Fr>::new(n_big.clone(), sign_big.clone(), tbs.clone(), CircuitParams::default());
+        let circuit = new_circuit();
+        let context = ProvingContext::new(ParamsKZG::<Bn256>::setup(circuit.params.k, OsRng), &circuit).unwrap();
+        for _ in 0..2 {
+            let proof = context.prove(new_circuit()).unwrap();
+            assert!(verify(context.params(), context.vk(), &circuit.public_inputs(), &proof).is_ok());
+        }
+
+        let other_params = CircuitParams { hide_digest: true, ..Default::default() };
+        let other = CertificateVerificationCircuit::<Fr>::new(n_big.clone(), sign_big.clone(), tbs.clone(), other_params);
+        assert!(context.prove(other).is_err());
+    }
+
+    #[test]
+    fn test_keygen_k_mismatch() {
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big, sign_big, tbs, CircuitParams::default());
+        let params = ParamsKZG::<Bn256>::setup(circuit.params.k + 1, OsRng);
+        assert!(matches!(keygen(&params, &circuit), Err(Error::Synthesis)));
+    }
+
+    #[test]
+    fn test_seeded_proofs_identical() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big, sign_big, tbs, CircuitParams::default());
+        let params = ParamsKZG::<Bn256>::setup(circuit.params.k, OsRng);
+        let pk = keygen(&params, &circuit).unwrap();
+        let proofs = [42, 42, 43].map(|seed| prove_with_rng(&params, &pk, &circuit, StdRng::seed_from_u64(seed)).unwrap());
+        assert_eq!(proofs[0], proofs[1]);
+        assert_ne!(proofs[0], proofs[2]);
+        assert!(verify(&params, pk.get_vk(), &circuit.public_inputs(), &proofs[0]).is_ok());
+    }
+
+    #[test]
+    fn test_verify_from_reader() {
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big, sign_big, tbs, CircuitParams::default());
+        let params = ParamsKZG::<Bn256>::setup(circuit.params.k, OsRng);
+        let pk = keygen(&params, &circuit).unwrap();
+        let proof = prove(&params, &pk, &circuit).unwrap();
+        let public_inputs = circuit.public_inputs();
+
+        assert!(verify(&params, pk.get_vk(), &public_inputs, &proof).is_ok());
+        assert!(verify_from_reader(&params, pk.get_vk(), Cursor::new(proof.clone()), &public_inputs).is_ok());
+        let flat = circuit.flatten_public_inputs();
+        assert!(verify_flat(&params, pk.get_vk(), &circuit.params, &flat, &proof).is_ok());
+        assert!(verify_flat(&params, pk.get_vk(), &circuit.params, &flat[1..], &proof).is_err());
+
+        let mut wrong_inputs = public_inputs;
+        wrong_inputs[1][0] += Fr::from(1u64);
+        assert!(verify_from_reader(&params, pk.get_vk(), Cursor::new(proof), &wrong_inputs).is_err());
+    }
+
+    #[test]
+    fn test_timeout_verifier() {
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big, sign_big, tbs, CircuitParams::default());
+        let params = ParamsKZG::<Bn256>::setup(circuit.params.k, OsRng);
+        let pk = keygen(&params, &circuit).unwrap();
+        let proof = prove(&params, &pk, &circuit).unwrap();
+        let public_inputs = circuit.public_inputs();
+        let verifier = TimeoutVerifier::new(Arc::new(params), Arc::new(pk.get_vk().clone()), 1);
+        let kind = |result: Result<(), Error>| match result {
+            Err(Error::Transcript(err)) => Some(err.kind()),
+            _ => None,
+        };
+
+        let timeout = Duration::from_secs(60);
+        assert!(verifier.verify(&public_inputs, &proof, timeout).is_ok());
+        let mut wrong_inputs = public_inputs.clone();
+        wrong_inputs[1][0] += Fr::from(1u64);
+        assert!(verifier.verify(&wrong_inputs, &proof, timeout).is_err());
+
+        // The timed out worker keeps the only slot until it finishes
+        assert_eq!(kind(verifier.verify(&public_inputs, &proof, Duration::ZERO)), Some(io::ErrorKind::TimedOut));
+        assert_eq!(kind(verifier.verify(&public_inputs, &proof, timeout)), Some(io::ErrorKind::WouldBlock));
+        while verifier.busy.load(Ordering::Acquire) > 0 {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(verifier.verify(&public_inputs, &proof, timeout).is_ok());
+    }
+
+    #[test]
+    fn test_proof_bundle_round_trip() {
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big, sign_big, tbs, CircuitParams::default());
+        let params = ParamsKZG::<Bn256>::setup(circuit.params.k, OsRng);
+        let pk = keygen(&params, &circuit).unwrap();
+        let proof = prove(&params, &pk, &circuit).unwrap();
+        let bundle = ProofBundle::new(pk.get_vk(), circuit.public_inputs(), proof);
+
+        let mut bytes = vec![];
+        bundle.write(&mut bytes).unwrap();
+        let read = ProofBundle::read(bytes.as_slice(), pk.get_vk()).unwrap();
+        assert_eq!(read, bundle);
+        assert!(verify(&params, pk.get_vk(), &read.public_inputs, &read.proof).is_ok());
+
+        assert!(ProofBundle::read(&bytes[..bytes.len() - 1], pk.get_vk()).is_err());
+        bytes[0] ^= 0xff;
+        assert_eq!(ProofBundle::read(bytes.as_slice(), pk.get_vk()).err().map(|e| e.kind()), Some(io::ErrorKind::InvalidData));
+    }
+
+    #[test]
+    fn test_read_section_length_prefix() {
+        let section = |len: u32, contents: &[u8]| [&len.to_le_bytes()[..], contents].concat();
+        assert_eq!(read_section(&mut section(3, &[1, 2, 3, 4]).as_slice()).unwrap(), vec![1, 2, 3]);
+
+        let oversized = section(u32::MAX, &[0; 32]);
+        assert_eq!(read_section(&mut oversized.as_slice()).err().map(|e| e.kind()), Some(io::ErrorKind::InvalidData));
+        let past_end = section(MAX_SECTION_LEN as u32, &[0; 32]);
+        assert_eq!(read_section(&mut past_end.as_slice()).err().map(|e| e.kind()), Some(io::ErrorKind::UnexpectedEof));
+    }
+
+    #[test]
+    fn test_prove_and_export_json() {
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big, sign_big, tbs, CircuitParams::default());
+        let params = ParamsKZG::<Bn256>::setup(circuit.params.k, OsRng);
+        let pk = keygen(&params, &circuit).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&prove_and_export_json(&params, &pk, &circuit).unwrap()).unwrap();
+
+        let unhex = |hex: &str| (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap()).collect::<Vec<_>>();
+        assert_eq!(json["version"], 1);
+        assert_eq!(unhex(json["vk_digest"].as_str().unwrap()), vk_digest(pk.get_vk()));
+        let proof = unhex(json["proof"].as_str().unwrap());
+        let public_inputs = json["public_inputs"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|column| {
+                let column = column.as_array().unwrap();
+                column.iter().map(|value| Fr::from_str_vartime(value.as_str().unwrap()).unwrap()).collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(public_inputs, circuit.public_inputs());
+        // The modulus limbs are 64-bit, so their decimal strings are plain integers
+        assert_eq!(json["public_inputs"][0][0].as_str().unwrap().parse::<u64>().ok().map(Fr::from), Some(public_inputs[0][0]));
+        assert!(verify(&params, pk.get_vk(), &public_inputs, &proof).is_ok());
+    }
+
+    #[test]
+    fn test_proof_bundle_rejects_other_vk() {
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big.clone(), sign_big.clone(), tbs.clone(), CircuitParams::default());
+        let params = ParamsKZG::<Bn256>::setup(circuit.params.k, OsRng);
+        circuit.params.set_current();
+        let vk = keygen_vk(&params, &circuit).unwrap();
+
+        let other_params = CircuitParams { hide_digest: true, ..Default::default() };
+        let other = CertificateVerificationCircuit::<Fr>::new(n_big, sign_big, tbs, other_params);
+        other.params.set_current();
+        let other_vk = keygen_vk(&params, &other).unwrap();
+        assert_ne!(vk_digest(&vk), vk_digest(&other_vk));
+
+        // Only the digest is checked on load, so a placeholder proof will do
+        let bundle = ProofBundle::new(&vk, circuit.public_inputs(), vec![0; 64]);
+        let mut bytes = vec![];
+        bundle.write(&mut bytes).unwrap();
+        assert!(ProofBundle::read(bytes.as_slice(), &vk).is_ok());
+        assert_eq!(ProofBundle::read(bytes.as_slice(), &other_vk).err().map(|e| e.kind()), Some(io::ErrorKind::InvalidData));
+    }
+}
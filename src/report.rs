@@ -0,0 +1,104 @@
+//! What a verification establishes about a certificate, gathered in one place for callers.
+use halo2_base::utils::PrimeField;
+use num_bigint::BigUint;
+use x509_parser::{certificate::TbsCertificate, prelude::FromDer};
+
+use crate::{CertError, CertificateVerificationCircuit};
+
+/// Everything a proof of a `CertificateVerificationCircuit` authenticates, computed natively.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerificationReport {
+    /// Whether the signature verifies, i.e. what the circuit constrains or exposes as `valid`.
+    pub signature_valid: bool,
+    /// The digest instance: SHA-256 of the message, or of the whole certificate with
+    /// `signed_range`.
+    pub digest: [u8; 32],
+    /// Bit length of the issuer modulus the signature is checked under.
+    pub modulus_bits: u64,
+    /// First commonName of the subject, if any.
+    pub subject_cn: Option<String>,
+    /// Unix timestamps of the validity period.
+    pub not_before: i64,
+    pub not_after: i64,
+    /// Number of signatures checked, from the certificate up: 1 for a single certificate.
+    pub chain_depth: usize,
+}
+
+/// What `ChainCircuit::verify_chain_report` finds about one certificate of a chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainLinkReport {
+    /// First commonName of the subject, if any.
+    pub subject_cn: Option<String>,
+    /// Whether the signature verifies under the issuer key: the SPKI of the next certificate, or
+    /// the anchor for the last one.
+    pub signature_valid: bool,
+    /// Whether the next certificate names this one's issuer, with matching key identifiers, and
+    /// has an RSA SPKI the circuit can take the key from. For a self-signed root, whether the
+    /// anchor is its own key; always true for an anchor that is only public.
+    pub key_linked: bool,
+    /// Whether the time the report was made for falls within the validity period.
+    pub within_validity: bool,
+}
+
+impl ChainLinkReport {
+    pub fn is_ok(&self) -> bool {
+        self.signature_valid && self.key_linked && self.within_validity
+    }
+}
+
+impl<F: PrimeField> CertificateVerificationCircuit<F> {
+    /// The `VerificationReport` of this circuit's certificate. An invalid signature is reported
+    /// through `signature_valid`, not as an error; errors mean the signed message isn't a TBS
+    /// certificate the fields can be read from.
+    pub fn verify_report(&self) -> Result<VerificationReport, CertError> {
+        let (_, tbs) = TbsCertificate::from_der(self.tbs())
+            .map_err(|_| CertError::MalformedStructure("signed message is not a tbsCertificate"))?;
+        let subject_cn = tbs.subject().iter_common_name().next().and_then(|cn| cn.as_str().ok()).map(String::from);
+        let n_big: &BigUint = self.params.fixed_modulus.as_ref().unwrap_or(&self.n_big);
+        Ok(VerificationReport {
+            signature_valid: self.signature_is_valid(),
+            digest: self.msg_digest(),
+            modulus_bits: n_big.bits(),
+            subject_cn,
+            not_before: tbs.validity().not_before.timestamp(),
+            not_after: tbs.validity().not_after.timestamp(),
+            chain_depth: 1,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::load_fixture;
+    use crate::CircuitParams;
+    use halo2_base::halo2_proofs::halo2curves::bn256::Fr;
+    use sha2::{Digest, Sha256};
+
+    #[test]
+    fn test_verify_report() {
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big, sign_big.clone(), tbs.clone(), CircuitParams::default());
+        assert_eq!(
+            circuit.verify_report(),
+            Ok(VerificationReport {
+                signature_valid: true,
+                digest: Sha256::digest(&tbs).into(),
+                modulus_bits: 2048,
+                subject_cn: Some("DocuSign, Inc.".to_string()),
+                not_before: 1596844071,
+                not_after: 1671580070,
+                chain_depth: 1,
+            })
+        );
+
+        // cert_1's key didn't sign it
+        let (other_n, _, _) = load_fixture("./certs/cert_2.pem", "./certs/cert_1.pem");
+        let circuit = CertificateVerificationCircuit::<Fr>::new(other_n, sign_big.clone(), tbs, CircuitParams::default());
+        assert!(!circuit.verify_report().unwrap().signature_valid);
+
+        let (n_big, _, _) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let circuit = CertificateVerificationCircuit::<Fr>::new(n_big, sign_big, b"not a certificate".to_vec(), CircuitParams::default());
+        assert!(circuit.verify_report().is_err());
+    }
+}
@@ -0,0 +1,162 @@
+//! The RSA modular exponentiation on its own, for profiling the most expensive gadget apart from
+//! SHA256 and the PKCS#1 v1.5 checks.
+use halo2_base::{
+    gates::RangeInstructions,
+    utils::PrimeField,
+    halo2_proofs::{
+        plonk::{Circuit, ConstraintSystem, Error, Column, Instance},
+        circuit::{SimpleFloorPlanner, Layouter, Value, Cell},
+    },
+    SKIP_FIRST_PASS
+};
+use halo2_rsa::{RSAConfig, RSAInstructions, RSAPubE, RSAPublicKey, RSASignature};
+use num_bigint::BigUint;
+
+use crate::{assign_modpow, configure_rsa, modulus_instance, CircuitParams, CircuitStats};
+
+/// Computes `s^e mod n` for a `params.bits_len`-bit `n`. Public inputs are `[n limbs, result
+/// limbs]`, both as in `modulus_instance`.
+///
+/// `e` is a constant of the circuit, like `CircuitParams::public_exponent` without
+/// `variable_exponent`, so each exponent has its own verifying key. Only `bits_len`, `limb_bits`,
+/// `exp_limb_bits`, `montgomery_exp` and the range settings of `params` apply.
+pub struct RsaExpCircuit<F: PrimeField> {
+    n: BigUint,
+    s: BigUint,
+    e: BigUint,
+    params: CircuitParams,
+    _f: std::marker::PhantomData<F>,
+}
+
+impl<F: PrimeField> RsaExpCircuit<F> {
+    pub fn new(n: BigUint, s: BigUint, e: BigUint, params: CircuitParams) -> Self {
+        Self { n, s, e, params, _f: std::marker::PhantomData }
+    }
+
+    /// `s^e mod n`, computed natively.
+    pub fn result(&self) -> BigUint {
+        self.s.modpow(&self.e, &self.n)
+    }
+
+    pub fn public_inputs(&self) -> Vec<Vec<F>> {
+        vec![modulus_instance(&self.n, &self.params), modulus_instance(&self.result(), &self.params)]
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RsaExpConfig<F: PrimeField> {
+    rsa_config: RSAConfig<F>,
+    n_instance: Column<Instance>,
+    result_instance: Column<Instance>,
+}
+
+impl<F: PrimeField> Circuit<F> for RsaExpCircuit<F> {
+    type Config = RsaExpConfig<F>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        unimplemented!();
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let params = CircuitParams::current();
+        let rsa_config = configure_rsa(meta, &params);
+        let n_instance = meta.instance_column();
+        meta.enable_equality(n_instance);
+        let result_instance = meta.instance_column();
+        meta.enable_equality(result_instance);
+
+        Self::Config {
+            rsa_config,
+            n_instance,
+            result_instance
+        }
+    }
+
+    fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+        let biguint_config = config.rsa_config.biguint_config();
+        biguint_config.range().load_lookup_table(&mut layouter)?;
+        let mut first_pass = SKIP_FIRST_PASS;
+        let (n_cells, result_cells) = layouter.assign_region(
+            || "rsa modular exponentiation",
+            |region| {
+                if first_pass {
+                    first_pass = false;
+                    return Ok((vec![], vec![]));
+                }
+
+                let mut aux = biguint_config.new_context(region);
+                let ctx = &mut aux;
+                let public_key = RSAPublicKey::new(Value::known(self.n.clone()), RSAPubE::Fix(self.e.clone()));
+                let public_key = config.rsa_config.assign_public_key(ctx, public_key)?;
+                let signature = RSASignature::new(Value::known(self.s.clone()));
+                let signature = config.rsa_config.assign_signature(ctx, signature)?;
+                let result = assign_modpow(&config.rsa_config, ctx, &public_key, &signature, self.params.montgomery_exp)?;
+
+                biguint_config.range().finalize(ctx);
+                CircuitStats {
+                    total_advice: ctx.total_advice,
+                    total_fixed: ctx.total_fixed,
+                    lookup_cells: ctx.cells_to_lookup.len(),
+                }.record();
+                let n_cells = public_key.n.limbs().iter().map(|v| v.cell()).collect::<Vec<Cell>>();
+                let result_cells = result.iter().map(|v| v.cell()).collect::<Vec<Cell>>();
+                Ok((n_cells, result_cells))
+            },
+        )?;
+        for (i, cell) in n_cells.into_iter().enumerate() {
+            layouter.constrain_instance(cell, config.n_instance, i)?;
+        }
+        for (i, cell) in result_cells.into_iter().enumerate() {
+            layouter.constrain_instance(cell, config.result_instance, i)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::load_fixture;
+    use halo2_base::halo2_proofs::{dev::MockProver, halo2curves::bn256::Fr};
+
+    #[test]
+    fn test_rsa_exp_matches_modpow() {
+        let (n, s, _) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let params = CircuitParams::default();
+        for e in [3u32, 65537] {
+            let e = BigUint::from(e);
+            let expected = s.modpow(&e, &n);
+            let circuit = RsaExpCircuit::<Fr>::new(n.clone(), s.clone(), e, params.clone());
+            assert_eq!(circuit.result(), expected);
+            circuit.params.set_current();
+            let instances = vec![modulus_instance(&n, &params), modulus_instance(&expected, &params)];
+            let prover = MockProver::run(params.k, &circuit, instances).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+
+            let wrong = vec![modulus_instance(&n, &params), modulus_instance(&(expected + 1u32), &params)];
+            let prover = MockProver::run(params.k, &circuit, wrong).unwrap();
+            assert!(prover.verify().is_err());
+        }
+    }
+
+    #[test]
+    fn test_montgomery_matches_mul_mod() {
+        let (n, s, _) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let e = BigUint::from(65537u32);
+        let expected = s.modpow(&e, &n);
+        // Both paths are bound to the same result instance, so passing both is agreeing
+        for montgomery_exp in [false, true] {
+            let params = CircuitParams { montgomery_exp, ..Default::default() };
+            let circuit = RsaExpCircuit::<Fr>::new(n.clone(), s.clone(), e.clone(), params.clone());
+            circuit.params.set_current();
+            let instances = vec![modulus_instance(&n, &params), modulus_instance(&expected, &params)];
+            let prover = MockProver::run(params.k, &circuit, instances).unwrap();
+            assert_eq!(prover.verify(), Ok(()), "montgomery_exp: {montgomery_exp}");
+
+            let wrong = vec![modulus_instance(&n, &params), modulus_instance(&(&expected + 1u32), &params)];
+            let prover = MockProver::run(params.k, &circuit, wrong).unwrap();
+            assert!(prover.verify().is_err(), "montgomery_exp: {montgomery_exp}");
+        }
+    }
+}
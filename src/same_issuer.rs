@@ -0,0 +1,221 @@
+//! Proves two TBS certificates name the same issuer without revealing it.
+use halo2_base::{
+    gates::{GateInstructions, RangeInstructions},
+    utils::PrimeField,
+    AssignedValue,
+    Context,
+    QuantumCell::{Constant, Existing},
+    halo2_proofs::{
+        plonk::{Circuit, ConstraintSystem, Error, Column, Instance},
+        circuit::{SimpleFloorPlanner, Layouter, Value, Cell},
+    },
+    SKIP_FIRST_PASS
+};
+use halo2_dynamic_sha256::Sha256DynamicConfig;
+use halo2_rsa::{RSAConfig, RSAInstructions};
+use sha2::{Digest, Sha256};
+
+use crate::der::{self, TAG_SEQUENCE};
+use crate::gadgets::{assign_issuer_location, select_window};
+use crate::{configure_gadgets, CircuitParams, CircuitStats};
+
+/// Index of the issuer Name among the TBS fields (after serialNumber and signature).
+const TBS_ISSUER: usize = 2;
+
+/// `(offset, length)` of the issuer Name TLV in `tbs`.
+pub fn issuer_range(tbs: &[u8]) -> Option<(usize, usize)> {
+    let issuer = der::tbs_fields(tbs)?.get(TBS_ISSUER).copied().filter(|tlv| tlv.tag == TAG_SEQUENCE)?;
+    Some((issuer.start, issuer.end() - issuer.start))
+}
+
+/// Hashes both TBS certificates and constrains their issuer Names (DER, at most `max_issuer_len`
+/// bytes) to be equal. Equal bytes is the same statement as equal issuer hashes, so only one is
+/// hashed; that SHA-256 is the only public input.
+///
+/// The witnessed location of each issuer Name must be the one `assign_issuer_location` walks to,
+/// so both TBS certificates need its layout: v3, a 2-byte long-form length, short-form serialNumber
+/// and signature, and an issuer of at most 255 content bytes. Nothing here authenticates the
+/// certificates themselves: pair it with the certificate circuit when the TBS digests need to be
+/// tied to signatures.
+pub struct SameIssuerCircuit<F: PrimeField> {
+    tbs: [Vec<u8>; 2],
+    /// `issuer_range` of each TBS, the witnessed `(offset, length)`.
+    issuers: [Option<(usize, usize)>; 2],
+    max_issuer_len: usize,
+    params: CircuitParams,
+    _f: std::marker::PhantomData<F>,
+}
+
+impl<F: PrimeField> SameIssuerCircuit<F> {
+    pub fn new(tbs_a: Vec<u8>, tbs_b: Vec<u8>, max_issuer_len: usize, params: CircuitParams) -> Self {
+        let issuers = [issuer_range(&tbs_a), issuer_range(&tbs_b)];
+        Self { tbs: [tbs_a, tbs_b], issuers, max_issuer_len, params, _f: std::marker::PhantomData }
+    }
+
+    /// SHA-256 of the first certificate's issuer Name; `None` if it can't be located.
+    pub fn public_inputs(&self) -> Option<Vec<Vec<F>>> {
+        let (offset, len) = issuer_range(&self.tbs[0])?;
+        let issuer = &self.tbs[0][offset..offset + len];
+        Some(vec![Sha256::digest(issuer).iter().map(|byte| F::from(*byte as u64)).collect()])
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SameIssuerConfig<F: PrimeField> {
+    rsa_config: RSAConfig<F>,
+    sha256_config: Sha256DynamicConfig<F>,
+    issuer_hash_instance: Column<Instance>,
+}
+
+impl<F: PrimeField> SameIssuerConfig<F> {
+    /// Constrains `a[j] == b[j]` for every `j < len`.
+    fn assert_prefix_equal<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        a: &[AssignedValue<'v, F>],
+        b: &[AssignedValue<'v, F>],
+        len: &AssignedValue<'v, F>
+    ) {
+        let range = self.rsa_config.biguint_config().range();
+        let gate = range.gate();
+        let len_bits = (usize::BITS - a.len().leading_zeros()) as usize;
+        for (j, (a, b)) in a.iter().zip(b).enumerate() {
+            let in_prefix = range.is_less_than(ctx, Constant(F::from(j as u64)), Existing(len), len_bits);
+            let is_eq = gate.is_equal(ctx, Existing(a), Existing(b));
+            let past_prefix = gate.not(ctx, Existing(&in_prefix));
+            let ok = gate.or(ctx, Existing(&is_eq), Existing(&past_prefix));
+            gate.assert_is_const(ctx, &ok, F::one());
+        }
+    }
+}
+
+impl<F: PrimeField> Circuit<F> for SameIssuerCircuit<F> {
+    type Config = SameIssuerConfig<F>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        unimplemented!();
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let params = CircuitParams::current();
+        // Both TBS certificates, then the shared issuer
+        let (rsa_config, sha256_config) = configure_gadgets(meta, &params, 3);
+        let issuer_hash_instance = meta.instance_column();
+        meta.enable_equality(issuer_hash_instance);
+
+        Self::Config {
+            rsa_config,
+            sha256_config,
+            issuer_hash_instance
+        }
+    }
+
+    fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+        let biguint_config = config.rsa_config.biguint_config();
+        config.sha256_config.load(&mut layouter)?;
+        biguint_config.range().load_lookup_table(&mut layouter)?;
+        let mut first_pass = SKIP_FIRST_PASS;
+        let issuer_hash_cells = layouter.assign_region(
+            || "same issuer",
+            |region| {
+                if first_pass {
+                    first_pass = false;
+                    return Ok(vec![]);
+                }
+
+                let mut aux = biguint_config.new_context(region);
+                let ctx = &mut aux;
+                let range = biguint_config.range();
+                let gate = range.gate();
+                let mut sha256_config = config.sha256_config.clone();
+
+                let mut issuers = vec![];
+                for (tbs, issuer) in self.tbs.iter().zip(self.issuers) {
+                    let (offset, issuer_len) = issuer.filter(|(_, len)| *len <= self.max_issuer_len).ok_or(Error::Synthesis)?;
+                    let tbs_bytes = sha256_config.digest(ctx, tbs, None)?.input_bytes;
+                    let start = gate.load_witness(ctx, Value::known(F::from(offset as u64)));
+                    let len = gate.load_witness(ctx, Value::known(F::from(issuer_len as u64)));
+                    let (issuer_start, issuer_len_in_tbs) = assign_issuer_location(range, ctx, &tbs_bytes);
+                    gate.assert_equal(ctx, Existing(&start), Existing(&issuer_start));
+                    gate.assert_equal(ctx, Existing(&len), Existing(&issuer_len_in_tbs));
+                    range.check_less_than_safe(ctx, &len, self.max_issuer_len as u64 + 1);
+                    let window = select_window(gate, ctx, &tbs_bytes, &start, self.max_issuer_len);
+                    issuers.push((window, len, offset..offset + issuer_len));
+                }
+                let (issuer_a, len_a, issuer_range_a) = &issuers[0];
+                let (issuer_b, len_b, _) = &issuers[1];
+                gate.assert_equal(ctx, Existing(len_a), Existing(len_b));
+                config.assert_prefix_equal(ctx, issuer_a, issuer_b, len_a);
+
+                // The hashed issuer is the first certificate's
+                let hash_result = sha256_config.digest(ctx, &self.tbs[0][issuer_range_a.clone()], None)?;
+                gate.assert_equal(ctx, Existing(&hash_result.input_len), Existing(len_a));
+                config.assert_prefix_equal(ctx, &hash_result.input_bytes, issuer_a, len_a);
+
+                range.finalize(ctx);
+                CircuitStats {
+                    total_advice: ctx.total_advice,
+                    total_fixed: ctx.total_fixed,
+                    lookup_cells: ctx.cells_to_lookup.len(),
+                }.record();
+                Ok(hash_result.output_bytes.iter().map(|v| v.cell()).collect::<Vec<Cell>>())
+            },
+        )?;
+        for (i, cell) in issuer_hash_cells.into_iter().enumerate() {
+            layouter.constrain_instance(cell, config.issuer_hash_instance, i)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::load_fixture;
+    use halo2_base::halo2_proofs::{dev::MockProver, halo2curves::bn256::Fr};
+
+    fn same_issuer_params() -> CircuitParams {
+        CircuitParams { k: 17, ..Default::default() }
+    }
+
+    #[test]
+    fn test_same_issuer() {
+        // Both are issued by test_ca
+        let (_, _, leaf_tbs) = load_fixture("./certs/san_leaf.pem", "./certs/test_ca.pem");
+        let (_, _, root_tbs) = load_fixture("./certs/test_ca.pem", "./certs/test_ca.pem");
+        let circuit = SameIssuerCircuit::<Fr>::new(leaf_tbs, root_tbs, 256, same_issuer_params());
+        circuit.params.set_current();
+        let prover = MockProver::run(circuit.params.k, &circuit, circuit.public_inputs().unwrap()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_issuer_window_moved_to_signature_algorithm() {
+        // Different issuers, but the same sha256WithRSAEncryption AlgorithmIdentifier
+        let (_, _, leaf_tbs) = load_fixture("./certs/san_leaf.pem", "./certs/test_ca.pem");
+        let (_, _, other_tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let mut circuit = SameIssuerCircuit::<Fr>::new(leaf_tbs, other_tbs, 256, same_issuer_params());
+        circuit.issuers = circuit.tbs.clone().map(|tbs| {
+            let sig_alg = der::tbs_fields(&tbs).unwrap()[TBS_ISSUER - 1];
+            Some((sig_alg.start, sig_alg.end() - sig_alg.start))
+        });
+        let [(a, a_len), (b, b_len)] = circuit.issuers.map(Option::unwrap);
+        assert_eq!(circuit.tbs[0][a..a + a_len], circuit.tbs[1][b..b + b_len]);
+
+        circuit.params.set_current();
+        let sig_alg_hash = Sha256::digest(&circuit.tbs[0][a..a + a_len]).iter().map(|byte| Fr::from(*byte as u64)).collect();
+        let prover = MockProver::run(circuit.params.k, &circuit, vec![sig_alg_hash]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_different_issuers() {
+        let (_, _, leaf_tbs) = load_fixture("./certs/san_leaf.pem", "./certs/test_ca.pem");
+        let (_, _, other_tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let circuit = SameIssuerCircuit::<Fr>::new(leaf_tbs, other_tbs, 256, same_issuer_params());
+        circuit.params.set_current();
+        let prover = MockProver::run(circuit.params.k, &circuit, circuit.public_inputs().unwrap()).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
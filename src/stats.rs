@@ -0,0 +1,131 @@
+use std::cell::{Cell, RefCell};
+
+use halo2_base::{utils::PrimeField, Context};
+
+/// Cell usage of the last `synthesize` on this thread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CircuitStats {
+    pub total_advice: usize,
+    pub total_fixed: usize,
+    pub lookup_cells: usize,
+}
+
+thread_local! {
+    static LAST_STATS: Cell<Option<CircuitStats>> = Cell::new(None);
+    static LAST_COSTS: RefCell<Vec<GadgetCost>> = RefCell::new(vec![]);
+}
+
+impl CircuitStats {
+    /// Keeps `self` for `last`. With the `print-stats` feature, also writes it to stderr: the
+    /// library never writes to stdout.
+    pub(crate) fn record(self) {
+        #[cfg(feature = "print-stats")]
+        eprintln!(
+            "total advice cells: {}, maximum rows used by a fixed column: {}, lookup cells used: {}",
+            self.total_advice,
+            self.total_fixed + 1,
+            self.lookup_cells
+        );
+        LAST_STATS.with(|stats| stats.set(Some(self)));
+    }
+
+    /// Stats of the most recent synthesis (e.g. a `MockProver::run`) on this thread.
+    pub fn last() -> Option<Self> {
+        LAST_STATS.with(|stats| stats.get())
+    }
+}
+
+/// Panics unless every cell count of `stats` is within `budget`, naming the counts over it. For
+/// tests that pin the size of a circuit, so an accidental extra region fails CI rather than
+/// showing up as a slower prover.
+pub fn assert_within_budget(stats: &CircuitStats, budget: &CircuitStats) {
+    let over = [
+        ("total_advice", stats.total_advice, budget.total_advice),
+        ("total_fixed", stats.total_fixed, budget.total_fixed),
+        ("lookup_cells", stats.lookup_cells, budget.lookup_cells),
+    ]
+    .into_iter()
+    .filter(|(_, used, budget)| used > budget)
+    .map(|(name, used, budget)| format!("{name} {used} > {budget}"))
+    .collect::<Vec<_>>();
+    assert!(over.is_empty(), "over budget: {} (measured {stats:?})", over.join(", "));
+}
+
+/// Panics unless every cell count of `stats` is within `tolerance_percent` of `baseline`, either
+/// way, naming the counts that moved. Unlike `assert_within_budget`, a snapshot also flags a
+/// circuit that shrank, so the baseline is updated along with the change that moved it.
+pub fn assert_near_baseline(stats: &CircuitStats, baseline: &CircuitStats, tolerance_percent: usize) {
+    let moved = [
+        ("total_advice", stats.total_advice, baseline.total_advice),
+        ("total_fixed", stats.total_fixed, baseline.total_fixed),
+        ("lookup_cells", stats.lookup_cells, baseline.lookup_cells),
+    ]
+    .into_iter()
+    .filter(|(_, used, baseline)| used.abs_diff(*baseline) * 100 > baseline * tolerance_percent)
+    .map(|(name, used, baseline)| format!("{name} {used} vs {baseline}"))
+    .collect::<Vec<_>>();
+    assert!(moved.is_empty(), "off baseline by more than {tolerance_percent}%: {} (measured {stats:?})", moved.join(", "));
+}
+
+/// Cells one gadget of a verification added to the main context.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GadgetCost {
+    pub gadget: &'static str,
+    pub advice_cells: usize,
+    pub lookup_cells: usize,
+}
+
+impl GadgetCost {
+    pub(crate) fn record_all(costs: Vec<GadgetCost>) {
+        LAST_COSTS.with(|last| *last.borrow_mut() = costs);
+    }
+
+    /// Per-gadget costs of the most recent synthesis on this thread, in the order the gadgets ran.
+    pub fn last() -> Vec<Self> {
+        LAST_COSTS.with(|last| last.borrow().clone())
+    }
+}
+
+/// Splits the cells of a context into `GadgetCost`s by noting the counters after each gadget.
+pub(crate) struct CostTracker {
+    advice_cells: usize,
+    lookup_cells: usize,
+    costs: Vec<GadgetCost>,
+}
+
+impl CostTracker {
+    pub(crate) fn new<F: PrimeField>(ctx: &Context<'_, F>) -> Self {
+        Self { advice_cells: ctx.total_advice, lookup_cells: ctx.cells_to_lookup.len(), costs: vec![] }
+    }
+
+    /// Attributes the cells added since the last call to `gadget`.
+    pub(crate) fn mark<F: PrimeField>(&mut self, ctx: &Context<'_, F>, gadget: &'static str) {
+        let (advice_cells, lookup_cells) = (ctx.total_advice, ctx.cells_to_lookup.len());
+        self.costs.push(GadgetCost {
+            gadget,
+            advice_cells: advice_cells - self.advice_cells,
+            lookup_cells: lookup_cells - self.lookup_cells,
+        });
+        (self.advice_cells, self.lookup_cells) = (advice_cells, lookup_cells);
+    }
+
+    pub(crate) fn finish(self) -> Vec<GadgetCost> {
+        self.costs
+    }
+}
+
+/// Where the cells of a circuit go, see `CertificateVerificationCircuit::cost_report`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CostReport {
+    pub k: u32,
+    pub total: CircuitStats,
+    pub gadgets: Vec<GadgetCost>,
+}
+
+impl CostReport {
+    /// Share of the main context's advice cells that went to `gadget`, 0 for unknown gadgets.
+    pub fn advice_share(&self, gadget: &str) -> f64 {
+        let cells = self.gadgets.iter().filter(|cost| cost.gadget == gadget).map(|cost| cost.advice_cells).sum::<usize>();
+        cells as f64 / self.total.total_advice.max(1) as f64
+    }
+}
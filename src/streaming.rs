@@ -0,0 +1,76 @@
+//! SHA-256 of a signed message that arrives in chunks, e.g. while a large document is read.
+use sha2::{Digest, Sha256};
+
+use crate::CertError;
+
+/// Hashes a message chunk by chunk as it's read, keeping its bytes for the circuit witness.
+/// Fails as soon as the message outgrows `max_len` (normally
+/// `CircuitParams::max_supported_msg_len()`), without reading the rest. Consumed by
+/// `CertificateVerificationCircuit::from_streaming_digest`, which then doesn't hash the message again.
+#[derive(Debug, Clone)]
+pub struct StreamingDigest {
+    hasher: Sha256,
+    msg: Vec<u8>,
+    max_len: usize,
+}
+
+impl StreamingDigest {
+    pub fn new(max_len: usize) -> Self {
+        Self { hasher: Sha256::new(), msg: vec![], max_len }
+    }
+
+    /// Appends `chunk`. On `MessageTooLong`, `len` is the length so far, a lower bound of the whole.
+    pub fn update(&mut self, chunk: &[u8]) -> Result<(), CertError> {
+        let len = self.msg.len() + chunk.len();
+        if len > self.max_len {
+            return Err(CertError::MessageTooLong { len, max: self.max_len });
+        }
+        self.hasher.update(chunk);
+        self.msg.extend_from_slice(chunk);
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.msg.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.msg.is_empty()
+    }
+
+    /// The digest and the bytes of the message.
+    pub fn finalize(self) -> ([u8; 32], Vec<u8>) {
+        (self.hasher.finalize().into(), self.msg)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::load_fixture;
+    use crate::{CertificateVerificationCircuit, CircuitParams};
+    use halo2_base::halo2_proofs::halo2curves::bn256::Fr;
+
+    #[test]
+    fn test_streaming_digest() {
+        let (n_big, sign_big, tbs) = load_fixture("./certs/cert_3.pem", "./certs/cert_2.pem");
+        let params = CircuitParams::default();
+        let mut stream = StreamingDigest::new(params.max_supported_msg_len());
+        for chunk in [&tbs[..100], &tbs[100..900], &tbs[900..]] {
+            stream.update(chunk).unwrap();
+        }
+        assert_eq!(stream.len(), tbs.len());
+        let (digest, msg) = stream.clone().finalize();
+        assert_eq!(digest.as_slice(), Sha256::digest(&tbs).as_slice());
+        assert_eq!(msg, tbs);
+
+        let streamed = CertificateVerificationCircuit::<Fr>::from_streaming_digest(n_big.clone(), sign_big.clone(), stream, params.clone()).unwrap();
+        let one_shot = CertificateVerificationCircuit::<Fr>::new(n_big, sign_big, tbs.clone(), params);
+        assert_eq!(streamed.public_inputs(), one_shot.public_inputs());
+        assert!(streamed.signature_is_valid());
+
+        let mut stream = StreamingDigest::new(1000);
+        stream.update(&tbs[..900]).unwrap();
+        assert_eq!(stream.update(&tbs[900..]), Err(CertError::MessageTooLong { len: tbs.len(), max: 1000 }));
+    }
+}
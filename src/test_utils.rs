@@ -0,0 +1,246 @@
+//! Certificate fixtures for tests, here and in downstream crates (`test-utils` feature).
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+use halo2_base::utils::PrimeField;
+use halo2_rsa::big_uint::decompose_biguint;
+use num_bigint::BigUint;
+use rsa::{Hash, PaddingScheme, PublicKeyParts, RsaPrivateKey, RsaPublicKey};
+use sha2::{Digest, Sha256};
+use x509_parser::{pem::parse_x509_pem, public_key::PublicKey};
+
+use crate::cert::CertParts;
+use crate::der::{children, encode_tlv, read_tlv, rsa_spki_prefix, RSA_SPKI_SUFFIX, TAG_EXTENSIONS, TAG_OID, TAG_SEQUENCE, TAG_SET, TAG_VERSION};
+use crate::{CertificateVerificationCircuit, CircuitParams};
+
+/// Environment variable naming the directory relative fixture paths, such as `./certs/cert_3.pem`,
+/// resolve against, for runs whose working directory isn't the crate root.
+pub const FIXTURE_DIR_VAR: &str = "ZKCERT_FIXTURE_DIR";
+
+/// `path` under `ZKCERT_FIXTURE_DIR` if that is set and `path` is relative, else `path` as given,
+/// relative to the working directory (the crate root under `cargo test`).
+pub fn fixture_path(path: impl AsRef<Path>) -> PathBuf {
+    fixture_path_in(std::env::var_os(FIXTURE_DIR_VAR).as_deref(), path)
+}
+
+/// `fixture_path` with `dir` in place of `ZKCERT_FIXTURE_DIR`.
+pub fn fixture_path_in(dir: Option<&OsStr>, path: impl AsRef<Path>) -> PathBuf {
+    let path = path.as_ref();
+    match dir {
+        Some(dir) if path.is_relative() => Path::new(dir).join(path),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// A certificate to verify: the issuer modulus, the signature and the signed message, with the
+/// public inputs the circuit should expose for them.
+#[derive(Debug, Clone)]
+pub struct CertFixture {
+    pub n_big: BigUint,
+    pub sign_big: BigUint,
+    pub msg: Vec<u8>,
+    pub params: CircuitParams,
+}
+
+impl CertFixture {
+    pub fn new(n_big: BigUint, sign_big: BigUint, msg: Vec<u8>) -> Self {
+        Self { n_big, sign_big, msg, params: CircuitParams::default() }
+    }
+
+    /// The TBS and signature of the PEM certificate at `cert_path` and the modulus of the PEM
+    /// certificate at `issuer_path`, both resolved with `fixture_path`.
+    ///
+    /// # Panics
+    /// If either file is missing or not a certificate, or the issuer key isn't RSA.
+    pub fn load(cert_path: &str, issuer_path: &str) -> Self {
+        let cert_pem = std::fs::read(fixture_path(cert_path)).expect("Failed to read cert PEM");
+        let (_, cert_pem) = parse_x509_pem(&cert_pem).expect("Failed to parse cert PEM");
+        let cert = cert_pem.parse_x509().expect("Failed to parse PEM certificate");
+        let msg = cert.tbs_certificate.as_ref().to_vec();
+        let sign_big = BigUint::from_bytes_be(&cert.signature_value.data);
+
+        let issuer_pem = std::fs::read(fixture_path(issuer_path)).expect("Failed to read issuer cert PEM");
+        let (_, issuer_pem) = parse_x509_pem(&issuer_pem).expect("Failed to parse issuer cert PEM");
+        let issuer = issuer_pem.parse_x509().expect("Failed to parse PEM certificate");
+        let n_big = match issuer.public_key().parsed().expect("Failed to parse issuer key") {
+            PublicKey::RSA(pub_key) => BigUint::from_bytes_be(pub_key.modulus),
+            _ => panic!("Failed to grab modulus. Not RSA"),
+        };
+        Self::new(n_big, sign_big, msg)
+    }
+
+    pub fn with_params(self, params: CircuitParams) -> Self {
+        Self { params, ..self }
+    }
+
+    pub fn circuit<F: PrimeField>(&self) -> CertificateVerificationCircuit<F> {
+        CertificateVerificationCircuit::new(self.n_big.clone(), self.sign_big.clone(), self.msg.as_slice(), self.params.clone())
+    }
+
+    /// SHA-256 of the message.
+    pub fn hashed_msg(&self) -> Vec<u8> {
+        Sha256::digest(&self.msg).to_vec()
+    }
+
+    /// `[modulus limbs, digest bytes]`, computed on each call from the raw inputs rather than by the
+    /// circuit, so tests check the circuit against an independent derivation. Only covers the
+    /// default instance columns.
+    pub fn public_inputs<F: PrimeField>(&self) -> Vec<Vec<F>> {
+        let n_fes = decompose_biguint::<F>(&self.n_big, self.params.num_limbs(), self.params.limb_bits);
+        let hash_fes = self.hashed_msg().iter().map(|byte| F::from(*byte as u64)).collect();
+        vec![n_fes, hash_fes]
+    }
+}
+
+/// Directory of the JSON test vectors `load_test_vector` reads, relative like the other fixtures.
+pub const TEST_VECTOR_DIR: &str = "./tests/fixtures";
+
+/// Loads the test vector `TEST_VECTOR_DIR/<name>.json`: an object with the issuer modulus `n`,
+/// the signature `sign` and the signed message `msg`, each as big-endian hex. Other members, such
+/// as a `description`, are ignored, so adding a vector is a data change.
+///
+/// # Panics
+/// If the file is missing, or a member is missing or not hex.
+pub fn load_test_vector(name: &str) -> CertFixture {
+    let path = fixture_path(format!("{TEST_VECTOR_DIR}/{name}.json"));
+    let json = std::fs::read(&path).unwrap_or_else(|_| panic!("Failed to read test vector {}", path.display()));
+    let vector: serde_json::Value = serde_json::from_slice(&json).expect("Failed to parse test vector JSON");
+    let field = |key: &str| {
+        let hex = vector[key].as_str().unwrap_or_else(|| panic!("test vector {name} has no `{key}` string"));
+        decode_hex(hex).unwrap_or_else(|| panic!("`{key}` of test vector {name} is not hex"))
+    };
+    CertFixture::new(BigUint::from_bytes_be(&field("n")), BigUint::from_bytes_be(&field("sign")), field("msg"))
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok()).collect()
+}
+
+/// Options for `make_cert`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestCertParams {
+    /// Subject (and issuer) commonName.
+    pub common_name: String,
+    /// UTCTime, `YYMMDDHHMMSSZ`.
+    pub not_before: String,
+    pub not_after: String,
+    pub key_bits: usize,
+}
+
+impl Default for TestCertParams {
+    fn default() -> Self {
+        Self {
+            common_name: "zkcert Synthetic".to_string(),
+            not_before: "200101000000Z".to_string(),
+            not_after: "300101000000Z".to_string(),
+            key_bits: 2048,
+        }
+    }
+}
+
+/// sha256WithRSAEncryption AlgorithmIdentifier, NULL parameters included.
+const SHA256_WITH_RSA_ALGORITHM_IDENTIFIER: [u8; 15] =
+    [0x30, 0x0d, 0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0b, 0x05, 0x00];
+
+/// Generates a fresh `key_bits` RSA key and a minimal self-signed v3 certificate for it, without
+/// extensions: the TBS, its sha256WithRSAEncryption signature and the key to verify it with.
+/// Key generation makes this slow for large keys.
+pub fn make_cert(params: TestCertParams) -> (CertParts, RsaPublicKey) {
+    let key = RsaPrivateKey::new(&mut rand::thread_rng(), params.key_bits).expect("Failed to generate RSA key");
+    let public_key = key.to_public_key();
+
+    // Name: SEQUENCE { SET { SEQUENCE { commonName, UTF8String } } }
+    let common_name = [encode_tlv(TAG_OID, &[0x55, 0x04, 0x03]), encode_tlv(0x0c, params.common_name.as_bytes())].concat();
+    let name = encode_tlv(TAG_SEQUENCE, &encode_tlv(TAG_SET, &encode_tlv(TAG_SEQUENCE, &common_name)));
+    let validity = [encode_tlv(0x17, params.not_before.as_bytes()), encode_tlv(0x17, params.not_after.as_bytes())].concat();
+    let spki = [rsa_spki_prefix(params.key_bits), public_key.n().to_bytes_be(), RSA_SPKI_SUFFIX.to_vec()].concat();
+    let tbs_fields = [
+        encode_tlv(TAG_VERSION, &[0x02, 0x01, 0x02]),
+        encode_tlv(0x02, &[0x01]),
+        SHA256_WITH_RSA_ALGORITHM_IDENTIFIER.to_vec(),
+        name.clone(),
+        encode_tlv(TAG_SEQUENCE, &validity),
+        name,
+        spki,
+    ];
+    let tbs = encode_tlv(TAG_SEQUENCE, &tbs_fields.concat());
+
+    let signature = key
+        .sign(PaddingScheme::new_pkcs1v15_sign(Some(Hash::SHA2_256)), &Sha256::digest(&tbs))
+        .expect("Failed to sign TBS");
+    (CertParts { tbs, signature: BigUint::from_bytes_be(&signature) }, public_key)
+}
+
+/// `tbs` re-encoded with the DER of its extensions rearranged by `permute`, e.g.
+/// `|exts| exts.reverse()`. Everything else keeps its bytes, though lengths around the extensions
+/// may change, so the original signature no longer covers the result.
+///
+/// # Panics
+/// If `tbs` doesn't parse or has no extensions.
+pub fn permute_extensions(tbs: &[u8], permute: impl Fn(&mut Vec<Vec<u8>>)) -> Vec<u8> {
+    let outer = read_tlv(tbs, 0).expect("TBS doesn't parse");
+    let fields = children(tbs, &outer).expect("TBS fields don't parse");
+    assert!(fields.iter().any(|field| field.tag == TAG_EXTENSIONS), "TBS has no extensions");
+    let fields = fields
+        .iter()
+        .map(|field| {
+            if field.tag != TAG_EXTENSIONS {
+                return tbs[field.start..field.end()].to_vec();
+            }
+            let seq = read_tlv(tbs, field.content().start).expect("Extensions don't parse");
+            let mut exts = children(tbs, &seq)
+                .expect("Extensions don't parse")
+                .iter()
+                .map(|ext| tbs[ext.start..ext.end()].to_vec())
+                .collect::<Vec<_>>();
+            permute(&mut exts);
+            encode_tlv(TAG_EXTENSIONS, &encode_tlv(TAG_SEQUENCE, &exts.concat()))
+        })
+        .collect::<Vec<_>>();
+    encode_tlv(TAG_SEQUENCE, &fields.concat())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::der;
+    use halo2_base::halo2_proofs::{dev::MockProver, halo2curves::bn256::Fr};
+
+    #[test]
+    fn test_make_cert_verifies() {
+        let (parts, public_key) = make_cert(TestCertParams::default());
+        assert!(der::subject_public_key_info(&parts.tbs).is_some());
+        assert_eq!(der::tbs_fields(&parts.tbs).map(|fields| fields.len()), Some(6));
+
+        let fixture = CertFixture::new(BigUint::from_bytes_be(&public_key.n().to_bytes_be()), parts.signature, parts.tbs);
+        let circuit = fixture.circuit::<Fr>();
+        assert!(circuit.signature_is_valid());
+        circuit.params.set_current();
+        let prover = MockProver::run(circuit.params.k, &circuit, fixture.public_inputs()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_load_test_vector() {
+        let vector = load_test_vector("docusign");
+        let fixture = CertFixture::load("./certs/cert_3.pem", "./certs/cert_2.pem");
+        assert_eq!((&vector.n_big, &vector.sign_big, &vector.msg), (&fixture.n_big, &fixture.sign_big, &fixture.msg));
+        assert_eq!(decode_hex("00ff0A"), Some(vec![0x00, 0xff, 0x0a]));
+        assert_eq!(decode_hex("0"), None);
+        assert_eq!(decode_hex("zz"), None);
+    }
+
+    #[test]
+    fn test_fixture_path_in() {
+        let dir = std::env::current_dir().unwrap().join("certs");
+        assert_eq!(fixture_path_in(Some(dir.as_os_str()), "cert_3.pem"), dir.join("cert_3.pem"));
+        assert_eq!(fixture_path_in(Some(dir.as_os_str()), dir.join("cert_2.pem")), dir.join("cert_2.pem"));
+        assert_eq!(fixture_path_in(None, "./certs/cert_3.pem"), PathBuf::from("./certs/cert_3.pem"));
+
+        let relocated = std::fs::read(fixture_path_in(Some(dir.as_os_str()), "cert_3.pem")).unwrap();
+        assert_eq!(relocated, std::fs::read("./certs/cert_3.pem").unwrap());
+    }
+}
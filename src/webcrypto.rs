@@ -0,0 +1,69 @@
+//! RSA keys and signatures as WebCrypto (`crypto.subtle`) hands them out: standard base64, with
+//! padding, of the `exportKey('spki')` and `sign` ArrayBuffers.
+use data_encoding::BASE64;
+use num_bigint::BigUint;
+
+use crate::cert::spki_rsa_public_key;
+use crate::der::{read_tlv, TAG_SEQUENCE};
+use crate::CertError;
+
+/// Bytes of a standard base64 string. Whitespace, as in a line-wrapped export, is skipped;
+/// base64url is rejected rather than guessed at.
+pub fn decode_base64(value: &str, what: &'static str) -> Result<Vec<u8>, CertError> {
+    let value = value.split_whitespace().collect::<String>();
+    BASE64.decode(value.as_bytes()).map_err(|_| CertError::InvalidBase64(what))
+}
+
+/// Big-endian unsigned integer from standard base64, e.g. a `sign` output.
+pub fn decode_uint(value: &str, what: &'static str) -> Result<BigUint, CertError> {
+    decode_base64(value, what).map(|bytes| BigUint::from_bytes_be(&bytes))
+}
+
+/// `(modulus, exponent)` of an `exportKey('spki')` key, or the modulus alone, without an
+/// exponent, when `key` is the base64 of a bare big-endian modulus. A DER SEQUENCE spanning all
+/// the bytes is read as a SubjectPublicKeyInfo and must be an RSA one.
+pub fn decode_public_key(key: &str) -> Result<(BigUint, Option<BigUint>), CertError> {
+    let bytes = decode_base64(key, "public key")?;
+    match read_tlv(&bytes, 0) {
+        Some(outer) if outer.tag == TAG_SEQUENCE && outer.end() == bytes.len() => {
+            let (n_big, e) = spki_rsa_public_key(&bytes)?;
+            Ok((n_big, Some(e)))
+        }
+        _ => Ok((BigUint::from_bytes_be(&bytes), None)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::{fixture_path, CertFixture};
+    use crate::{CertificateVerificationCircuit, CircuitParams};
+    use data_encoding::BASE64URL;
+    use halo2_base::halo2_proofs::{dev::MockProver, halo2curves::bn256::Fr};
+
+    #[test]
+    fn test_from_webcrypto() {
+        let fixture = CertFixture::load("./certs/cert_3.pem", "./certs/cert_2.pem");
+        // The body of a PUBLIC KEY PEM is the base64 of `exportKey('spki')`
+        let pem = std::fs::read_to_string(fixture_path("./certs/cert_2_pubkey.pem")).unwrap();
+        let spki = pem.lines().filter(|line| !line.starts_with("-----")).collect::<String>();
+        let signature = BASE64.encode(&fixture.sign_big.to_bytes_be());
+
+        assert_eq!(decode_public_key(&spki), Ok((fixture.n_big.clone(), Some(BigUint::from(65537u32)))));
+        let modulus = BASE64.encode(&fixture.n_big.to_bytes_be());
+        assert_eq!(decode_public_key(&modulus), Ok((fixture.n_big.clone(), None)));
+
+        let circuit = CertificateVerificationCircuit::<Fr>::from_webcrypto(&spki, &signature, &fixture.msg, CircuitParams::default()).unwrap();
+        assert!(circuit.signature_is_valid());
+        circuit.params.set_current();
+        let prover = MockProver::run(circuit.params.k, &circuit, fixture.public_inputs()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // This signature's base64url has `_`, which standard base64 doesn't
+        let url_signature = BASE64URL.encode(&fixture.sign_big.to_bytes_be());
+        assert_eq!(
+            CertificateVerificationCircuit::<Fr>::from_webcrypto(&spki, &url_signature, &fixture.msg, CircuitParams::default()).err(),
+            Some(CertError::InvalidBase64("signature"))
+        );
+    }
+}